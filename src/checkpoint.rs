@@ -0,0 +1,231 @@
+use serde_json;
+use serde_json::json;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Where a split into numbered files can go on from after it was stopped,
+/// taken between two statements or values tuples of the dump.
+#[derive(Debug,Default,Clone,PartialEq)]
+pub struct Checkpoint {
+    // arguments of the split, a resumed one has to have the same
+    pub arguments: Vec<String>,
+    // bytes of the dump read
+    pub offset: u64,
+    // file being written and its size
+    pub part: usize,
+    pub size: u64,
+    // bytes of the file the splitter counted to its limit
+    pub total_bytes: usize,
+    // head of the insert or `COPY` being written
+    pub insert: Vec<u8>,
+    pub delimiter: Vec<u8>,
+    pub preamble: Vec<u8>,
+    pub envelope: Option<Vec<u8>>,
+    pub started: bool,
+    pub rows: bool,
+    pub in_values: bool,
+    pub skipping: bool,
+    pub copy_open: bool,
+    // reading the rows of a `COPY`, and inside a `/*! .. */` comment
+    pub copying: bool,
+    pub conditional: bool,
+}
+
+pub const FILE: &str = "checkpoint.json";
+
+// the text of a statement head, or its bytes when it isn't UTF-8
+fn text(bytes: &[u8]) -> Value {
+    match ::std::str::from_utf8(bytes) {
+        Ok(text) => json!(text),
+        Err(_) => json!(bytes),
+    }
+}
+
+fn bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(text) => Some(text.as_bytes().to_vec()),
+        Value::Array(bytes) => bytes.iter().map(|byte| byte.as_u64().filter(|byte| *byte <= 0xff).map(|byte| byte as u8)).collect(),
+        _ => None,
+    }
+}
+
+impl Checkpoint {
+    pub fn json(&self) -> String {
+        let checkpoint = json!({
+            "arguments": self.arguments,
+            "offset": self.offset,
+            "part": self.part,
+            "size": self.size,
+            "total_bytes": self.total_bytes,
+            "insert": text(&self.insert),
+            "delimiter": text(&self.delimiter),
+            "preamble": text(&self.preamble),
+            "envelope": self.envelope.as_ref().map(|envelope| text(envelope)),
+            "started": self.started,
+            "rows": self.rows,
+            "in_values": self.in_values,
+            "skipping": self.skipping,
+            "copy_open": self.copy_open,
+            "copying": self.copying,
+            "conditional": self.conditional,
+        });
+        serde_json::to_string_pretty(&checkpoint).unwrap_or_default()
+    }
+
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let invalid = || "checkpoint.json is invalid".to_string();
+        let value: Value = serde_json::from_str(json).map_err(|_| invalid())?;
+        let number = |key: &str| value[key].as_u64().ok_or_else(invalid);
+        let flag = |key: &str| value[key].as_bool().ok_or_else(invalid);
+        let bytes = |key: &str| bytes(&value[key]).ok_or_else(invalid);
+
+        let arguments = value["arguments"].as_array().ok_or_else(invalid)?.iter()
+            .map(|argument| argument.as_str().map(String::from).ok_or_else(invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Checkpoint {
+            arguments,
+            offset: number("offset")?,
+            part: number("part")? as usize,
+            size: number("size")?,
+            total_bytes: number("total_bytes")? as usize,
+            insert: bytes("insert")?,
+            delimiter: bytes("delimiter")?,
+            preamble: bytes("preamble")?,
+            envelope: match value["envelope"] {
+                Value::Null => None,
+                ref envelope => Some(self::bytes(envelope).ok_or_else(invalid)?),
+            },
+            started: flag("started")?,
+            rows: flag("rows")?,
+            in_values: flag("in_values")?,
+            skipping: flag("skipping")?,
+            copy_open: flag("copy_open")?,
+            copying: flag("copying")?,
+            conditional: flag("conditional")?,
+        })
+    }
+
+    /// Writes `checkpoint.json` in place of the last one, so a stop
+    /// in the middle leaves the last one whole.
+    pub fn save(&self) -> io::Result<()> {
+        let next = format!("{}.next", FILE);
+        fs::write(&next, self.json())?;
+        fs::rename(&next, FILE)
+    }
+
+    pub fn load() -> Result<Self, String> {
+        if !Path::new(FILE).exists() {
+            return Err("There is no checkpoint.json to resume from".to_string())
+        }
+        let json = fs::read_to_string(FILE).map_err(|_| "Unable to read checkpoint.json".to_string())?;
+        Self::parse(&json)
+    }
+}
+
+
+#[cfg(test)]
+mod checkpoint_test{
+    use super::*;
+    use splitter::{Splitter, SplitterSettings, SplitterState, FileState, Dialect};
+    use splitter::splitter_test::settings;
+    use std::io::Cursor;
+
+    #[test]
+    fn json_round_trip(){
+        let checkpoint = Checkpoint {
+            arguments: vec!["dump.sql".to_string(), "--output=1gb".to_string()],
+            offset: 1 << 40,
+            part: 12,
+            size: 1024,
+            total_bytes: 1024,
+            insert: b"INSERT INTO `t` VALUES ".to_vec(),
+            delimiter: b";;".to_vec(),
+            envelope: Some(b"PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;".to_vec()),
+            in_values: true,
+            rows: true,
+            ..Checkpoint::default()
+        };
+        assert_eq!(Checkpoint::parse(&checkpoint.json()).unwrap(), checkpoint);
+
+        // an insert head in latin1 is kept byte for byte
+        let binary = Checkpoint {
+            insert: b"INSERT INTO `caf\xe9` VALUES ".to_vec(),
+            preamble: b"SET NAMES latin1;\n".to_vec(),
+            envelope: Some(vec![0xff, b';']),
+            ..Checkpoint::default()
+        };
+        assert_eq!(Checkpoint::parse(&binary.json()).unwrap(), binary);
+        assert!(Checkpoint::parse("{}").is_err());
+    }
+
+    type Files = Vec<Vec<u8>>;
+
+    // the files of a split, from the checkpoint when one is given, and the
+    // checkpoints it passed with the files as they were at each of them
+    fn split(dump: &[u8], dialect: Dialect, resumed: Option<&Checkpoint>, mut files: Files) -> (Files, Vec<(Checkpoint, Files)>) {
+        let mut file = Cursor::new(dump.to_vec());
+        file.set_position(resumed.map_or(0, |checkpoint| checkpoint.offset));
+        let mut splitter = Splitter::new(SplitterSettings { write: 60, file, dialect, ..settings("") });
+        if let Some(checkpoint) = resumed {
+            splitter.resume(checkpoint);
+        }
+
+        let mut checkpoints = vec![];
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(_, file_state, tokens) => {
+                    if files.is_empty() || file_state == FileState::New {
+                        files.push(vec![]);
+                    }
+                    files.last_mut().unwrap().extend(tokens);
+                    if let Some(mut checkpoint) = splitter.checkpoint() {
+                        checkpoint.part = files.len();
+                        checkpoint.size = files.last().unwrap().len() as u64;
+                        checkpoints.push((checkpoint, files.clone()));
+                    }
+                },
+                SplitterState::Done => break,
+                _ => panic!("split failed"),
+            }
+        }
+        (files, checkpoints)
+    }
+
+    // resumed from every checkpoint, the files come out the same
+    fn resumes(dump: &[u8], dialect: Dialect) {
+        let (files, checkpoints) = split(dump, dialect, None, vec![]);
+        assert!(files.len() > 2 && checkpoints.len() > 2);
+        for (checkpoint, written) in checkpoints {
+            let checkpoint = Checkpoint::parse(&checkpoint.json()).unwrap();
+            let mut written = written[..checkpoint.part].to_vec();
+            if let Some(last) = written.last_mut() {
+                last.truncate(checkpoint.size as usize);
+            }
+            assert_eq!(split(dump, dialect, Some(&checkpoint), written).0, files, "resumed at {}", checkpoint.offset);
+        }
+    }
+
+    #[test]
+    fn resumes_inserts_and_copy(){
+        resumes(b"DELIMITER ;;\nCREATE TRIGGER t1 BEFORE INSERT ON t FOR EACH ROW SET NEW.a = 1;;\n\
+            INSERT INTO `t` VALUES (1,'a'),\n(2,'b'),\n(3,'c'),\n(4,'d');;\nDELIMITER ;\n\
+            INSERT INTO `u` VALUES (5),(6),(7);\n", Dialect::Mysql);
+        resumes(b"CREATE TABLE public.t (a integer);\n\nCOPY public.t (a) FROM stdin;\n1\n2\n3\n4\n5\n6\n7\n8\n\\.\n\n\
+            COPY public.u (a) FROM stdin;\n9\n10\n\\.\n", Dialect::Postgres);
+    }
+
+    #[test]
+    fn resumes_sqlite_transaction(){
+        resumes(b"PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE t(a);\n\
+            INSERT INTO t VALUES(1);\nINSERT INTO t VALUES(2);\nINSERT INTO t VALUES(3);\n\
+            INSERT INTO t VALUES(4);\nINSERT INTO t VALUES(5);\nCOMMIT;\n", Dialect::Sqlite);
+    }
+
+    #[test]
+    fn resumes_latin1_insert(){
+        resumes(b"SET NAMES latin1;\nINSERT INTO `caf\xe9` VALUES (1,'cr\xe8me'),\n(2,'br\xfbl\xe9e'),\n(3,'a'),\n(4,'b'),\n(5,'c');\n\
+            INSERT INTO `caf\xe9` VALUES (6,'d'),(7,'e');\n", Dialect::Mysql);
+    }
+}
@@ -1,8 +1,27 @@
 use clap::App;
-use std::fs::File;
+use clap::ArgMatches;
 use std::result::Result;
 use std::str;
 use std::path::Path;
+use std::path::PathBuf;
+use sql_split::filter::TableFilter;
+use sql_split::splitter::Mode;
+use sql_split::splitter::Layout;
+use sql_split::splitter::Dialect;
+use sql_split::splitter::Format;
+use sql_split::delimited::Delimited;
+use sql_split::mask::Masks;
+use sql_split::predicate::RowFilter;
+use sql_split::subset::Subset;
+use sql_split::sample::Sampling;
+use sql_split::rename::Renames;
+use sql_split::rewrite::Rewrites;
+use sql_split::rewrite::Definer;
+use sql_split::input::Input;
+use sql_split::merge;
+use sql_split::index::Pick;
+use std::fs;
+use std::env;
 
 fn parse_size(input: Option<&str>, arg_name: &str) -> Result<usize, String> {
     match input {
@@ -43,24 +62,344 @@ fn parse_size(input: Option<&str>, arg_name: &str) -> Result<usize, String> {
 }
 
 
-pub fn args() -> (Result<File, &'static str>, Result<usize, String>) {
-    let yaml = load_yaml!("../cli.yml");
-    let matches = App::from_yaml(yaml).get_matches();
-    let write_buffer = matches.value_of("OUTPUT_SIZE");
-    let file = match matches.value_of("INPUT") {
+pub struct Args {
+    pub file: Input,
+    // path of the dump as given
+    pub source: String,
+    pub write: usize,
+    pub filter: TableFilter,
+    pub mode: Mode,
+    pub layout: Layout,
+    pub masks: Masks,
+    pub row_filter: RowFilter,
+    pub subset: Subset,
+    pub sampling: Sampling,
+    pub renames: Renames,
+    pub rewrites: Rewrites,
+    pub to: Dialect,
+    pub sqlite: Option<String>,
+    pub format: Format,
+    pub delimited: Delimited,
+    pub strict: bool,
+    pub dialect: Dialect,
+    pub manifest: bool,
+    // bytes of the dump read between two checkpoints
+    pub checkpoint: Option<usize>,
+    pub resume: bool,
+    // arguments of the split, without `--resume`
+    pub arguments: Vec<String>,
+}
+
+fn open_file(input: Option<&str>) -> Result<Input, String> {
+    match input {
         Some(file) => {
             let path = Path::new(file);
-            if path.exists(){
-                match File::open(path) {
+            if path.is_dir(){
+                Input::open(path).map_err(|e| format!("Unable to read the dump directory: {}", e))
+            }else if path.exists(){
+                match Input::open(path) {
                     Ok(file) => Ok(file),
-                    Err(_) => Err("Unable to open file"),
+                    Err(_) => Err("Unable to open file".to_string()),
                 }
             }else{
-                Err("File path is invalid")
+                Err("File path is invalid".to_string())
+            }
+        },
+        None => Err("File name is missing".to_string()),
+    }
+}
+
+fn masks(input: Option<&str>) -> Result<Masks, String> {
+    match input {
+        Some(path) => {
+            match fs::read_to_string(path) {
+                Ok(config) => Masks::parse(&config),
+                Err(_) => Err("Unable to read mask config".to_string()),
             }
         },
-        None => Err("File name is missing"),
+        None => Ok(Masks::default()),
+    }
+}
+
+/// `merge` joins the chunks of a split back into one dump.
+pub struct MergeArgs {
+    pub chunks: Vec<PathBuf>,
+    pub output: String,
+    pub dialect: Dialect,
+}
+
+/// `verify` compares a dump with the chunks of its split.
+pub struct VerifyArgs {
+    pub file: Input,
+    pub chunks: Vec<PathBuf>,
+    pub dialect: Dialect,
+}
+
+/// `index` writes the statement index of a dump.
+pub struct IndexArgs {
+    pub file: Input,
+    pub source: String,
+    pub index: String,
+    pub dialect: Dialect,
+}
+
+/// `extract` writes statements out of a dump with its index.
+pub struct ExtractArgs {
+    pub file: Input,
+    pub index: String,
+    pub pick: Pick,
+}
+
+pub enum Command {
+    Split(Box<Args>),
+    Merge(MergeArgs),
+    Verify(VerifyArgs),
+    Index(IndexArgs),
+    Extract(ExtractArgs),
+}
+
+/// Arguments that can't be run, split ones exit with 0 and subcommand ones with 1.
+pub enum Invalid {
+    Split(String),
+    Subcommand(String),
+}
+
+impl From<String> for Invalid {
+    fn from(err: String) -> Invalid {
+        Invalid::Split(err)
+    }
+}
+
+fn dialect(matches: &ArgMatches) -> Dialect {
+    match matches.value_of("DIALECT") {
+        Some("postgres") => Dialect::Postgres,
+        Some("sqlite") => Dialect::Sqlite,
+        _ => Dialect::Mysql,
+    }
+}
+
+fn chunks(matches: &ArgMatches) -> Result<Vec<PathBuf>, String> {
+    let directory = Path::new(matches.value_of("CHUNKS").unwrap_or_default());
+    if !directory.is_dir() {
+        return Err("The chunks should be a directory".to_string())
+    }
+    merge::chunks(directory).map_err(|e| e.text.to_string())
+}
+
+fn merge_args(matches: &ArgMatches) -> Result<MergeArgs, String> {
+    Ok(MergeArgs {
+        chunks: chunks(matches)?,
+        output: matches.value_of("OUTPUT").unwrap_or_default().to_string(),
+        dialect: dialect(matches),
+    })
+}
+
+fn verify_args(matches: &ArgMatches) -> Result<VerifyArgs, String> {
+    Ok(VerifyArgs {
+        file: open_file(matches.value_of("INPUT"))?,
+        chunks: chunks(matches)?,
+        dialect: dialect(matches),
+    })
+}
+
+// the dump with `.index` after it, next to a directory for mydumper ones
+fn index_path(matches: &ArgMatches) -> String {
+    match matches.value_of("INDEX") {
+        Some(index) => index.to_string(),
+        None => format!("{}.index", matches.value_of("INPUT").unwrap_or_default().trim_end_matches('/')),
+    }
+}
+
+fn index_args(matches: &ArgMatches) -> Result<IndexArgs, String> {
+    Ok(IndexArgs {
+        file: open_file(matches.value_of("INPUT"))?,
+        source: matches.value_of("INPUT").unwrap_or_default().to_string(),
+        index: index_path(matches),
+        dialect: dialect(matches),
+    })
+}
+
+fn extract_args(matches: &ArgMatches) -> Result<ExtractArgs, String> {
+    let pick = match matches.value_of("STATEMENT") {
+        Some(number) => match number.parse::<u64>() {
+            Ok(number) if number > 0 => Pick::Statement(number),
+            _ => return Err("statement should be a number from 1".to_string()),
+        },
+        None => Pick::Tables(TableFilter::new(TableFilter::patterns(matches.value_of("TABLES")), vec![])),
+    };
+    Ok(ExtractArgs {
+        file: open_file(matches.value_of("INPUT"))?,
+        index: index_path(matches),
+        pick,
+    })
+}
+
+pub fn command() -> Result<Command, Invalid> {
+    let yaml = load_yaml!("../cli.yml");
+    let matches = App::from_yaml(yaml).get_matches();
+
+    match matches.subcommand() {
+        ("merge", Some(merge)) => Ok(Command::Merge(merge_args(merge).map_err(Invalid::Subcommand)?)),
+        ("verify", Some(verify)) => Ok(Command::Verify(verify_args(verify).map_err(Invalid::Subcommand)?)),
+        ("index", Some(index)) => Ok(Command::Index(index_args(index).map_err(Invalid::Subcommand)?)),
+        ("extract", Some(extract)) => Ok(Command::Extract(extract_args(extract).map_err(Invalid::Subcommand)?)),
+        _ => Ok(Command::Split(Box::new(args(&matches)?))),
+    }
+}
+
+fn args(matches: &ArgMatches) -> Result<Args, String> {
+    let mode = if matches.is_present("SCHEMA_ONLY") {
+        Mode::SchemaOnly
+    }else if matches.is_present("DATA_ONLY") {
+        Mode::DataOnly
+    }else{
+        Mode::All
+    };
+
+    let layout = match matches.value_of("LAYOUT") {
+        Some("mydumper") => Layout::Mydumper,
+        _ => Layout::Chunks,
+    };
+
+    let seed = match matches.value_of("SEED") {
+        Some(seed) => match seed.parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => return Err("seed should be a positive number".to_string()),
+        },
+        None => 0,
     };
 
-    (file, parse_size(write_buffer, "output-size"))
-}
\ No newline at end of file
+    let mut subset = Subset::parse(&matches.values_of("SUBSET").map_or(vec![], |values| values.collect()))?;
+    subset.seed = seed;
+
+    let sqlite = matches.value_of("SQLITE").map(String::from);
+    let to = match matches.value_of("TO") {
+        Some("postgres") if sqlite.is_some() => return Err("--sqlite can't write postgres".to_string()),
+        Some("postgres") => Dialect::Postgres,
+        Some("sqlite") => Dialect::Sqlite,
+        _ if sqlite.is_some() => Dialect::Sqlite,
+        _ => Dialect::Mysql,
+    };
+
+    let format = match matches.value_of("FORMAT") {
+        Some("csv") => Format::Csv,
+        Some("tsv") => Format::Tsv,
+        Some("jsonl") => Format::Jsonl,
+        Some("parquet") => Format::Parquet,
+        _ => Format::Sql,
+    };
+
+    if matches.is_present("LOAD_DATA") && format != Format::Csv && format != Format::Tsv {
+        return Err("--load-data needs --format=csv or --format=tsv".to_string())
+    }
+
+    // `LOAD DATA` tells NULL from a string only by its own marker
+    let native = if format == Format::Csv { "NULL" } else { "\\N" };
+    if matches.is_present("LOAD_DATA") && matches.value_of("NULL").is_some_and(|null| null != native) {
+        return Err(format!("--load-data reads NULL back only when written as {}", native))
+    }
+
+    if matches.is_present("MANIFEST") && sqlite.is_some() {
+        return Err("--manifest lists the files of a split, --sqlite writes none".to_string())
+    }
+
+    let checkpoint = match matches.value_of("CHECKPOINT") {
+        Some(size) => Some(parse_size(Some(size), "checkpoint")?),
+        None => None,
+    };
+
+    if matches.is_present("RESUME") && checkpoint.is_none() {
+        return Err("--resume needs the --checkpoint the split was started with".to_string())
+    }
+
+    // the state of the other ones isn't kept in the checkpoint
+    if checkpoint.is_some() {
+        let stateful = ["MASK_CONFIG", "WHERE", "SUBSET", "SAMPLE", "SAMPLE_ROWS", "TO", "SQLITE", "MANIFEST"];
+        if stateful.iter().any(|arg| matches.is_present(arg)) || layout != Layout::Chunks || format != Format::Sql {
+            return Err("--checkpoint resumes splits into numbered files, filtered by table and renamed at most".to_string())
+        }
+    }
+
+    if matches.is_present("STRICT") && format != Format::Parquet {
+        return Err("--strict needs --format=parquet".to_string())
+    }
+
+    let dialect = dialect(matches);
+
+    // rows and statements are only understood in MySQL
+    if dialect != Dialect::Mysql {
+        let rewriting = [
+            "MASK_CONFIG", "WHERE", "SUBSET", "SAMPLE", "SAMPLE_ROWS", "RENAME_TABLE", "RENAME_SCHEMA",
+            "TABLE_PREFIX", "STRIP_DEFINER", "DEFINER", "STRIP_USE", "STRIP_CREATE_DATABASE", "ENGINE",
+            "CHARSET", "COLLATION", "STRIP_AUTO_INCREMENT", "STRIP_ROW_FORMAT", "TO", "SQLITE", "FORMAT",
+        ];
+        if rewriting.iter().any(|arg| matches.is_present(arg)) {
+            return Err(format!("a --dialect={} dump can only be split, filtered by table and laid out", matches.value_of("DIALECT").unwrap_or_default()))
+        }
+    }
+
+    // one file, so nothing to split
+    let write = match matches.value_of("OUTPUT_SIZE") {
+        None if sqlite.is_some() => usize::MAX,
+        size => parse_size(size, "output-size")?,
+    };
+
+    let definer = if matches.is_present("STRIP_DEFINER") {
+        Definer::Strip
+    }else{
+        match matches.value_of("DEFINER") {
+            Some(definer) => Definer::parse(definer)?,
+            None => Definer::Keep,
+        }
+    };
+
+    Ok(Args {
+        file: open_file(matches.value_of("INPUT"))?,
+        source: matches.value_of("INPUT").unwrap_or_default().to_string(),
+        write,
+        filter: TableFilter::new(
+            TableFilter::patterns(matches.value_of("TABLES")),
+            TableFilter::patterns(matches.value_of("EXCLUDE_TABLES")),
+        ),
+        mode,
+        layout,
+        masks: masks(matches.value_of("MASK_CONFIG"))?,
+        row_filter: RowFilter::parse(&matches.values_of("WHERE").map_or(vec![], |values| values.collect()))?,
+        subset,
+        sampling: Sampling::parse(
+            &matches.values_of("SAMPLE").map_or(vec![], |values| values.collect()),
+            &matches.values_of("SAMPLE_ROWS").map_or(vec![], |values| values.collect()),
+            seed,
+        )?,
+        renames: Renames::parse(
+            &matches.values_of("RENAME_TABLE").map_or(vec![], |values| values.collect()),
+            &matches.values_of("RENAME_SCHEMA").map_or(vec![], |values| values.collect()),
+            matches.value_of("TABLE_PREFIX"),
+        )?,
+        rewrites: Rewrites {
+            definer,
+            strip_use: matches.is_present("STRIP_USE"),
+            strip_create_database: matches.is_present("STRIP_CREATE_DATABASE"),
+            engine: matches.value_of("ENGINE").map(String::from),
+            charset: matches.value_of("CHARSET").map(String::from),
+            collation: matches.value_of("COLLATION").map(String::from),
+            strip_auto_increment: matches.is_present("STRIP_AUTO_INCREMENT"),
+            strip_row_format: matches.is_present("STRIP_ROW_FORMAT"),
+        },
+        to,
+        sqlite,
+        format,
+        delimited: Delimited::new(
+            format,
+            matches.is_present("HEADER"),
+            matches.value_of("NULL"),
+            matches.is_present("LOAD_DATA"),
+        ),
+        strict: matches.is_present("STRICT"),
+        dialect,
+        manifest: matches.is_present("MANIFEST"),
+        checkpoint,
+        resume: matches.is_present("RESUME"),
+        arguments: env::args().skip(1).filter(|argument| argument != "--resume").collect(),
+    })
+}
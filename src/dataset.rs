@@ -0,0 +1,586 @@
+use statement::Column;
+use value::Value;
+use value::Fields;
+use postgres::binary;
+use std::str;
+
+/// Arrow type of a column of `--format=parquet`, from its type in `CREATE TABLE`.
+/// Columns of tables without one are text.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum ColumnType {
+    Boolean,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
+    // precision and scale
+    Decimal(u8, i8),
+    Date,
+    // micro seconds, `timestamp` columns are in UTC
+    Timestamp(bool),
+    Utf8,
+    Binary,
+}
+
+/// A column of the dataset of a table.
+#[derive(Debug,PartialEq,Clone)]
+pub struct Field {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+impl Field {
+    /// The field of a column, NOT NULL columns only stay that way with `--strict`.
+    pub fn new(column: &Column, strict: bool) -> Self {
+        Field {
+            name: column.name.clone(),
+            column_type: column_type(column),
+            nullable: column.nullable || !strict,
+        }
+    }
+
+    // a column the dump doesn't say more about
+    pub fn text(name: &str) -> Self {
+        Field { name: name.to_string(), column_type: ColumnType::Utf8, nullable: true }
+    }
+}
+
+pub fn column_type(column: &Column) -> ColumnType {
+    let data_type = &column.data_type;
+    let unsigned = data_type.unsigned;
+    match data_type.name.as_str() {
+        "bool" | "boolean" => ColumnType::Boolean,
+        "tinyint" if unsigned => ColumnType::UInt8,
+        "tinyint" => ColumnType::Int8,
+        "smallint" if unsigned => ColumnType::UInt16,
+        "smallint" | "year" => ColumnType::Int16,
+        "mediumint" | "int" | "integer" if unsigned => ColumnType::UInt32,
+        "mediumint" | "int" | "integer" => ColumnType::Int32,
+        "bigint" if unsigned => ColumnType::UInt64,
+        "bigint" => ColumnType::Int64,
+        "bit" => ColumnType::UInt64,
+        "float" => ColumnType::Float32,
+        "double" | "real" => ColumnType::Float64,
+        "decimal" | "numeric" | "dec" | "fixed" => {
+            let precision = data_type.args.first().and_then(|arg| arg.trim().parse::<u8>().ok()).unwrap_or(10);
+            let scale = data_type.args.get(1).and_then(|arg| arg.trim().parse::<i8>().ok()).unwrap_or(0);
+            // wider than a 128 bit decimal holds
+            if precision == 0 || precision > 38 || scale < 0 || scale as u8 > precision {
+                ColumnType::Utf8
+            }else{
+                ColumnType::Decimal(precision, scale)
+            }
+        },
+        "date" => ColumnType::Date,
+        "datetime" => ColumnType::Timestamp(false),
+        "timestamp" => ColumnType::Timestamp(true),
+        _ if binary(data_type) => ColumnType::Binary,
+        _ => ColumnType::Utf8,
+    }
+}
+
+/// A value as its column has it.
+#[derive(Debug,PartialEq,Clone)]
+pub enum Cell {
+    Null,
+    Boolean(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Decimal(i128),
+    // days since 1970-01-01
+    Date(i32),
+    // micro seconds since 1970-01-01 00:00:00
+    Timestamp(i64),
+    Utf8(String),
+    Binary(Vec<u8>),
+}
+
+// an integer value, decimals only when they have no fraction
+fn integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Int(value) => Some(i128::from(*value)),
+        Value::Bit(value) => Some(i128::from(*value)),
+        Value::Float(value) if value.fract() == 0.0 && value.abs() < 1e38 => Some(*value as i128),
+        Value::Decimal(text) => text.trim().parse::<i128>().ok(),
+        Value::String(text) => str::from_utf8(text).ok()?.trim().parse::<i128>().ok(),
+        _ => None,
+    }
+}
+
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Bit(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        Value::Decimal(text) => text.trim().parse::<f64>().ok(),
+        Value::String(text) => str::from_utf8(text).ok()?.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+// `-12.50` as 1250 for a scale of 2, None when it has more digits than the column
+fn decimal(text: &str, precision: u8, scale: i8) -> Option<i128> {
+    let text = text.trim();
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (whole, fraction) = match digits.find('.') {
+        Some(dot) => (&digits[..dot], &digits[dot + 1..]),
+        None => (digits, ""),
+    };
+    let scale = scale as usize;
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > scale ||
+        !whole.bytes().chain(fraction.bytes()).all(|byte| byte.is_ascii_digit()) {
+        return None
+    }
+
+    let whole = whole.trim_start_matches('0');
+    if whole.len() + scale > precision as usize {
+        return None
+    }
+
+    let mut number: i128 = 0;
+    for byte in whole.bytes().chain(fraction.bytes()) {
+        number = number * 10 + i128::from(byte - b'0');
+    }
+    for _ in fraction.len()..scale {
+        number *= 10;
+    }
+    Some(if negative { -number } else { number })
+}
+
+// days from 1970-01-01 to a day of the proleptic gregorian calendar
+fn days(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn number(text: &str, length: usize) -> Option<i64> {
+    if text.len() != length || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None
+    }
+    text.parse().ok()
+}
+
+// `2024-02-29`
+fn date(text: &str) -> Option<i64> {
+    let mut parts = text.split('-');
+    let year = number(parts.next()?, 4)?;
+    let month = number(parts.next()?, 2)?;
+    let day = number(parts.next()?, 2)?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if parts.next().is_some() || day < 1 || day > month_days {
+        return None
+    }
+    Some(days(year, month, day))
+}
+
+// `2024-02-29 13:45:00.25`, a date alone is its midnight
+fn timestamp(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (day, time) = match text.find([' ', 'T']) {
+        Some(at) => (&text[..at], &text[at + 1..]),
+        None => (text, "00:00:00"),
+    };
+    let (time, fraction) = match time.find('.') {
+        Some(dot) => (&time[..dot], &time[dot + 1..]),
+        None => (time, ""),
+    };
+
+    let mut parts = time.split(':');
+    let hour = number(parts.next()?, 2)?;
+    let minute = number(parts.next()?, 2)?;
+    let second = number(parts.next()?, 2)?;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 59 || fraction.len() > 6 {
+        return None
+    }
+    let micros = if fraction.is_empty() {
+        0
+    }else{
+        number(fraction, fraction.len())? * 10i64.pow(6 - fraction.len() as u32)
+    };
+
+    Some((date(day)? * 86_400 + hour * 3_600 + minute * 60 + second) * 1_000_000 + micros)
+}
+
+/// A value as the type of its column has it, None when it doesn't fit.
+pub fn cell(value: &Value, column_type: ColumnType) -> Option<Cell> {
+    let text = match value.text() {
+        Some(text) => text,
+        None => return Some(Cell::Null),
+    };
+    let utf8 = || str::from_utf8(&text).ok().map(str::trim);
+
+    let bounded = |min: i128, max: i128| integer(value).filter(|number| *number >= min && *number <= max);
+    match column_type {
+        ColumnType::Boolean => integer(value).map(|number| Cell::Boolean(number != 0)),
+        ColumnType::Int8 => bounded(i8::MIN.into(), i8::MAX.into()).map(|number| Cell::Int(number as i64)),
+        ColumnType::Int16 => bounded(i16::MIN.into(), i16::MAX.into()).map(|number| Cell::Int(number as i64)),
+        ColumnType::Int32 => bounded(i32::MIN.into(), i32::MAX.into()).map(|number| Cell::Int(number as i64)),
+        ColumnType::Int64 => bounded(i64::MIN.into(), i64::MAX.into()).map(|number| Cell::Int(number as i64)),
+        ColumnType::UInt8 => bounded(0, u8::MAX.into()).map(|number| Cell::UInt(number as u64)),
+        ColumnType::UInt16 => bounded(0, u16::MAX.into()).map(|number| Cell::UInt(number as u64)),
+        ColumnType::UInt32 => bounded(0, u32::MAX.into()).map(|number| Cell::UInt(number as u64)),
+        ColumnType::UInt64 => bounded(0, u64::MAX.into()).map(|number| Cell::UInt(number as u64)),
+        ColumnType::Float32 |
+        ColumnType::Float64 => float(value).map(Cell::Float),
+        ColumnType::Decimal(precision, scale) => match value {
+            Value::Int(_) | Value::Decimal(_) | Value::String(_) => decimal(utf8()?, precision, scale).map(Cell::Decimal),
+            _ => None,
+        },
+        ColumnType::Date => match value {
+            Value::String(_) => date(utf8()?).map(|days| Cell::Date(days as i32)),
+            _ => None,
+        },
+        ColumnType::Timestamp(_) => match value {
+            Value::String(_) => timestamp(utf8()?).map(Cell::Timestamp),
+            _ => None,
+        },
+        ColumnType::Utf8 => Some(Cell::Utf8(String::from_utf8_lossy(&text).into_owned())),
+        ColumnType::Binary => Some(Cell::Binary(text.into_owned())),
+    }
+}
+
+/// The cells of a values tuple, with `--strict` an error when a value doesn't fit
+/// its column or the row doesn't have one for every column. Values that don't fit
+/// are NULL otherwise.
+pub fn cells(tuple: &[u8], fields: &[Field], strict: bool) -> Result<Vec<Cell>, &'static str> {
+    let values: Vec<Value> = Fields::new(tuple).collect();
+    if strict && values.len() != fields.len() {
+        return Err("--strict: a row doesn't have a value for every column.")
+    }
+
+    let mut cells = Vec::with_capacity(fields.len());
+    for (index, field) in fields.iter().enumerate() {
+        let cell = match values.get(index) {
+            Some(value) => match cell(value, field.column_type) {
+                Some(cell) => cell,
+                None if strict => return Err("--strict: a value doesn't fit the type of its column."),
+                None => Cell::Null,
+            },
+            None => Cell::Null,
+        };
+        if cell == Cell::Null && !field.nullable {
+            return Err("--strict: NULL in a NOT NULL column.")
+        }
+        cells.push(cell);
+    }
+    Ok(cells)
+}
+
+#[cfg(feature = "parquet")]
+mod writer {
+    use super::{Cell, ColumnType, Field};
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_array::array::*;
+    use arrow_schema::{DataType, Schema, SchemaRef, TimeUnit};
+    use arrow_schema;
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+    use std::mem;
+
+    // rows of a record batch
+    const BATCH_ROWS: usize = 8192;
+    // bytes of values tuples in a row group
+    const ROW_GROUP_BYTES: usize = 128 * 1024 * 1024;
+
+    fn data_type(column_type: ColumnType) -> DataType {
+        match column_type {
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int8 => DataType::Int8,
+            ColumnType::Int16 => DataType::Int16,
+            ColumnType::Int32 => DataType::Int32,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::UInt8 => DataType::UInt8,
+            ColumnType::UInt16 => DataType::UInt16,
+            ColumnType::UInt32 => DataType::UInt32,
+            ColumnType::UInt64 => DataType::UInt64,
+            ColumnType::Float32 => DataType::Float32,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Decimal(precision, scale) => DataType::Decimal128(precision, scale),
+            ColumnType::Date => DataType::Date32,
+            ColumnType::Timestamp(utc) => DataType::Timestamp(TimeUnit::Microsecond, if utc { Some("UTC".into()) } else { None }),
+            ColumnType::Utf8 => DataType::Utf8,
+            ColumnType::Binary => DataType::Binary,
+        }
+    }
+
+    fn array(column_type: ColumnType, cells: &[Cell]) -> ArrayRef {
+        let int = |cell: &Cell| match cell { Cell::Int(value) => Some(*value), _ => None };
+        let uint = |cell: &Cell| match cell { Cell::UInt(value) => Some(*value), _ => None };
+        let float = |cell: &Cell| match cell { Cell::Float(value) => Some(*value), _ => None };
+        match column_type {
+            ColumnType::Boolean => Arc::new(cells.iter().map(|cell| match cell {
+                Cell::Boolean(value) => Some(*value),
+                _ => None,
+            }).collect::<BooleanArray>()),
+            ColumnType::Int8 => Arc::new(cells.iter().map(|cell| int(cell).map(|value| value as i8)).collect::<Int8Array>()),
+            ColumnType::Int16 => Arc::new(cells.iter().map(|cell| int(cell).map(|value| value as i16)).collect::<Int16Array>()),
+            ColumnType::Int32 => Arc::new(cells.iter().map(|cell| int(cell).map(|value| value as i32)).collect::<Int32Array>()),
+            ColumnType::Int64 => Arc::new(cells.iter().map(int).collect::<Int64Array>()),
+            ColumnType::UInt8 => Arc::new(cells.iter().map(|cell| uint(cell).map(|value| value as u8)).collect::<UInt8Array>()),
+            ColumnType::UInt16 => Arc::new(cells.iter().map(|cell| uint(cell).map(|value| value as u16)).collect::<UInt16Array>()),
+            ColumnType::UInt32 => Arc::new(cells.iter().map(|cell| uint(cell).map(|value| value as u32)).collect::<UInt32Array>()),
+            ColumnType::UInt64 => Arc::new(cells.iter().map(uint).collect::<UInt64Array>()),
+            ColumnType::Float32 => Arc::new(cells.iter().map(|cell| float(cell).map(|value| value as f32)).collect::<Float32Array>()),
+            ColumnType::Float64 => Arc::new(cells.iter().map(float).collect::<Float64Array>()),
+            ColumnType::Decimal(precision, scale) => Arc::new(cells.iter().map(|cell| match cell {
+                Cell::Decimal(value) => Some(*value),
+                _ => None,
+            }).collect::<Decimal128Array>().with_precision_and_scale(precision, scale).expect("checked by column_type")),
+            ColumnType::Date => Arc::new(cells.iter().map(|cell| match cell {
+                Cell::Date(value) => Some(*value),
+                _ => None,
+            }).collect::<Date32Array>()),
+            ColumnType::Timestamp(utc) => {
+                let array = cells.iter().map(|cell| match cell {
+                    Cell::Timestamp(value) => Some(*value),
+                    _ => None,
+                }).collect::<TimestampMicrosecondArray>();
+                Arc::new(if utc { array.with_timezone("UTC") } else { array })
+            },
+            ColumnType::Utf8 => Arc::new(cells.iter().map(|cell| match cell {
+                Cell::Utf8(value) => Some(value.as_str()),
+                _ => None,
+            }).collect::<StringArray>()),
+            ColumnType::Binary => Arc::new(cells.iter().map(|cell| match cell {
+                Cell::Binary(value) => Some(value.as_slice()),
+                _ => None,
+            }).collect::<BinaryArray>()),
+        }
+    }
+
+    /// A Parquet file of the rows of a table being written. The bytes come out
+    /// as row groups fill up, so they can be appended to the file as they are.
+    pub struct Dataset {
+        fields: Vec<Field>,
+        strict: bool,
+        schema: SchemaRef,
+        writer: ArrowWriter<Vec<u8>>,
+        // cells of the rows not in a record batch yet, by column
+        columns: Vec<Vec<Cell>>,
+        rows: usize,
+        group_bytes: usize,
+    }
+
+    const WRITE_ERROR: &str = "Unable to write parquet.";
+
+    impl Dataset {
+        pub fn new(fields: Vec<Field>, strict: bool) -> Result<Self, &'static str> {
+            let schema = Arc::new(Schema::new(fields.iter().map(|field| {
+                arrow_schema::Field::new(field.name.clone(), data_type(field.column_type), field.nullable)
+            }).collect::<Vec<_>>()));
+            let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+            let writer = ArrowWriter::try_new(vec![], schema.clone(), Some(properties)).map_err(|_| WRITE_ERROR)?;
+            Ok(Dataset {
+                columns: vec![vec![]; fields.len()],
+                fields,
+                strict,
+                schema,
+                writer,
+                rows: 0,
+                group_bytes: 0,
+            })
+        }
+
+        pub fn fields(&self) -> &[Field] {
+            &self.fields
+        }
+
+        // the buffered rows as a record batch
+        fn write_batch(&mut self) -> Result<(), &'static str> {
+            if self.rows == 0 {
+                return Ok(())
+            }
+
+            let arrays = self.fields.iter().zip(self.columns.iter_mut())
+                .map(|(field, cells)| array(field.column_type, &mem::take(cells)))
+                .collect::<Vec<_>>();
+            self.rows = 0;
+            let batch = RecordBatch::try_new(self.schema.clone(), arrays).map_err(|_| WRITE_ERROR)?;
+            self.writer.write(&batch).map_err(|_| WRITE_ERROR)
+        }
+
+        /// Adds a values tuple, returns the bytes of the file written so far.
+        pub fn push(&mut self, tuple: &[u8]) -> Result<Vec<u8>, &'static str> {
+            let cells = super::cells(tuple, &self.fields, self.strict)?;
+            for (column, cell) in self.columns.iter_mut().zip(cells) {
+                column.push(cell);
+            }
+            self.rows += 1;
+            self.group_bytes += tuple.len();
+
+            if self.rows >= BATCH_ROWS {
+                self.write_batch()?;
+            }
+            if self.group_bytes >= ROW_GROUP_BYTES {
+                self.write_batch()?;
+                self.writer.flush().map_err(|_| WRITE_ERROR)?;
+                self.group_bytes = 0;
+            }
+            Ok(mem::take(self.writer.inner_mut()))
+        }
+
+        /// The rest of the file, with its footer.
+        pub fn finish(mut self) -> Result<Vec<u8>, &'static str> {
+            self.write_batch()?;
+            self.writer.into_inner().map_err(|_| WRITE_ERROR)
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use self::writer::Dataset;
+
+#[cfg(not(feature = "parquet"))]
+pub struct Dataset {
+    fields: Vec<Field>,
+}
+
+#[cfg(not(feature = "parquet"))]
+impl Dataset {
+    pub fn new(_fields: Vec<Field>, _strict: bool) -> Result<Self, &'static str> {
+        Err("sql-split was built without the parquet feature")
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn push(&mut self, _tuple: &[u8]) -> Result<Vec<u8>, &'static str> {
+        Ok(vec![])
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, &'static str> {
+        Ok(vec![])
+    }
+}
+
+
+#[cfg(test)]
+mod dataset_test{
+    use statement::Statement;
+    use super::*;
+
+    #[test]
+    fn column_types(){
+        let sql = b"CREATE TABLE `t` (\n\
+            `id` int unsigned NOT NULL AUTO_INCREMENT,\n\
+            `age` tinyint DEFAULT NULL,\n\
+            `total` decimal(10,2) NOT NULL,\n\
+            `huge` decimal(65,30) DEFAULT NULL,\n\
+            `born` date DEFAULT NULL,\n\
+            `seen` datetime DEFAULT NULL,\n\
+            `created` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,\n\
+            `name` varchar(255) NOT NULL,\n\
+            `avatar` blob,\n\
+            PRIMARY KEY (`id`)\n\
+            )";
+        let columns = match Statement::parse(sql) {
+            Statement::CreateTable { columns, .. } => columns,
+            _ => panic!("not a create table"),
+        };
+        let types: Vec<ColumnType> = columns.iter().map(column_type).collect();
+        assert_eq!(types, vec![
+            ColumnType::UInt32,
+            ColumnType::Int8,
+            ColumnType::Decimal(10, 2),
+            ColumnType::Utf8,
+            ColumnType::Date,
+            ColumnType::Timestamp(false),
+            ColumnType::Timestamp(true),
+            ColumnType::Utf8,
+            ColumnType::Binary,
+        ]);
+        assert!(!Field::new(&columns[0], true).nullable);
+        assert!(Field::new(&columns[0], false).nullable);
+    }
+
+    #[test]
+    fn cells_of_rows(){
+        let fields = vec![
+            Field { name: "id".to_string(), column_type: ColumnType::UInt8, nullable: false },
+            Field { name: "total".to_string(), column_type: ColumnType::Decimal(5, 2), nullable: true },
+            Field { name: "born".to_string(), column_type: ColumnType::Date, nullable: true },
+            Field { name: "seen".to_string(), column_type: ColumnType::Timestamp(true), nullable: true },
+            Field::text("name"),
+        ];
+
+        assert_eq!(cells(b"(7,-12.5,'2000-02-29','1970-01-02 00:00:01.5','Ann')", &fields, true), Ok(vec![
+            Cell::UInt(7),
+            Cell::Decimal(-1250),
+            Cell::Date(11016),
+            Cell::Timestamp(86_401_500_000),
+            Cell::Utf8("Ann".to_string()),
+        ]));
+        assert_eq!(cells(b"(7,NULL,NULL,NULL,NULL)", &fields, true).map(|cells| cells.len()), Ok(5));
+
+        assert!(cells(b"(256,1,NULL,NULL,NULL)", &fields, true).is_err());
+        assert!(cells(b"(1,1234.5,NULL,NULL,NULL)", &fields, true).is_err());
+        assert!(cells(b"(1,1.125,NULL,NULL,NULL)", &fields, true).is_err());
+        assert!(cells(b"(1,1,'2001-02-29',NULL,NULL)", &fields, true).is_err());
+        assert!(cells(b"(NULL,1,NULL,NULL,NULL)", &fields, true).is_err());
+        assert!(cells(b"(1,1,NULL,NULL)", &fields, true).is_err());
+
+        // values that don't fit are NULL without --strict
+        let loose: Vec<Field> = fields.iter().map(|field| Field { nullable: true, ..field.clone() }).collect();
+        assert_eq!(cells(b"(256,'abc','0000-00-00')", &loose, false), Ok(vec![
+            Cell::Null, Cell::Null, Cell::Null, Cell::Null, Cell::Null,
+        ]));
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn writes_parquet(){
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field as Value;
+
+        let fields = vec![
+            Field { name: "id".to_string(), column_type: ColumnType::Int32, nullable: false },
+            Field::text("name"),
+            Field { name: "seen".to_string(), column_type: ColumnType::Timestamp(true), nullable: true },
+        ];
+        let mut dataset = Dataset::new(fields, true).unwrap();
+        let mut bytes = dataset.push(b"(1,'Ann','2024-01-02 10:00:00')").unwrap();
+        bytes.extend(dataset.push(b"(2,NULL,NULL)").unwrap());
+        bytes.extend(dataset.finish().unwrap());
+
+        let path = ::std::env::temp_dir().join("sql-split-dataset-test.parquet");
+        ::std::fs::write(&path, bytes).unwrap();
+        let reader = SerializedFileReader::new(::std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let rows: Vec<String> = reader.get_row_iter(None).unwrap()
+            .map(|row| row.unwrap().get_column_iter().map(|(_, value)| match value {
+                Value::Int(value) => value.to_string(),
+                Value::Str(value) => value.clone(),
+                Value::TimestampMicros(value) => value.to_string(),
+                Value::Null => "NULL".to_string(),
+                value => format!("{:?}", value),
+            }).collect::<Vec<_>>().join(","))
+            .collect();
+        assert_eq!(rows, vec!["1,Ann,1704189600000000", "2,NULL,NULL"]);
+    }
+}
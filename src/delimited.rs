@@ -0,0 +1,197 @@
+use parser::Table;
+use splitter::Format;
+use value::Value;
+use value::Fields;
+use value::escape;
+use output::quoted;
+
+/// Rows of `--format=csv` and `--format=tsv`. CSV quotes the fields that need
+/// it, TSV escapes tabs, line feeds and backslashes like `SELECT .. INTO OUTFILE`.
+#[derive(Debug,Clone)]
+pub struct Delimited {
+    tsv: bool,
+    // `--header`, the column names on the first line of every file
+    pub header: bool,
+    // `--null`, `\N` unless set, and `NULL` in CSV for `--load-data`
+    pub null: Vec<u8>,
+    // `--load-data`, a `LOAD DATA` statement for every file, the NULL marker
+    // has to be the one it reads: `\N` in TSV and a bare `NULL` in CSV
+    pub load_data: bool,
+}
+
+impl Default for Delimited {
+    fn default() -> Self {
+        Delimited { tsv: false, header: false, null: b"\\N".to_vec(), load_data: false }
+    }
+}
+
+impl Delimited {
+    pub fn new(format: Format, header: bool, null: Option<&str>, load_data: bool) -> Self {
+        Delimited {
+            tsv: format == Format::Tsv,
+            header,
+            null: match null {
+                Some(null) => null.as_bytes().to_vec(),
+                None if load_data && format == Format::Csv => b"NULL".to_vec(),
+                None => b"\\N".to_vec(),
+            },
+            load_data,
+        }
+    }
+
+    fn separator(&self) -> u8 {
+        if self.tsv { b'\t' } else { b',' }
+    }
+
+    fn field(&self, text: &[u8], out: &mut Vec<u8>) {
+        if self.tsv {
+            for &byte in text {
+                match byte {
+                    b'\\' => out.extend(b"\\\\"),
+                    b'\t' => out.extend(b"\\t"),
+                    b'\n' => out.extend(b"\\n"),
+                    b'\r' => out.extend(b"\\r"),
+                    0 => out.extend(b"\\0"),
+                    byte => out.push(byte),
+                }
+            }
+            return
+        }
+
+        if !text.iter().any(|&byte| matches!(byte, b',' | b'"' | b'\n' | b'\r')) {
+            out.extend(text);
+            return
+        }
+        Self::quote(text, out)
+    }
+
+    fn quote(text: &[u8], out: &mut Vec<u8>) {
+        out.push(b'"');
+        for &byte in text {
+            if byte == b'"' {
+                out.push(b'"');
+            }
+            out.push(byte);
+        }
+        out.push(b'"');
+    }
+
+    /// The `--header` line, empty without it.
+    pub fn header(&self, columns: &[String]) -> Vec<u8> {
+        let mut out = vec![];
+        if !self.header || columns.is_empty() {
+            return out
+        }
+
+        for (index, column) in columns.iter().enumerate() {
+            if index > 0 {
+                out.push(self.separator());
+            }
+            self.field(column.as_bytes(), &mut out);
+        }
+        out.push(b'\n');
+        out
+    }
+
+    /// A values tuple as a line.
+    pub fn row(&self, tuple: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        for (index, value) in Fields::new(tuple).enumerate() {
+            if index > 0 {
+                out.push(self.separator());
+            }
+
+            match value {
+                Value::Null => out.extend(&self.null),
+                // only NULL is the bare marker, `''` with `--null=''` is `""`
+                value => match value.text() {
+                    Some(text) if !self.tsv && text == self.null => Self::quote(&text, &mut out),
+                    Some(text) => self.field(&text, &mut out),
+                    None => {},
+                },
+            }
+        }
+        out.push(b'\n');
+        out
+    }
+
+    /// `LOAD DATA` for a file of rows. It reads `\N` in TSV as NULL, and a bare
+    /// `NULL` in CSV, which it tells from a quoted `"NULL"`. Turning other markers
+    /// back into NULL would take strings that read like them along.
+    pub fn load_data(&self, file: &str, table: &Table, columns: &[String]) -> Vec<u8> {
+        let literal = |text: &[u8]| {
+            let mut out = b"'".to_vec();
+            escape(text, &mut out);
+            out.push(b'\'');
+            String::from_utf8_lossy(&out).into_owned()
+        };
+
+        let mut sql = format!("LOAD DATA LOCAL INFILE {} INTO TABLE {}\n", literal(file.as_bytes()), quoted(table));
+        if self.tsv {
+            sql.push_str("FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\'\n");
+        }else{
+            sql.push_str("FIELDS TERMINATED BY ',' OPTIONALLY ENCLOSED BY '\"' ESCAPED BY ''\n");
+        }
+        sql.push_str("LINES TERMINATED BY '\\n'");
+        if self.header && !columns.is_empty() {
+            sql.push_str(" IGNORE 1 LINES");
+        }
+
+        let column = |name: &String| format!("`{}`", name.replace('`', "``"));
+        if !columns.is_empty() {
+            let names: Vec<String> = columns.iter().map(column).collect();
+            sql.push_str(&format!("\n({})", names.join(", ")));
+        }
+        sql.push_str(";\n");
+        sql.into_bytes()
+    }
+}
+
+
+#[cfg(test)]
+mod delimited_test{
+    use parser::Table;
+    use splitter::Format;
+    use super::*;
+
+    #[test]
+    fn rows(){
+        let csv = Delimited::new(Format::Csv, true, None, false);
+        let columns = vec!["id".to_string(), "note".to_string()];
+        assert_eq!(csv.header(&columns), b"id,note\n".to_vec());
+        assert_eq!(csv.row(b"(1,'a, \\\"b\\\"\\nc',NULL,0x41,b'11',2.50)"), b"1,\"a, \"\"b\"\"\nc\",\\N,A,3,2.50\n".to_vec());
+
+        // strings that read as the marker are quoted
+        let empty = Delimited::new(Format::Csv, false, Some(""), false);
+        assert_eq!(empty.row(b"(1,'',NULL,'\\\\N')"), b"1,\"\",,\\N\n".to_vec());
+        assert_eq!(csv.row(b"('\\\\N',NULL)"), b"\"\\N\",\\N\n".to_vec());
+
+        let tsv = Delimited::new(Format::Tsv, false, Some(""), false);
+        assert_eq!(tsv.header(&columns), b"".to_vec());
+        assert_eq!(tsv.row(b"\n(1,'a\\tb\\\\',NULL,'x,y')"), b"1\ta\\tb\\\\\t\tx,y\n".to_vec());
+    }
+
+    #[test]
+    fn load_data(){
+        let table = Table { schema: None, name: "users".to_string() };
+        let columns = vec!["id".to_string(), "name".to_string()];
+
+        let csv = Delimited::new(Format::Csv, true, None, true);
+        assert_eq!(String::from_utf8(csv.load_data("users.csv", &table, &columns)).unwrap(),
+            "LOAD DATA LOCAL INFILE 'users.csv' INTO TABLE `users`\n\
+            FIELDS TERMINATED BY ',' OPTIONALLY ENCLOSED BY '\"' ESCAPED BY ''\n\
+            LINES TERMINATED BY '\\n' IGNORE 1 LINES\n\
+            (`id`, `name`);\n");
+        // NULL is the bare word it reads as NULL, strings that read like
+        // a marker are quoted or escaped and load as themselves
+        assert_eq!(csv.row(b"(NULL,'NULL','\\\\N','')"), b"NULL,\"NULL\",\\N,\n".to_vec());
+
+        let tsv = Delimited::new(Format::Tsv, false, None, true);
+        assert_eq!(String::from_utf8(tsv.load_data("users.0002.tsv", &table, &columns)).unwrap(),
+            "LOAD DATA LOCAL INFILE 'users.0002.tsv' INTO TABLE `users`\n\
+            FIELDS TERMINATED BY '\\t' ESCAPED BY '\\\\'\n\
+            LINES TERMINATED BY '\\n'\n\
+            (`id`, `name`);\n");
+        assert_eq!(tsv.row(b"(NULL,'\\\\N','')"), b"\\N\t\\\\N\t\n".to_vec());
+    }
+}
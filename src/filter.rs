@@ -0,0 +1,129 @@
+use parser::Table;
+
+/// Decides which tables make it into the output, based on the
+/// `--tables` and `--exclude-tables` patterns. Patterns support `*`
+/// and `?` wildcards and may be schema qualified (`db.tbl`).
+#[derive(Debug,Default)]
+pub struct TableFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl TableFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self { include, exclude }
+    }
+
+    /// Splits a comma separated list of patterns.
+    pub fn patterns(list: Option<&str>) -> Vec<String> {
+        match list {
+            Some(list) => {
+                list.split(',')
+                    .map(|pattern| pattern.trim().to_string())
+                    .filter(|pattern| !pattern.is_empty())
+                    .collect()
+            },
+            None => vec![],
+        }
+    }
+
+    pub fn allows(&self, table: &Table) -> bool {
+        if self.exclude.iter().any(|pattern| matches(pattern, table)) {
+            return false
+        }
+
+        self.include.is_empty() || self.include.iter().any(|pattern| matches(pattern, table))
+    }
+}
+
+fn matches(pattern: &str, table: &Table) -> bool {
+    match pattern.find('.') {
+        Some(index) => {
+            let (schema, name) = (&pattern[..index], &pattern[index + 1..]);
+            // unqualified tables belong to the schema the dump is loaded into
+            let schema_matches = match table.schema {
+                Some(ref value) => wildcard(schema.as_bytes(), value.as_bytes()),
+                None => true,
+            };
+
+            schema_matches && wildcard(name.as_bytes(), table.name.as_bytes())
+        },
+        None => wildcard(pattern.as_bytes(), table.name.as_bytes()),
+    }
+}
+
+// glob style matching, `*` matches any run of bytes and `?` a single byte.
+fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text index it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        }else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        }else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        }else{
+            return false
+        }
+    }
+
+    pattern[p..].iter().all(|byte| *byte == b'*')
+}
+
+
+#[cfg(test)]
+mod filter_test{
+    use parser::Table;
+    use super::TableFilter;
+    use super::wildcard;
+
+    fn table(schema: Option<&str>, name: &str) -> Table {
+        Table {
+            schema: schema.map(|s| s.to_string()),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn wildcards(){
+        assert!(wildcard(b"users", b"users"));
+        assert!(!wildcard(b"users", b"user"));
+        assert!(wildcard(b"audit_*", b"audit_"));
+        assert!(wildcard(b"audit_*", b"audit_2024_01"));
+        assert!(!wildcard(b"audit_*", b"xaudit_2024"));
+        assert!(wildcard(b"*_log", b"access_log"));
+        assert!(wildcard(b"log_??", b"log_01"));
+        assert!(!wildcard(b"log_??", b"log_001"));
+        assert!(wildcard(b"*a*b*", b"xxaxxbxx"));
+    }
+
+    #[test]
+    fn include_and_exclude(){
+        let filter = TableFilter::new(
+            TableFilter::patterns(Some("users, orders,shop.items")),
+            TableFilter::patterns(Some("orders")),
+        );
+
+        assert!(filter.allows(&table(None, "users")));
+        assert!(filter.allows(&table(Some("shop"), "users")));
+        assert!(!filter.allows(&table(None, "orders")));
+        assert!(filter.allows(&table(Some("shop"), "items")));
+        assert!(!filter.allows(&table(Some("blog"), "items")));
+        assert!(!filter.allows(&table(None, "payments")));
+    }
+
+    #[test]
+    fn exclude_only(){
+        let filter = TableFilter::new(vec![], TableFilter::patterns(Some("audit_*,log_*")));
+        assert!(filter.allows(&table(None, "users")));
+        assert!(!filter.allows(&table(None, "audit_2024")));
+        assert!(!filter.allows(&table(Some("shop"), "log_access")));
+    }
+}
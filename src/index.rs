@@ -0,0 +1,308 @@
+use parser::Parser;
+use parser::TokenStream;
+use parser::Kind;
+use parser::Table;
+use filter::TableFilter;
+use reader::Reader;
+use splitter::Dialect;
+use tokenizer::Tokenizer;
+use tokenizer::SyntaxErr;
+use postgres::hex;
+use sha2::{Digest, Sha256};
+use serde_json;
+use serde_json::json;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+/// A statement of the dump, where it is and what it is.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Entry {
+    pub offset: u64,
+    pub length: u64,
+    // `create table`, `insert`, `copy` and on
+    pub kind: String,
+    pub table: Option<Table>,
+    // tuples of an insert, rows of a `COPY`
+    pub rows: u64,
+    // the `DELIMITER` it ends with
+    pub delimiter: Vec<u8>,
+}
+
+fn kind(kind: &Kind) -> String {
+    let object = |object| format!("{:?}", object).to_lowercase();
+    match kind {
+        Kind::Create(created) => format!("create {}", object(created)),
+        Kind::Alter(altered) => format!("alter {}", object(altered)),
+        Kind::Drop(dropped) => format!("drop {}", object(dropped)),
+        // `INSERT .. SELECT`, the inserts with values are `insert`
+        Kind::Insert => "insert select".to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+// bytes hashed at each end of the dump
+const BLOCK: u64 = 64 * 1024;
+
+/// Size of the dump and a SHA-256 of its first and last 64kb, to tell
+/// the index of a dump that changed since it was written.
+#[derive(Debug,PartialEq)]
+pub struct Fingerprint {
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl Fingerprint {
+    /// Reads both ends of the dump and leaves it at its start.
+    pub fn of<R: Read + Seek>(dump: &mut R) -> io::Result<Self> {
+        let bytes = dump.seek(SeekFrom::End(0))?;
+        let mut ends = vec![];
+        dump.seek(SeekFrom::Start(0))?;
+        dump.by_ref().take(BLOCK).read_to_end(&mut ends)?;
+        if bytes > BLOCK {
+            dump.seek(SeekFrom::Start((bytes - BLOCK).max(BLOCK)))?;
+            dump.by_ref().take(BLOCK).read_to_end(&mut ends)?;
+        }
+        dump.seek(SeekFrom::Start(0))?;
+        Ok(Fingerprint { bytes, sha256: hex(&Sha256::digest(&ends)) })
+    }
+}
+
+fn invalid() -> SyntaxErr {
+    SyntaxErr{ text: "The index is invalid." }
+}
+
+fn unwritable() -> SyntaxErr {
+    SyntaxErr{ text: "Unable to write the index." }
+}
+
+impl Entry {
+    fn json(&self) -> String {
+        let mut entry = json!({
+            "offset": self.offset,
+            "length": self.length,
+            "kind": self.kind,
+            "table": self.table.as_ref().map(|table| table.name.clone()),
+            "rows": self.rows,
+        });
+        if let Some(Table { schema: Some(ref schema), .. }) = self.table {
+            entry["schema"] = json!(schema);
+        }
+        if self.delimiter != b";" {
+            entry["delimiter"] = json!(String::from_utf8_lossy(&self.delimiter));
+        }
+        entry.to_string()
+    }
+
+    pub fn parse(line: &str) -> Result<Self, SyntaxErr> {
+        let value: serde_json::Value = serde_json::from_str(line).map_err(|_| invalid())?;
+        let table = value["table"].as_str().map(|name| Table {
+            schema: value["schema"].as_str().map(String::from),
+            name: name.to_string(),
+        });
+        Ok(Entry {
+            offset: value["offset"].as_u64().ok_or_else(invalid)?,
+            length: value["length"].as_u64().ok_or_else(invalid)?,
+            kind: value["kind"].as_str().ok_or_else(invalid)?.to_string(),
+            table,
+            rows: value["rows"].as_u64().ok_or_else(invalid)?,
+            delimiter: value["delimiter"].as_str().unwrap_or(";").as_bytes().to_vec(),
+        })
+    }
+}
+
+/// Writes the index of a dump: a line with its name and fingerprint, then a line
+/// for each statement with its offset, length, kind, table and rows. White
+/// space and comments between the statements are left out.
+pub fn index<R: Read, W: Write>(file: R, dialect: Dialect, source: &str, fingerprint: &Fingerprint, out: &mut W) -> Result<u64, SyntaxErr> {
+    let header = json!({ "source": source, "bytes": fingerprint.bytes, "sha256": fingerprint.sha256 });
+    writeln!(out, "{}", header).map_err(|_| unwritable())?;
+
+    let mut parser = Parser::new(Tokenizer::for_dialect(Reader::new(file), dialect));
+    let mut current: Option<Entry> = None;
+    let mut statements = 0;
+    let mut start = 0;
+
+    let mut close = |entry: Option<Entry>, out: &mut W| -> Result<(), SyntaxErr> {
+        if let Some(entry) = entry {
+            statements += 1;
+            writeln!(out, "{}", entry.json()).map_err(|_| unwritable())?;
+        }
+        Ok(())
+    };
+
+    while let Some(item) = parser.token_stream()? {
+        let end = parser.position();
+        let statement = |kind: String, table: Option<Table>, rows: u64| Entry {
+            offset: start,
+            length: end - start,
+            kind,
+            table,
+            rows,
+            delimiter: parser.delimiter().to_vec(),
+        };
+        match item {
+            TokenStream::Insert(_, _, table) => {
+                close(current.take(), out)?;
+                current = Some(statement("insert".to_string(), table, 1));
+            },
+            TokenStream::Copy(_, table) => {
+                close(current.take(), out)?;
+                current = Some(statement("copy".to_string(), table, 0));
+            },
+            TokenStream::Block(block, _, table) => {
+                close(current.take(), out)?;
+                close(Some(statement(kind(&block), table, 0)), out)?;
+            },
+            TokenStream::ValuesTuple(_) | TokenStream::CopyRow(_) | TokenStream::CopyEnd(_) => {
+                if let Some(ref mut entry) = current {
+                    entry.length = end - entry.offset;
+                    if !matches!(item, TokenStream::CopyEnd(_)) {
+                        entry.rows += 1;
+                    }
+                }
+            },
+            // white space between the tuples comes on its own
+            TokenStream::Comment(_) | TokenStream::SpaceOrLineFeed(_) => {},
+        }
+        start = end;
+    }
+    close(current.take(), out)?;
+    out.flush().map_err(|_| unwritable())?;
+    Ok(statements)
+}
+
+/// What `extract` writes out of the dump.
+pub enum Pick {
+    Tables(TableFilter),
+    // the nth statement of the index, from 1
+    Statement(u64),
+}
+
+/// Copies the statements picked from the index out of the dump, one per line,
+/// switching the `DELIMITER` where the dump did. Tables come with the `SET`
+/// statements before the first table and the `UNLOCK TABLES` of their
+/// `LOCK TABLES`. The dump has to have the fingerprint it was indexed with.
+pub fn extract<R: Read + Seek, I: BufRead, W: Write>(dump: &mut R, index: I, pick: &Pick, out: &mut W) -> Result<u64, SyntaxErr> {
+    let unreadable = || SyntaxErr{ text: "Unable to read the dump." };
+    let unwritable = || SyntaxErr{ text: "Unable to write the statements." };
+    let mut lines = index.lines();
+
+    let header = lines.next().and_then(|line| line.ok()).ok_or_else(invalid)?;
+    let header: serde_json::Value = serde_json::from_str(&header).map_err(|_| invalid())?;
+    let fingerprint = Fingerprint::of(dump).map_err(|_| unreadable())?;
+    if header["bytes"].as_u64() != Some(fingerprint.bytes) || header["sha256"].as_str() != Some(fingerprint.sha256.as_str()) {
+        return Err(SyntaxErr{ text: "The dump changed since it was indexed, index it again." })
+    }
+
+    let mut delimiter = b";".to_vec();
+    let mut extracted = 0;
+    // a statement with a table went by, the preamble is over
+    let mut tabled = false;
+    // a picked `LOCK TABLES` waits for its `UNLOCK TABLES`
+    let mut locked = false;
+    for (number, line) in lines.enumerate() {
+        let entry = Entry::parse(&line.map_err(|_| invalid())?)?;
+        let picked = match (pick, &entry.table) {
+            (Pick::Tables(filter), Some(table)) => filter.allows(table),
+            (Pick::Tables(_), None) => (entry.kind == "set" && !tabled) || (entry.kind == "unlock" && locked),
+            (Pick::Statement(nth), _) => number as u64 + 1 == *nth,
+        };
+        tabled |= entry.table.is_some();
+        if !picked {
+            continue
+        }
+        match entry.kind.as_str() {
+            "lock" => locked = true,
+            "unlock" => locked = false,
+            _ => {},
+        }
+
+        // a `DELIMITER` picked on its own switches it already
+        if entry.kind == "delimiter" {
+            delimiter = entry.delimiter.clone();
+        }else if entry.delimiter != delimiter {
+            delimiter = entry.delimiter.clone();
+            out.write_all(&[&b"DELIMITER "[..], &delimiter, b"\n"].concat()).map_err(|_| unwritable())?;
+        }
+        dump.seek(SeekFrom::Start(entry.offset)).map_err(|_| unreadable())?;
+        let copied = io::copy(&mut dump.take(entry.length), out).map_err(|_| unwritable())?;
+        if copied != entry.length {
+            return Err(unreadable())
+        }
+        out.write_all(b"\n").map_err(|_| unwritable())?;
+        extracted += 1;
+    }
+
+    if delimiter != b";" {
+        out.write_all(b"DELIMITER ;\n").map_err(|_| unwritable())?;
+    }
+    out.flush().map_err(|_| unwritable())?;
+    Ok(extracted)
+}
+
+
+#[cfg(test)]
+mod index_test{
+    use super::*;
+    use std::io::Cursor;
+
+    fn indexed(dump: &str, dialect: Dialect) -> (Vec<u8>, Vec<Entry>) {
+        let mut written = vec![];
+        let fingerprint = Fingerprint::of(&mut Cursor::new(dump)).unwrap();
+        index(dump.as_bytes(), dialect, "dump.sql", &fingerprint, &mut written).unwrap();
+        let entries = String::from_utf8(written.clone()).unwrap().lines().skip(1)
+            .map(|line| Entry::parse(line).unwrap())
+            .collect();
+        (written, entries)
+    }
+
+    #[test]
+    fn indexes_statements(){
+        let dump = "/*!40101 SET NAMES utf8 */;\nCREATE TABLE `users` (id int);\n-- rows\n\
+            INSERT INTO `users` VALUES (1),\n(2),\n(3);\nINSERT INTO `orders` VALUES (4);\n";
+        let (_, entries) = indexed(dump, Dialect::Mysql);
+        let slices: Vec<&str> = entries.iter().map(|entry| &dump[entry.offset as usize..(entry.offset + entry.length) as usize]).collect();
+        assert_eq!(slices, vec![
+            "/*!40101 SET NAMES utf8 */;", "CREATE TABLE `users` (id int);",
+            "INSERT INTO `users` VALUES (1),\n(2),\n(3);", "INSERT INTO `orders` VALUES (4);",
+        ]);
+        let kinds: Vec<(&str, u64)> = entries.iter().map(|entry| (entry.kind.as_str(), entry.rows)).collect();
+        assert_eq!(kinds, vec![("set", 0), ("create table", 0), ("insert", 3), ("insert", 1)]);
+        assert_eq!(entries[2].table, Some(Table { schema: None, name: "users".to_string() }));
+    }
+
+    #[test]
+    fn extracts_tables(){
+        let dump = "CREATE TABLE t (a int);\nDELIMITER ;;\nCREATE TRIGGER t1 BEFORE INSERT ON t FOR EACH ROW SET NEW.a = 1;;\n\
+            DELIMITER ;\nINSERT INTO u VALUES (1);\nINSERT INTO t VALUES (2),(3);\n";
+        let (index, _) = indexed(dump, Dialect::Mysql);
+        let pick = Pick::Tables(TableFilter::new(vec!["t".to_string()], vec![]));
+        let mut out = vec![];
+        let count = extract(&mut Cursor::new(dump), &index[..], &pick, &mut out).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(String::from_utf8(out).unwrap(), "CREATE TABLE t (a int);\nDELIMITER ;;\n\
+            CREATE TRIGGER t1 BEFORE INSERT ON t FOR EACH ROW SET NEW.a = 1;;\nDELIMITER ;\nINSERT INTO t VALUES (2),(3);\n");
+
+        let mut out = vec![];
+        extract(&mut Cursor::new(dump), &index[..], &Pick::Statement(5), &mut out).unwrap();
+        assert_eq!(out, b"INSERT INTO u VALUES (1);\n");
+        let grown = format!("{}--\n", dump);
+        assert!(extract(&mut Cursor::new(grown), &index[..], &Pick::Statement(1), &mut vec![]).is_err());
+        // the same size, other bytes
+        let edited = dump.replacen("(1)", "(9)", 1);
+        assert!(extract(&mut Cursor::new(edited), &index[..], &Pick::Statement(1), &mut vec![]).is_err());
+        assert_eq!(extract(&mut Cursor::new(dump), &index[..], &Pick::Statement(7), &mut vec![]).unwrap(), 0);
+    }
+
+    #[test]
+    fn extracts_preamble_and_unlock(){
+        let dump = "/*!40101 SET NAMES utf8 */;\nSET foreign_key_checks=0;\nCREATE TABLE t (a int);\n\
+            LOCK TABLES `u` WRITE;\nINSERT INTO u VALUES (1);\nUNLOCK TABLES;\n\
+            LOCK TABLES `t` WRITE;\nINSERT INTO t VALUES (2);\nUNLOCK TABLES;\nSET foreign_key_checks=1;\n";
+        let (index, _) = indexed(dump, Dialect::Mysql);
+        let pick = Pick::Tables(TableFilter::new(vec!["t".to_string()], vec![]));
+        let mut out = vec![];
+        extract(&mut Cursor::new(dump), &index[..], &pick, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "/*!40101 SET NAMES utf8 */;\nSET foreign_key_checks=0;\n\
+            CREATE TABLE t (a int);\nLOCK TABLES `t` WRITE;\nINSERT INTO t VALUES (2);\nUNLOCK TABLES;\n");
+    }
+}
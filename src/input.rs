@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// The dump being split, a file or a directory of them.
+pub enum Input {
+    File(File),
+    Directory(Directory),
+}
+
+impl Input {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            Ok(Input::Directory(Directory::open(path)?))
+        }else{
+            Ok(Input::File(File::open(path)?))
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            Input::Directory(directory) => directory.read(buf),
+        }
+    }
+}
+
+impl Seek for Input {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        match self {
+            Input::File(file) => file.seek(position),
+            Input::Directory(directory) => directory.seek(position),
+        }
+    }
+}
+
+/// When a file of a dump directory is loaded.
+#[derive(Debug,PartialEq,Eq,PartialOrd,Ord,Clone,Copy)]
+pub enum Stage {
+    // `db-schema-create.sql`
+    Database,
+    // `db.users-schema.sql`
+    Schema,
+    // `db.users.00001.sql`, `data/users.0001.sql` or the `1.sql` of the chunks layout
+    Data,
+    // `db.users-schema-post.sql`, `db-schema-post.sql` and `routines.sql`
+    Post,
+    // `db.v-schema-view.sql` and `views.sql`
+    Views,
+    // `db.users-schema-triggers.sql` and `triggers.sql`
+    Triggers,
+}
+
+/// Stage, table and chunk number of a file of mydumper, or of `--layout=mydumper`,
+/// None for the files that aren't SQL like `metadata`.
+pub fn classify(name: &str) -> Option<(Stage, String, u64)> {
+    let stem = name.strip_suffix(".sql")?;
+    let suffixes = [
+        ("-schema-create", Stage::Database),
+        ("-schema-post", Stage::Post),
+        ("-schema-view", Stage::Views),
+        ("-schema-triggers", Stage::Triggers),
+        ("-schema", Stage::Schema),
+    ];
+    for (suffix, stage) in &suffixes {
+        if let Some(table) = stem.strip_suffix(suffix) {
+            return Some((*stage, table.to_string(), 0))
+        }
+    }
+
+    match stem {
+        "routines" => return Some((Stage::Post, String::new(), 0)),
+        "views" => return Some((Stage::Views, String::new(), 0)),
+        "triggers" => return Some((Stage::Triggers, String::new(), 0)),
+        _ => {},
+    }
+
+    // chunks are numbered after the table, `1.sql` is a chunk of no table
+    let (table, chunk) = match stem.rfind('.') {
+        Some(dot) => (&stem[..dot], &stem[dot + 1..]),
+        None => ("", stem),
+    };
+    match chunk.parse::<u64>() {
+        Ok(chunk) => Some((Stage::Data, table.to_string(), chunk)),
+        _ => Some((Stage::Data, stem.to_string(), 0)),
+    }
+}
+
+fn compressed(name: &str) -> bool {
+    [".sql.gz", ".sql.zst", ".sql.bz2", ".sql.xz"].iter().any(|suffix| name.ends_with(suffix))
+}
+
+// the files of a directory and the ones below it
+fn walk(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, files)?;
+        }else{
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Orders the files of a dump directory to be loaded: the database, the
+/// tables, their data by table and chunk, then indexes, routines, views and triggers.
+pub fn order(files: Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+    let mut keyed = vec![];
+    for path in files {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if compressed(&name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is compressed, decompress the dump first", name)))
+        }
+        if let Some(key) = classify(&name) {
+            keyed.push((key, path));
+        }
+    }
+
+    keyed.sort_by(|(a, a_path), (b, b_path)| match a.cmp(b) {
+        Ordering::Equal => a_path.cmp(b_path),
+        ordering => ordering,
+    });
+    Ok(keyed.into_iter().map(|(_, path)| path).collect())
+}
+
+struct Part {
+    path: PathBuf,
+    length: u64,
+    // a line feed after a file that doesn't end with one,
+    // so its last statement doesn't run into the next file
+    padded: bool,
+}
+
+impl Part {
+    fn size(&self) -> u64 {
+        self.length + self.padded as u64
+    }
+}
+
+/// The SQL files of a dump directory read one after the other like one dump.
+pub struct Directory {
+    parts: Vec<Part>,
+    index: usize,
+    // position in the part being read
+    offset: u64,
+    file: Option<File>,
+}
+
+impl Directory {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut files = vec![];
+        walk(path, &mut files)?;
+
+        let mut parts = vec![];
+        for path in order(files)? {
+            let mut file = File::open(&path)?;
+            let length = file.metadata()?.len();
+            let padded = length > 0 && {
+                let mut last = [0];
+                file.seek(SeekFrom::End(-1))?;
+                file.read_exact(&mut last)?;
+                last[0] != b'\n'
+            };
+            parts.push(Part { path, length, padded });
+        }
+
+        if parts.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the directory has no .sql files"))
+        }
+        Ok(Directory { parts, index: 0, offset: 0, file: None })
+    }
+
+    /// Files in the order they are read.
+    pub fn files(&self) -> Vec<&Path> {
+        self.parts.iter().map(|part| part.path.as_path()).collect()
+    }
+
+    fn len(&self) -> u64 {
+        self.parts.iter().map(Part::size).sum()
+    }
+}
+
+impl Read for Directory {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(part) = self.parts.get(self.index) {
+            if self.offset < part.length {
+                if self.file.is_none() {
+                    let mut file = File::open(&part.path)?;
+                    file.seek(SeekFrom::Start(self.offset))?;
+                    self.file = Some(file);
+                }
+
+                let read = self.file.as_mut().expect("opened above").read(buf)?;
+                if read > 0 {
+                    self.offset += read as u64;
+                    return Ok(read)
+                }
+                // the file got shorter since it was opened
+                self.offset = part.length;
+            }
+
+            if self.offset == part.length && part.padded && !buf.is_empty() {
+                buf[0] = b'\n';
+                self.offset += 1;
+                return Ok(1)
+            }
+
+            self.index += 1;
+            self.offset = 0;
+            self.file = None;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for Directory {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let current = self.parts[..self.index.min(self.parts.len())].iter().map(Part::size).sum::<u64>() + self.offset;
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => current.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len().checked_add_signed(delta),
+        };
+        let target = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the dump"))?;
+
+        let mut start = 0;
+        self.index = self.parts.len();
+        self.offset = 0;
+        for (index, part) in self.parts.iter().enumerate() {
+            if target < start + part.size() {
+                self.index = index;
+                self.offset = target - start;
+                break
+            }
+            start += part.size();
+        }
+        self.file = None;
+        Ok(target)
+    }
+}
+
+
+#[cfg(test)]
+mod input_test{
+    use super::*;
+
+    #[test]
+    fn orders_files(){
+        let names = [
+            "metadata", "shop.users.00001.sql", "shop.users-schema.sql", "shop-schema-create.sql", "shop.users.00000.sql",
+            "shop.orders.sql", "shop.orders-schema.sql", "shop.v-schema-view.sql", "shop.users-schema-triggers.sql",
+            "shop-schema-post.sql", "shop.users.00010.sql", "shop.users-metadata",
+        ];
+        let ordered = order(names.iter().map(PathBuf::from).collect()).unwrap();
+        assert_eq!(ordered, [
+            "shop-schema-create.sql", "shop.orders-schema.sql", "shop.users-schema.sql", "shop.orders.sql",
+            "shop.users.00000.sql", "shop.users.00001.sql", "shop.users.00010.sql", "shop-schema-post.sql",
+            "shop.v-schema-view.sql", "shop.users-schema-triggers.sql",
+        ].iter().map(PathBuf::from).collect::<Vec<_>>());
+
+        let chunks = order(["10.sql", "2.sql", "1.sql"].iter().map(PathBuf::from).collect()).unwrap();
+        assert_eq!(chunks, ["1.sql", "2.sql", "10.sql"].iter().map(PathBuf::from).collect::<Vec<_>>());
+        assert!(order(vec![PathBuf::from("shop.users.00000.sql.gz")]).is_err());
+    }
+
+    #[test]
+    fn reads_directory(){
+        let root = ::std::env::temp_dir().join("sql-split-input-test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("schema")).unwrap();
+        fs::write(root.join("schema").join("t-schema.sql"), "CREATE TABLE t (id int);").unwrap();
+        fs::write(root.join("t.0001.sql"), "INSERT INTO t VALUES (1);\n").unwrap();
+        fs::write(root.join("metadata"), "[config]\n").unwrap();
+
+        let mut directory = Directory::open(&root).unwrap();
+        let mut dump = String::new();
+        directory.read_to_string(&mut dump).unwrap();
+        assert_eq!(dump, "CREATE TABLE t (id int);\nINSERT INTO t VALUES (1);\n");
+
+        directory.seek(SeekFrom::Start(25)).unwrap();
+        let mut rest = String::new();
+        directory.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "INSERT INTO t VALUES (1);\n");
+    }
+}
@@ -0,0 +1,143 @@
+use statement::DataType;
+use postgres::binary;
+use value::Value;
+use value::Fields;
+use serde_json;
+use std::str;
+
+/// How a column is written in `--format=jsonl`, picked from its type
+/// in `CREATE TABLE`. Columns of tables without one go by their values.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum FieldType {
+    Integer,
+    Float,
+    // a string, doubles can't hold them
+    Decimal,
+    // base64
+    Binary,
+    // the document itself when it parses
+    Json,
+    Text,
+    Unknown,
+}
+
+pub fn field_type(data_type: &DataType) -> FieldType {
+    match data_type.name.as_str() {
+        "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint" |
+        "bool" | "boolean" | "bit" | "year" => FieldType::Integer,
+        "float" | "double" | "real" => FieldType::Float,
+        "decimal" | "numeric" | "dec" | "fixed" => FieldType::Decimal,
+        "json" => FieldType::Json,
+        _ if binary(data_type) => FieldType::Binary,
+        _ => FieldType::Text,
+    }
+}
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let block = chunk.iter().enumerate().fold(0u32, |block, (index, &byte)| block | u32::from(byte) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                out.push(BASE64[(block >> (18 - 6 * index) & 63) as usize] as char);
+            }else{
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn string(text: &[u8]) -> String {
+    serde_json::to_string(&String::from_utf8_lossy(text)).unwrap_or_default()
+}
+
+// a number as it was dumped, when JSON can have it as is
+fn number(text: &[u8]) -> Option<String> {
+    let text = str::from_utf8(text).ok()?.trim();
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(serde_json::Value::Number(_)) => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+fn field(value: &Value, field_type: FieldType) -> String {
+    let text = match value.text() {
+        Some(text) => text,
+        None => return "null".to_string(),
+    };
+
+    match (field_type, value) {
+        (FieldType::Binary, _) |
+        (FieldType::Unknown, Value::Hex(_)) => format!("\"{}\"", base64(&text)),
+        (FieldType::Integer, _) |
+        (FieldType::Float, _) |
+        (FieldType::Unknown, Value::Int(_)) |
+        (FieldType::Unknown, Value::Float(_)) |
+        (FieldType::Unknown, Value::Bit(_)) => number(&text).unwrap_or_else(|| string(&text)),
+        (FieldType::Json, _) => match serde_json::from_slice::<serde_json::Value>(&text) {
+            Ok(document) => document.to_string(),
+            Err(_) => string(&text),
+        },
+        _ => string(&text),
+    }
+}
+
+/// A values tuple as a JSON object on a line, keyed by the column names.
+/// Fields past the known columns are keyed by their position.
+pub fn row(tuple: &[u8], columns: &[(String, FieldType)]) -> Vec<u8> {
+    let mut out = String::from("{");
+    for (index, value) in Fields::new(tuple).enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+
+        let (name, field_type) = match columns.get(index) {
+            Some((name, field_type)) => (name.clone(), *field_type),
+            None => ((index + 1).to_string(), FieldType::Unknown),
+        };
+        out.push_str(&string(name.as_bytes()));
+        out.push(':');
+        out.push_str(&field(&value, field_type));
+    }
+    out.push_str("}\n");
+    out.into_bytes()
+}
+
+
+#[cfg(test)]
+mod json_test{
+    use super::*;
+
+    #[test]
+    fn base64s(){
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(&[0, 255, 16, 1]), "AP8QAQ==");
+    }
+
+    #[test]
+    fn rows(){
+        let columns = vec![
+            ("id".to_string(), FieldType::Integer),
+            ("total".to_string(), FieldType::Decimal),
+            ("score".to_string(), FieldType::Float),
+            ("note".to_string(), FieldType::Text),
+            ("avatar".to_string(), FieldType::Binary),
+            ("tags".to_string(), FieldType::Json),
+            ("flags".to_string(), FieldType::Json),
+            ("paid".to_string(), FieldType::Integer),
+        ];
+        let row = |tuple: &[u8]| String::from_utf8(super::row(tuple, &columns)).unwrap();
+
+        assert_eq!(row(b"(18446744073709551615,19.90,1.5e3,'say \\\"hi\\\"\\n',0x00FF,'{\\\"a\\\": [1, 2]}','not json','1')"),
+            "{\"id\":18446744073709551615,\"total\":\"19.90\",\"score\":1500,\"note\":\"say \\\"hi\\\"\\n\",\
+            \"avatar\":\"AP8=\",\"tags\":{\"a\":[1,2]},\"flags\":\"not json\",\"paid\":1}\n");
+        assert_eq!(row(b"(NULL,NULL,NULL,NULL,NULL,NULL,NULL,NULL,'extra',7)"),
+            "{\"id\":null,\"total\":null,\"score\":null,\"note\":null,\"avatar\":null,\"tags\":null,\"flags\":null,\"paid\":null,\"9\":\"extra\",\"10\":7}\n");
+    }
+}
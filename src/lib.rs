@@ -0,0 +1,37 @@
+extern crate sha2;
+extern crate toml;
+extern crate serde_json;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(feature = "parquet")]
+extern crate arrow_array;
+#[cfg(feature = "parquet")]
+extern crate arrow_schema;
+
+pub mod reader;
+pub mod tokenizer;
+pub mod parser;
+pub mod statement;
+pub mod value;
+pub mod filter;
+pub mod splitter;
+pub mod output;
+pub mod mask;
+pub mod predicate;
+pub mod subset;
+pub mod sample;
+pub mod rename;
+pub mod rewrite;
+pub mod postgres;
+pub mod sqlite;
+pub mod delimited;
+pub mod json;
+pub mod dataset;
+pub mod input;
+pub mod merge;
+pub mod manifest;
+pub mod verify;
+pub mod checkpoint;
+pub mod index;
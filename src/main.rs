@@ -1,69 +1,230 @@
 #[macro_use] 
 extern crate clap;
-mod parser;
-mod splitter;
-mod reader;
+extern crate sql_split;
 mod cli;
-mod tokenizer;
 
 use std::str;
 use std::process;
-use std::fs::File;
-use std::io::prelude::*;
-use splitter::SplitterSettings;
-use splitter::SplitterState;
-use splitter::Splitter;
+use std::fs::{self, File};
+use std::path::Path;
+use std::io::BufWriter;
+use cli::Args;
+use cli::Command;
+use cli::Invalid;
+use cli::MergeArgs;
+use cli::VerifyArgs;
+use cli::IndexArgs;
+use cli::ExtractArgs;
+use sql_split::verify::Summary;
+use sql_split::splitter::SplitterSettings;
+use sql_split::splitter::SplitterState;
+use sql_split::splitter::Splitter;
+use sql_split::output::Output;
+use sql_split::sqlite::Database;
+use sql_split::subset::Selection;
+use sql_split::manifest::Manifest;
+use sql_split::checkpoint::{self, Checkpoint};
+use sql_split::index::{Fingerprint, Pick};
+use std::io::{self, Seek, SeekFrom, Write};
 
 fn log_error(err: &str) -> ! {
     eprintln!("{}", err);
     process::exit(0)
 }
 
-fn create_file(name: usize) -> File {
-    let file_name = format!("./{:?}.sql", name);
-    File::create(file_name).unwrap()
+// the subcommands exit with 1 on every error
+fn fail(err: &str) -> ! {
+    eprintln!("{}", err);
+    process::exit(1)
 }
 
-
 fn main(){
-    let (file, write_buffer) = cli::args();
+    match cli::command() {
+        Ok(Command::Split(args)) => split(*args),
+        Ok(Command::Merge(args)) => merge(args),
+        Ok(Command::Verify(args)) => verify(args),
+        Ok(Command::Index(args)) => index(args),
+        Ok(Command::Extract(args)) => extract(args),
+        Err(Invalid::Split(e)) => log_error(&e),
+        Err(Invalid::Subcommand(e)) => fail(&e),
+    }
+}
 
-    let file = match file {
+fn merge(args: MergeArgs) {
+    let file = match File::create(&args.output) {
         Ok(file) => file,
-        Err(e) => log_error(e),
+        Err(_) => fail("Unable to create the output file"),
     };
+    if let Err(e) = sql_split::merge::merge(&args.chunks, args.dialect, &mut BufWriter::new(file)) {
+        fail(e.text)
+    }
+}
 
-    let write_buffer = match write_buffer {
+fn verify(args: VerifyArgs) {
+    let dump = match Summary::dump(args.file, args.dialect) {
+        Ok(summary) => summary,
+        Err(e) => fail(e.text),
+    };
+    let chunks = match Summary::chunks(&args.chunks, args.dialect) {
+        Ok(summary) => summary,
+        Err(e) => fail(&e),
+    };
+
+    let differences = dump.differences(&chunks);
+    if differences.is_empty() {
+        println!("{}", chunks.checked(args.chunks.len()));
+    }else{
+        fail(&differences.join("\n"))
+    }
+}
+
+fn index(mut args: IndexArgs) {
+    let fingerprint = match Fingerprint::of(&mut args.file) {
+        Ok(fingerprint) => fingerprint,
+        Err(_) => fail("Unable to read the dump"),
+    };
+    let file = match File::create(&args.index) {
         Ok(file) => file,
-        Err(e) => log_error(e.as_str()),
+        Err(_) => fail("Unable to create the index file"),
+    };
+    let dump = io::BufReader::new(args.file);
+    match sql_split::index::index(dump, args.dialect, &args.source, &fingerprint, &mut BufWriter::new(file)) {
+        Ok(statements) => println!("{} statements in {}", statements, args.index),
+        Err(e) => fail(e.text),
+    }
+}
+
+fn extract(mut args: ExtractArgs) {
+    let index = match File::open(&args.index) {
+        Ok(index) => io::BufReader::new(index),
+        Err(_) => fail("There is no index of the dump, run `sql-split index` first"),
+    };
+    let stdout = io::stdout();
+    match sql_split::index::extract(&mut args.file, index, &args.pick, &mut BufWriter::new(stdout.lock())) {
+        Ok(0) => if let Pick::Statement(number) = args.pick {
+            fail(&format!("There is no statement {} in the index", number))
+        },
+        Ok(_) => {},
+        Err(e) => fail(e.text),
+    }
+}
+
+fn split(args: Args) {
+    let mut file = args.file;
+    let selection = if args.subset.is_empty() {
+        Selection::default()
+    }else{
+        match args.subset.plan(&mut file) {
+            Ok(selection) => selection,
+            Err(e) => log_error(e.text),
+        }
+    };
+
+    let resumed = if args.resume {
+        let checkpoint = match Checkpoint::load() {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => log_error(&e),
+        };
+        if checkpoint.arguments != args.arguments {
+            log_error("The split in checkpoint.json was started with other arguments")
+        }
+        if file.seek(SeekFrom::Start(checkpoint.offset)).is_err() {
+            log_error("Unable to seek the dump to the checkpoint")
+        }
+        Some(checkpoint)
+    }else{
+        None
     };
 
     let mut splitter = Splitter::new(SplitterSettings {
-        write: write_buffer,
+        write: args.write,
         file,
+        filter: args.filter,
+        mode: args.mode,
+        layout: args.layout,
+        masks: args.masks,
+        row_filter: args.row_filter,
+        selection,
+        sampling: args.sampling,
+        renames: args.renames,
+        rewrites: args.rewrites,
+        to: args.to,
+        format: args.format,
+        delimited: args.delimited,
+        strict: args.strict,
+        dialect: args.dialect,
     });
 
-    let mut file_count = 1;
-    let mut buffer = create_file(file_count);
-    let mut first_file = true;
+    let mut output = Output::for_format(args.format);
+    if let Some(ref checkpoint) = resumed {
+        splitter.resume(checkpoint);
+        if output.resume(checkpoint.part, checkpoint.size).is_err() {
+            log_error("Unable to go on with the files of the checkpoint")
+        }
+    }
+    let mut checkpointed = resumed.map_or(0, |checkpoint| checkpoint.offset);
+    let arguments = args.arguments;
+    let mut manifest = if args.manifest { Some(Manifest::default()) } else { None };
+    let mut database = match args.sqlite {
+        Some(path) => match Database::open(&path) {
+            Ok(database) => Some(database),
+            Err(e) => log_error(&e),
+        },
+        None => None,
+    };
 
     loop {
         match splitter.process() {
-            SplitterState::Chunk(file_state, tokens) => {
-                if file_state == splitter::FileState::New {
-                    if first_file == true {
-                        first_file = false;
-                        continue;
+            SplitterState::Chunk(target, file_state, tokens) => {
+                let written = match database {
+                    Some(ref mut database) => database.write(&tokens),
+                    None => output.write(&target, file_state, &tokens).map_err(|e| e.to_string()),
+                };
+                if let Err(e) = written {
+                    log_error(&e)
+                }
+                if let (Some(ref mut manifest), Some(path)) = (&mut manifest, output.current()) {
+                    manifest.record(path, &tokens, splitter.tally());
+                }
+                if let Some(interval) = args.checkpoint {
+                    if splitter.tally().source.1 - checkpointed >= interval as u64 {
+                        if let Some(mut checkpoint) = splitter.checkpoint() {
+                            let saved = output.checkpoint().and_then(|(part, size)| {
+                                checkpoint.part = part;
+                                checkpoint.size = size;
+                                checkpoint.arguments = arguments.clone();
+                                checkpoint.save()
+                            });
+                            if saved.is_err() {
+                                log_error("Unable to write checkpoint.json")
+                            }
+                            checkpointed = checkpoint.offset;
+                        }
                     }
-
-                    file_count += 1;
-                    buffer = create_file(file_count);
                 }
-
-                buffer.write_all(&tokens).unwrap();
             },
             SplitterState::SyntaxErr(e) => log_error(e.text),
             SplitterState::Done => break,
         }
     }
+
+    let finished = match database {
+        Some(ref mut database) => database.finish(),
+        None => output.finish().map_err(|e| e.to_string()),
+    };
+    if let Err(e) = finished {
+        log_error(&e)
+    }
+
+    // nothing is left to resume
+    if args.checkpoint.is_some() && Path::new(checkpoint::FILE).exists() && fs::remove_file(checkpoint::FILE).is_err() {
+        log_error("Unable to remove checkpoint.json")
+    }
+
+    if let Some(manifest) = manifest {
+        let json = manifest.json(&args.source, splitter.preamble());
+        if File::create("manifest.json").and_then(|mut file| file.write_all(json.as_bytes())).is_err() {
+            fail("Unable to write manifest.json")
+        }
+    }
 }
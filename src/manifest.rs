@@ -0,0 +1,136 @@
+use splitter::Tally;
+use sha2::{Digest, Sha256};
+use serde_json;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// a file written by the split
+struct Entry {
+    file: String,
+    bytes: u64,
+    hasher: Sha256,
+    rows: BTreeMap<String, u64>,
+    statements: u64,
+    // first and last byte of the dump that went into it
+    source: (u64, u64),
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Files of a split with their size, SHA-256, rows of each table,
+/// statements and the part of the dump they came from.
+#[derive(Default)]
+pub struct Manifest {
+    entries: Vec<Entry>,
+    index: HashMap<String, usize>,
+}
+
+impl Manifest {
+    pub fn record(&mut self, path: &Path, tokens: &[u8], tally: &Tally) {
+        let file = path.to_string_lossy().trim_start_matches("./").to_string();
+        let entries = &mut self.entries;
+        let index = *self.index.entry(file.clone()).or_insert_with(|| {
+            entries.push(Entry {
+                file,
+                bytes: 0,
+                hasher: Sha256::new(),
+                rows: BTreeMap::new(),
+                statements: 0,
+                source: tally.source,
+            });
+            entries.len() - 1
+        });
+
+        let entry = &mut self.entries[index];
+        entry.bytes += tokens.len() as u64;
+        entry.hasher.update(tokens);
+        entry.statements += tally.statements;
+        entry.source = (entry.source.0.min(tally.source.0), entry.source.1.max(tally.source.1));
+        if tally.rows > 0 {
+            let table = tally.table.as_ref().map(|table| table.to_string()).unwrap_or_default();
+            *entry.rows.entry(table).or_insert(0) += tally.rows;
+        }
+    }
+
+    /// `manifest.json`, with the dump split and the preamble repeated in its files.
+    pub fn json(self, source: &str, preamble: &[u8]) -> String {
+        let files: Vec<serde_json::Value> = self.entries.into_iter().map(|entry| json!({
+            "file": entry.file,
+            "bytes": entry.bytes,
+            "sha256": hex(&entry.hasher.finalize()),
+            "rows": entry.rows,
+            "statements": entry.statements,
+            "first_offset": entry.source.0,
+            "last_offset": entry.source.1,
+        })).collect();
+
+        let manifest = json!({
+            "source": source,
+            "preamble": String::from_utf8_lossy(preamble),
+            "files": files,
+        });
+        serde_json::to_string_pretty(&manifest).unwrap_or_default()
+    }
+}
+
+
+#[cfg(test)]
+mod manifest_test{
+    use super::*;
+    use parser::Table;
+    use splitter::{Splitter, SplitterSettings, SplitterState, FileState};
+    use splitter::splitter_test::settings;
+    use std::path::PathBuf;
+
+    #[test]
+    fn records_files(){
+        let mut manifest = Manifest::default();
+        let users = Tally { source: (10, 40), table: Some(Table { schema: None, name: "users".to_string() }), rows: 2, statements: 1 };
+        manifest.record(Path::new("./1.sql"), b"INSERT INTO users VALUES (1),(2);", &users);
+        manifest.record(Path::new("./1.sql"), b"\n", &Tally { source: (40, 41), ..Tally::default() });
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.json("dump.sql", b"")).unwrap();
+        let file = &json["files"][0];
+        assert_eq!(file["file"], "1.sql");
+        assert_eq!(file["bytes"], 34);
+        assert_eq!(file["rows"]["users"], 2);
+        assert_eq!(file["statements"], 1);
+        assert_eq!((file["first_offset"].clone(), file["last_offset"].clone()), (json!(10), json!(41)));
+        assert_eq!(file["sha256"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn tallies_a_split(){
+        let dump = "CREATE TABLE t (a int);\nINSERT INTO t VALUES (1),\n(2),\n(3),\n(4);\n";
+        let mut splitter = Splitter::new(SplitterSettings { write: 30, ..settings(dump) });
+
+        let mut manifest = Manifest::default();
+        let mut parts = 0;
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(_, file_state, tokens) => {
+                    if file_state == FileState::New {
+                        parts += 1;
+                    }
+                    manifest.record(&PathBuf::from(format!("{}.sql", parts)), &tokens, splitter.tally());
+                },
+                SplitterState::Done => break,
+                _ => panic!("split failed"),
+            }
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.json("dump.sql", b"")).unwrap();
+        let files = json["files"].as_array().unwrap();
+        let rows: Vec<u64> = files.iter().map(|file| file["rows"]["t"].as_u64().unwrap()).collect();
+        let statements: Vec<u64> = files.iter().map(|file| file["statements"].as_u64().unwrap()).collect();
+        assert_eq!(rows, vec![1, 2, 1]);
+        // the insert starts over in every file it continues in
+        assert_eq!(statements, vec![2, 1, 1]);
+        assert_eq!(files[1]["first_offset"], 49);
+        assert_eq!(files.last().unwrap()["last_offset"], dump.len());
+        assert_eq!(files[0]["first_offset"], 0);
+    }
+}
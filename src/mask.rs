@@ -0,0 +1,339 @@
+use filter::TableFilter;
+use parser::Table;
+use statement::DataType;
+use value::Value;
+use value::parse;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use toml;
+
+const FIRST_NAMES: &[&str] = &[
+    "James", "Mary", "John", "Patricia", "Robert", "Jennifer", "Michael", "Linda",
+    "William", "Elizabeth", "David", "Barbara", "Richard", "Susan", "Joseph", "Jessica",
+    "Thomas", "Sarah", "Charles", "Karen", "Daniel", "Nancy", "Matthew", "Lisa",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis",
+    "Rodriguez", "Martinez", "Hernandez", "Lopez", "Wilson", "Anderson", "Thomas", "Taylor",
+    "Moore", "Jackson", "Martin", "Lee", "Thompson", "White", "Harris", "Clark",
+];
+
+const CITIES: &[&str] = &[
+    "Springfield", "Riverside", "Franklin", "Greenville", "Bristol", "Clinton",
+    "Fairview", "Salem", "Madison", "Georgetown", "Arlington", "Ashland",
+];
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Fake {
+    Name,
+    FirstName,
+    LastName,
+    Phone,
+    City,
+}
+
+/// What a masked column is replaced with. NULL stays NULL.
+#[derive(Debug,PartialEq,Clone)]
+pub enum Rule {
+    // `*` for every character of strings, zero for numbers
+    Redact,
+    // `user_` and the first 128 bits of the sha256 of the value in hex,
+    // so distinct addresses don't end up the same
+    FakeEmail,
+    // hex sha256 of the salt and the value, numbers
+    // stay numbers in the range of their column
+    Hash(String),
+    Faker(Fake),
+}
+
+/// Masking rules of `--mask-config`, like
+///
+/// ```toml
+/// [users]
+/// email = "fake_email"
+/// phone = "hash(sha256, salt)"
+/// name = "faker(name)"
+///
+/// [payments]
+/// card = "redact"
+/// ```
+///
+/// Table names may be schema qualified and support `*` and `?` wildcards.
+/// The same value always masks to the same output. Hashed numbers are
+/// cut to the range of their column type, so keys that reference each
+/// other stay consistent across tables when both columns have the same
+/// type, and stay unique only for `bigint` columns.
+#[derive(Debug,Default)]
+pub struct Masks {
+    rules: Vec<(TableFilter, String, Rule)>,
+}
+
+fn rule(text: &str) -> Result<Rule, String> {
+    let text = text.trim();
+    let (name, args) = match text.find('(') {
+        Some(index) if text.ends_with(')') => {
+            let args = text[index + 1..text.len() - 1]
+                .split(',')
+                .map(|arg| arg.trim().trim_matches(|c| c == '\'' || c == '"'))
+                .filter(|arg| !arg.is_empty())
+                .collect();
+            (text[..index].trim(), args)
+        },
+        _ => (text, vec![]),
+    };
+
+    match (name, args.as_slice()) {
+        ("redact", []) => Ok(Rule::Redact),
+        ("fake_email", []) => Ok(Rule::FakeEmail),
+        ("hash", ["sha256"]) => Ok(Rule::Hash(String::new())),
+        ("hash", ["sha256", salt]) => Ok(Rule::Hash(salt.to_string())),
+        ("hash", _) => Err(format!("{}: only hash(sha256) and hash(sha256, salt) are supported", text)),
+        ("faker", [kind]) => {
+            let fake = match *kind {
+                "name" => Fake::Name,
+                "first_name" => Fake::FirstName,
+                "last_name" => Fake::LastName,
+                "phone" => Fake::Phone,
+                "city" => Fake::City,
+                _ => return Err(format!("{}: unknown faker, choose from name, first_name, last_name, phone or city", text)),
+            };
+            Ok(Rule::Faker(fake))
+        },
+        _ => Err(format!("{}: unknown mask. choose from redact, fake_email, hash(sha256, salt) or faker(name)", text)),
+    }
+}
+
+impl Masks {
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let value = match config.parse::<toml::Value>() {
+            Ok(value) => value,
+            Err(e) => return Err(format!("invalid mask config: {}", e)),
+        };
+
+        let mut masks = Self::default();
+        masks.collect(&mut vec![], &value)?;
+        Ok(masks)
+    }
+
+    // `[shop.users]` and `users.email = ..` are nested tables in toml,
+    // the last key is the column and the ones before it the table
+    fn collect(&mut self, path: &mut Vec<String>, value: &toml::Value) -> Result<(), String> {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    path.push(key.clone());
+                    self.collect(path, value)?;
+                    path.pop();
+                }
+                Ok(())
+            },
+            toml::Value::String(text) if path.len() > 1 => {
+                let column = path[path.len() - 1].clone();
+                let table = path[..path.len() - 1].join(".");
+                self.rules.push((TableFilter::new(vec![table], vec![]), column, rule(text)?));
+                Ok(())
+            },
+            _ => Err(format!("invalid mask for {}, expected table.column = \"rule\"", path.join("."))),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules for the fields of a table's rows, by position.
+    pub fn positions(&self, table: &Table, columns: &[String]) -> Vec<(usize, Rule)> {
+        let mut positions = vec![];
+        for (filter, column, rule) in &self.rules {
+            if !filter.allows(table) {
+                continue
+            }
+
+            if let Some(position) = columns.iter().position(|name| name.eq_ignore_ascii_case(column)) {
+                positions.push((position, rule.clone()));
+            }
+        }
+        positions.sort_by_key(|(position, _)| *position);
+        positions
+    }
+}
+
+fn digest(salt: &str, text: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(text);
+    hasher.finalize().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn pick<'a>(list: &[&'a str], byte: u8) -> &'a str {
+    list[byte as usize % list.len()]
+}
+
+// hashed numbers of a column whose type isn't known fit a signed int
+const INT: i64 = 0x7fff_ffff;
+
+/// Largest number a column of the type holds, None when it isn't a number.
+pub fn largest(data_type: &DataType) -> Option<i64> {
+    let unsigned = data_type.unsigned;
+    let bits = match data_type.name.as_str() {
+        "tinyint" | "bool" | "boolean" if unsigned => 8,
+        "tinyint" | "bool" | "boolean" => 7,
+        "smallint" if unsigned => 16,
+        "smallint" => 15,
+        "mediumint" if unsigned => 24,
+        "mediumint" => 23,
+        "int" | "integer" if unsigned => 32,
+        "int" | "integer" => 31,
+        "bigint" => 63,
+        // integers a float holds exactly
+        "float" => 24,
+        "double" | "real" => 53,
+        "decimal" | "numeric" | "dec" | "fixed" => {
+            let arg = |index: usize, default| data_type.args.get(index).and_then(|arg| arg.trim().parse::<u32>().ok()).unwrap_or(default);
+            let digits = arg(0, 10).saturating_sub(arg(1, 0));
+            return Some(10i64.checked_pow(digits).map_or(i64::MAX, |bound| bound - 1))
+        },
+        _ => return None,
+    };
+    Some(((1u64 << bits) - 1) as i64)
+}
+
+// the first 64 bits of the digest as a number from 0 to largest
+fn number(digest: &[u8], largest: i64) -> i64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) % (largest as u64 + 1)) as i64
+}
+
+/// Masks a field as written in a values tuple, hashed numbers are
+/// at most `largest`, the largest number their column holds.
+pub fn mask(rule: &Rule, raw: &[u8], largest: Option<i64>) -> Vec<u8> {
+    let value = parse(raw);
+    let text = match value.text() {
+        Some(text) => text.into_owned(),
+        None => return raw.to_vec(),
+    };
+
+    let numeric = matches!(value, Value::Int(_) | Value::Decimal(_) | Value::Float(_));
+    let masked = match rule {
+        Rule::Redact => {
+            match value {
+                Value::Int(_) | Value::Decimal(_) | Value::Float(_) => Value::Int(0),
+                Value::Bit(_) => Value::Bit(0),
+                Value::Hex(bytes) => Value::Hex(Cow::Owned(vec![0; bytes.len()])),
+                // not data, like `NOW()`
+                Value::Expression(_) => return raw.to_vec(),
+                _ => {
+                    let count = String::from_utf8_lossy(&text).chars().count();
+                    Value::String(Cow::Owned(vec![b'*'; count]))
+                },
+            }
+        },
+        Rule::FakeEmail => {
+            let digest = digest("", &text);
+            Value::String(Cow::Owned(format!("user_{}@example.com", hex(&digest[..16])).into_bytes()))
+        },
+        Rule::Hash(salt) => {
+            let digest = digest(salt, &text);
+            if numeric {
+                Value::Int(number(&digest, largest.unwrap_or(INT)))
+            }else{
+                Value::String(Cow::Owned(hex(&digest).into_bytes()))
+            }
+        },
+        Rule::Faker(fake) => {
+            let digest = digest("", &text);
+            let fake = match fake {
+                Fake::Name => format!("{} {}", pick(FIRST_NAMES, digest[0]), pick(LAST_NAMES, digest[1])),
+                Fake::FirstName => pick(FIRST_NAMES, digest[0]).to_string(),
+                Fake::LastName => pick(LAST_NAMES, digest[1]).to_string(),
+                Fake::Phone => format!("555-{:04}", number(&digest, 9999)),
+                Fake::City => pick(CITIES, digest[2]).to_string(),
+            };
+            Value::String(Cow::Owned(fake.into_bytes()))
+        },
+    };
+
+    masked.to_sql()
+}
+
+
+#[cfg(test)]
+mod mask_test{
+    use parser::Table;
+    use super::*;
+
+    fn table(name: &str) -> Table {
+        Table { schema: None, name: name.to_string() }
+    }
+
+    #[test]
+    fn config(){
+        let masks = Masks::parse("users.email = \"fake_email\"\n\
+            [payments]\n\
+            card = \"redact\"\n\
+            [shop.users]\n\
+            phone = \"hash(sha256, s3cret)\"\n").unwrap();
+
+        let columns: Vec<String> = vec!["id", "email", "phone"].into_iter().map(String::from).collect();
+        let both = vec![(1, Rule::FakeEmail), (2, Rule::Hash("s3cret".to_string()))];
+        let shop = Table { schema: Some("shop".to_string()), name: "users".to_string() };
+        let other = Table { schema: Some("other".to_string()), name: "users".to_string() };
+        assert_eq!(masks.positions(&shop, &columns), both);
+        // unqualified tables belong to any schema
+        assert_eq!(masks.positions(&table("users"), &columns), both);
+        assert_eq!(masks.positions(&other, &columns), vec![(1, Rule::FakeEmail)]);
+        assert!(masks.positions(&table("orders"), &columns).is_empty());
+
+        assert!(Masks::parse("users.email = \"md5\"").is_err());
+        assert!(Masks::parse("users.email = \"hash(md5)\"").is_err());
+    }
+
+    #[test]
+    fn deterministic(){
+        let email = mask(&Rule::FakeEmail, b"'ann@example.com'", None);
+        assert_eq!(email, mask(&Rule::FakeEmail, b"'ann@example.com'", None));
+        assert_ne!(email, mask(&Rule::FakeEmail, b"'bob@example.com'", None));
+        assert!(email.starts_with(b"'user_") && email.ends_with(b"@example.com'"));
+        assert_eq!(email.len(), "'user_@example.com'".len() + 32);
+
+        // numbers stay numbers
+        assert!(matches!(parse(&mask(&Rule::Hash("s".to_string()), b"42", None)), Value::Int(_)));
+        assert_ne!(mask(&Rule::Hash("a".to_string()), b"'x'", None), mask(&Rule::Hash("b".to_string()), b"'x'", None));
+
+        assert_eq!(mask(&Rule::Redact, b"'O\\'Neil'", None), b"'******'".to_vec());
+        assert_eq!(mask(&Rule::Redact, b"NULL", None), b"NULL".to_vec());
+        assert_eq!(mask(&Rule::Faker(Fake::Name), b"'Ann'", None), mask(&Rule::Faker(Fake::Name), b"'Ann'", None));
+    }
+
+    #[test]
+    fn numbers(){
+        let data_type = |name: &str, args: &[&str], unsigned| DataType {
+            name: name.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            unsigned,
+        };
+        assert_eq!(largest(&data_type("tinyint", &[], false)), Some(127));
+        assert_eq!(largest(&data_type("tinyint", &[], true)), Some(255));
+        assert_eq!(largest(&data_type("smallint", &[], false)), Some(32767));
+        assert_eq!(largest(&data_type("mediumint", &[], true)), Some(16777215));
+        assert_eq!(largest(&data_type("int", &[], true)), Some(4294967295));
+        assert_eq!(largest(&data_type("bigint", &[], true)), Some(i64::MAX));
+        assert_eq!(largest(&data_type("decimal", &["10", "2"], false)), Some(99999999));
+        assert_eq!(largest(&data_type("decimal", &["40"], false)), Some(i64::MAX));
+        assert_eq!(largest(&data_type("varchar", &["10"], false)), None);
+
+        // hashed numbers fit their column
+        let salt = Rule::Hash("s".to_string());
+        for value in 0..200 {
+            let hashed = mask(&salt, value.to_string().as_bytes(), Some(127));
+            assert!(matches!(parse(&hashed), Value::Int(0..=127)));
+        }
+        assert_eq!(mask(&salt, b"42", Some(i64::MAX)), mask(&salt, b"42", Some(i64::MAX)));
+    }
+}
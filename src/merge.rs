@@ -0,0 +1,241 @@
+use parser::Parser;
+use parser::TokenStream;
+use parser::Kind;
+use reader::Reader;
+use splitter::Dialect;
+use tokenizer::Tokenizer;
+use tokenizer::SyntaxErr;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const COMMIT: &[u8] = b"\nCOMMIT;\n";
+const COPY_END: &[u8] = b"\\.\n";
+
+fn unreadable() -> SyntaxErr {
+    SyntaxErr{ text: "Unable to read a chunk." }
+}
+
+/// The numbered files of the chunks layout, `1.sql`, `2.sql` and on.
+pub fn chunks(directory: &Path) -> Result<Vec<PathBuf>, SyntaxErr> {
+    let mut numbered = vec![];
+    for entry in fs::read_dir(directory).map_err(|_| unreadable())? {
+        let path = entry.map_err(|_| unreadable())?.path();
+        let number = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".sql"))
+            .and_then(|stem| stem.parse::<u64>().ok());
+        if let Some(number) = number {
+            numbered.push((number, path));
+        }
+    }
+
+    if numbered.is_empty() {
+        return Err(SyntaxErr{ text: "The directory has no numbered chunks." })
+    }
+    numbered.sort();
+    Ok(numbered.into_iter().map(|(_, path)| path).collect())
+}
+
+// what the splitter repeats at the start of the file after this one
+#[derive(Debug,Default)]
+struct Ending {
+    // head of the insert the file ends in
+    insert: Option<Vec<u8>>,
+    // `COPY .. FROM stdin;` line of the rows the file ends in
+    copy: Option<Vec<u8>>,
+    delimiter: Vec<u8>,
+    // sqlite pragmas and the `BEGIN TRANSACTION` every file starts with
+    envelope: Vec<u8>,
+}
+
+fn ending<R: Read>(file: R, dialect: Dialect) -> Result<Ending, SyntaxErr> {
+    let mut parser = Parser::new(Tokenizer::for_dialect(Reader::new(file), dialect));
+    let mut ending = Ending::default();
+    let mut pragmas = vec![];
+    let mut opening = dialect == Dialect::Sqlite;
+
+    while let Some(item) = parser.token_stream()? {
+        match item {
+            TokenStream::Insert(_, insert_stmt, _) => {
+                ending.insert = Some(insert_stmt);
+                ending.copy = None;
+            },
+            TokenStream::Copy(header, _) => {
+                ending.copy = Some(header);
+                ending.insert = None;
+            },
+            // white space between the tuples comes on its own
+            TokenStream::ValuesTuple(_) | TokenStream::CopyRow(_) | TokenStream::CopyEnd(_) |
+            TokenStream::Comment(_) | TokenStream::SpaceOrLineFeed(_) => {},
+            TokenStream::Block(kind, tokens, _) => {
+                if opening && kind == Kind::Other && tokens.len() > 6 && tokens[..6].eq_ignore_ascii_case(b"pragma") {
+                    pragmas.extend(&tokens);
+                    pragmas.push(b'\n');
+                }else if opening && kind == Kind::Transaction && tokens[0].eq_ignore_ascii_case(&b'b') {
+                    ending.envelope = ::std::mem::take(&mut pragmas);
+                    ending.envelope.extend(&tokens);
+                    ending.envelope.push(b'\n');
+                    opening = false;
+                }else{
+                    opening = false;
+                }
+                ending.insert = None;
+                ending.copy = None;
+            },
+        }
+    }
+    ending.delimiter = parser.delimiter().to_vec();
+    Ok(ending)
+}
+
+// a file of the merge, written from `skip` up to `end` followed by `append`
+struct Piece {
+    path: PathBuf,
+    skip: u64,
+    end: u64,
+    append: &'static [u8],
+}
+
+fn head(path: &Path, length: usize) -> Result<Vec<u8>, SyntaxErr> {
+    let mut head = vec![];
+    File::open(path).and_then(|file| file.take(length as u64).read_to_end(&mut head)).map_err(|_| unreadable())?;
+    Ok(head)
+}
+
+fn tail(path: &Path, end: u64, length: u64) -> Result<Vec<u8>, SyntaxErr> {
+    let length = length.min(end);
+    let mut tail = vec![];
+    File::open(path).and_then(|mut file| {
+        file.seek(SeekFrom::Start(end - length))?;
+        file.take(length).read_to_end(&mut tail)
+    }).map_err(|_| unreadable())?;
+    Ok(tail)
+}
+
+// takes back what the split of a file added at its end and the start of the next one
+fn join(before: &mut Piece, ending: &Ending, envelope: &[u8], next: &Path) -> Result<u64, SyntaxErr> {
+    let delimiter_line = [&b"DELIMITER "[..], &ending.delimiter, b"\n"].concat();
+    let head_length = ending.insert.as_ref().or(ending.copy.as_ref()).map_or(0, |head| head.len());
+    let next_head = head(next, envelope.len() + delimiter_line.len() + head_length)?;
+    let mut skip = 0;
+
+    // the transaction of a sqlite dump is committed at the end of every file
+    if !envelope.is_empty() && next_head.starts_with(envelope) &&
+        tail(&before.path, before.end, COMMIT.len() as u64)? == COMMIT {
+        before.end -= COMMIT.len() as u64;
+        skip += envelope.len();
+    }
+
+    if ending.delimiter != b";" && next_head[skip..].starts_with(&delimiter_line) {
+        skip += delimiter_line.len();
+    }
+
+    // an insert cut in its values ends with `;` instead of `,`
+    if let Some(ref insert) = ending.insert {
+        if next_head[skip..].starts_with(insert) && tail(&before.path, before.end, 1)? == b";" {
+            before.end -= 1;
+            before.append = b",";
+            skip += insert.len();
+        }
+    }
+
+    if let Some(ref copy) = ending.copy {
+        if next_head[skip..].starts_with(copy) && tail(&before.path, before.end, COPY_END.len() as u64)? == COPY_END {
+            before.end -= COPY_END.len() as u64;
+            skip += copy.len();
+        }
+    }
+    Ok(skip as u64)
+}
+
+/// Joins the chunks of a split back into one dump. What the split repeats
+/// at the start of a file is left out: the head of an insert or a `COPY` cut
+/// by the file before, the `DELIMITER` in use and the sqlite `BEGIN TRANSACTION`,
+/// and what closed the file before, its `;`, `\.` or `COMMIT`, is taken back.
+pub fn merge<W: Write>(files: &[PathBuf], dialect: Dialect, out: &mut W) -> Result<(), SyntaxErr> {
+    let mut envelope = vec![];
+    let mut before: Option<(Piece, Ending)> = None;
+
+    for (index, path) in files.iter().enumerate() {
+        let file = File::open(path).map_err(|_| unreadable())?;
+        let length = file.metadata().map_err(|_| unreadable())?.len();
+        let ending = ending(io::BufReader::new(file), dialect)?;
+        if index == 0 {
+            envelope = ending.envelope.clone();
+        }
+
+        let mut piece = Piece { path: path.clone(), skip: 0, end: length, append: b"" };
+        if let Some((mut previous, previous_ending)) = before.take() {
+            piece.skip = join(&mut previous, &previous_ending, &envelope, path)?;
+            write(&previous, out)?;
+        }
+        before = Some((piece, ending));
+    }
+
+    if let Some((last, _)) = before {
+        write(&last, out)?;
+    }
+    out.flush().map_err(|_| SyntaxErr{ text: "Unable to write the merged dump." })
+}
+
+fn write<W: Write>(piece: &Piece, out: &mut W) -> Result<(), SyntaxErr> {
+    let mut file = File::open(&piece.path).map_err(|_| unreadable())?;
+    file.seek(SeekFrom::Start(piece.skip)).map_err(|_| unreadable())?;
+    io::copy(&mut file.take(piece.end.saturating_sub(piece.skip)), out)
+        .and_then(|_| out.write_all(piece.append))
+        .map_err(|_| SyntaxErr{ text: "Unable to write the merged dump." })
+}
+
+
+#[cfg(test)]
+mod merge_test{
+    use super::*;
+    use splitter::SplitterSettings;
+    use splitter::splitter_test::{settings, split as split_dump};
+
+    // splits the dump into numbered files of a temporary directory
+    fn split(name: &str, dump: &str, write: usize, dialect: Dialect) -> Vec<PathBuf> {
+        let directory = ::std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let files = split_dump(SplitterSettings { write, dialect, ..settings(dump) });
+        files.iter().enumerate().map(|(index, (_, bytes))| {
+            let path = directory.join(format!("{}.sql", index + 1));
+            fs::write(&path, bytes).unwrap();
+            path
+        }).collect()
+    }
+
+    fn merged(files: &[PathBuf], dialect: Dialect) -> String {
+        let mut out = vec![];
+        merge(files, dialect, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn merges_cut_inserts(){
+        let dump = "DELIMITER ;;\nCREATE TRIGGER t1 BEFORE INSERT ON t FOR EACH ROW SET NEW.a = 1;;\n\
+            INSERT INTO `t` VALUES (1,'a'),\n(2,'b'),\n(3,'c'),\n(4,'d');;\nDELIMITER ;\n\
+            INSERT INTO `u` VALUES (5),(6),(7);\n";
+        let files = split("sql-split-merge-inserts", dump, 20, Dialect::Mysql);
+        assert!(files.len() > 3);
+        assert_eq!(merged(&files, Dialect::Mysql), dump);
+        assert_eq!(chunks(files[0].parent().unwrap()).unwrap(), files);
+    }
+
+    #[test]
+    fn merges_copy_and_transactions(){
+        let copy = "CREATE TABLE public.t (a integer);\n\nCOPY public.t (a) FROM stdin;\n1\n2\n3\n4\n\\.\n\n";
+        let files = split("sql-split-merge-copy", copy, 40, Dialect::Postgres);
+        assert!(files.len() > 2);
+        assert_eq!(merged(&files, Dialect::Postgres), copy);
+
+        let sqlite = "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE t(a);\n\
+            INSERT INTO t VALUES(1);\nINSERT INTO t VALUES(2);\nINSERT INTO t VALUES(3);\nCOMMIT;\n";
+        let files = split("sql-split-merge-sqlite", sqlite, 60, Dialect::Sqlite);
+        assert!(files.len() > 2);
+        assert_eq!(merged(&files, Dialect::Sqlite), sqlite);
+    }
+}
@@ -0,0 +1,314 @@
+use splitter::Target;
+use splitter::FileState;
+use splitter::Format;
+use parser::Table;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Writes the chunks of the splitter into their files.
+pub struct Output {
+    // last numbered file of the chunks layout
+    parts: usize,
+    // last data file of each table in the mydumper layout
+    data_files: HashMap<Table, usize>,
+    // tables in the order they show up, for the metadata file
+    tables: Vec<Table>,
+    schema: Vec<Table>,
+    post: Vec<Table>,
+    // views, routines and triggers files that were written
+    files: Vec<&'static str>,
+    current: Option<(PathBuf, BufWriter<File>)>,
+    format: Format,
+}
+
+// table names are used as is, apart from path separators
+fn file_name(table: &Table) -> String {
+    table.to_string()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Name of the `part`th file of rows of a table, the first
+/// one is just `users.csv` for the formats other than SQL.
+pub fn data_file(table: &Table, part: usize, format: Format) -> String {
+    match format {
+        Format::Sql => format!("data/{}.{:04}.sql", file_name(table), part),
+        format if part == 1 => format!("{}.{}", file_name(table), format.extension()),
+        format => format!("{}.{:04}.{}", file_name(table), part, format.extension()),
+    }
+}
+
+pub fn quoted(table: &Table) -> String {
+    let name = table.name.replace('`', "``");
+    match table.schema {
+        Some(ref schema) => format!("`{}`.`{}`", schema.replace('`', "``"), name),
+        None => format!("`{}`", name),
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self::for_format(Format::Sql)
+    }
+
+    pub fn for_format(format: Format) -> Self {
+        Self {
+            parts: 0,
+            data_files: HashMap::new(),
+            tables: vec![],
+            schema: vec![],
+            post: vec![],
+            files: vec![],
+            current: None,
+            format,
+        }
+    }
+
+    fn seen(&mut self, table: &Table) {
+        if !self.tables.contains(table) {
+            self.tables.push(table.clone());
+        }
+    }
+
+    fn path(&mut self, target: &Target, file_state: &FileState) -> PathBuf {
+        let new = *file_state == FileState::New;
+        let path = match target {
+            Target::Part => {
+                if new {
+                    self.parts += 1;
+                }
+                format!("./{}.sql", self.parts)
+            },
+            Target::Schema(table) => {
+                self.seen(table);
+                if !self.schema.contains(table) {
+                    self.schema.push(table.clone());
+                }
+                format!("schema/{}-schema.sql", file_name(table))
+            },
+            Target::SchemaPost(table) => {
+                self.seen(table);
+                if !self.post.contains(table) {
+                    self.post.push(table.clone());
+                }
+                format!("schema/{}-schema-post.sql", file_name(table))
+            },
+            Target::Data(table) => {
+                self.seen(table);
+                let count = self.data_files.entry(table.clone()).or_insert(0);
+                if new {
+                    *count += 1;
+                }
+                data_file(table, *count, self.format)
+            },
+            Target::Views |
+            Target::Routines |
+            Target::Triggers => {
+                let name = match target {
+                    Target::Views => "views.sql",
+                    Target::Routines => "routines.sql",
+                    _ => "triggers.sql",
+                };
+                if !self.files.contains(&name) {
+                    self.files.push(name);
+                }
+                name.to_string()
+            },
+            Target::LoadData => "load-data.sql".to_string(),
+        };
+
+        PathBuf::from(path)
+    }
+
+    pub fn write(&mut self, target: &Target, file_state: FileState, tokens: &[u8]) -> io::Result<()> {
+        let path = self.path(target, &file_state);
+        let reopen = match self.current {
+            Some((ref current, _)) => *current != path || file_state == FileState::New,
+            None => true,
+        };
+
+        if reopen {
+            if let Some((_, mut file)) = self.current.take() {
+                file.flush()?;
+            }
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // files of the mydumper layout are written in turns,
+            // only the first write of each one starts it over
+            let file = if file_state == FileState::New {
+                File::create(&path)?
+            }else{
+                OpenOptions::new().append(true).create(true).open(&path)?
+            };
+            self.current = Some((path, BufWriter::new(file)));
+        }
+
+        match self.current {
+            Some((_, ref mut file)) => file.write_all(tokens),
+            None => Ok(()),
+        }
+    }
+
+    /// Flushes the numbered file being written, for a checkpoint
+    /// of the split, and gives its number and size.
+    pub fn checkpoint(&mut self) -> io::Result<(usize, u64)> {
+        match self.current {
+            Some((ref path, ref mut file)) => {
+                file.flush()?;
+                Ok((self.parts, fs::metadata(path)?.len()))
+            },
+            None => Ok((self.parts, 0)),
+        }
+    }
+
+    /// Writes on into the `part`th numbered file from a checkpoint. It is cut
+    /// back to its size then, and the files written after it are removed.
+    pub fn resume(&mut self, part: usize, size: u64) -> io::Result<()> {
+        if part > 0 {
+            OpenOptions::new().write(true).open(format!("./{}.sql", part))?.set_len(size)?;
+        }
+
+        let mut next = part + 1;
+        while Path::new(&format!("./{}.sql", next)).exists() {
+            fs::remove_file(format!("./{}.sql", next))?;
+            next += 1;
+        }
+        self.parts = part;
+        Ok(())
+    }
+
+    /// File of the last write.
+    pub fn current(&self) -> Option<&Path> {
+        self.current.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Flushes the last file and writes the `metadata` file
+    /// of the mydumper layout.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some((_, mut file)) = self.current.take() {
+            file.flush()?;
+        }
+
+        match self.metadata() {
+            Some(metadata) => File::create("metadata")?.write_all(metadata.as_bytes()),
+            None => Ok(()),
+        }
+    }
+
+    // the tables and files of the mydumper layout, None for the numbered files
+    fn metadata(&self) -> Option<String> {
+        if self.tables.is_empty() && self.files.is_empty() || self.format != Format::Sql {
+            return None
+        }
+
+        let mut metadata = String::from("[config]\nlayout = mydumper\n");
+        for table in &self.tables {
+            metadata.push_str(&format!("\n[{}]\n", quoted(table)));
+            if self.schema.contains(table) {
+                metadata.push_str(&format!("schema = schema/{}-schema.sql\n", file_name(table)));
+            }
+            if self.post.contains(table) {
+                metadata.push_str(&format!("post = schema/{}-schema-post.sql\n", file_name(table)));
+            }
+            let data_files = self.data_files.get(table).cloned().unwrap_or(0);
+            metadata.push_str(&format!("data_files = {}\n", data_files));
+        }
+
+        if !self.files.is_empty() {
+            metadata.push_str("\n[files]\n");
+            for name in &self.files {
+                metadata.push_str(&format!("{} = {}\n", name.trim_end_matches(".sql"), name));
+            }
+        }
+        Some(metadata)
+    }
+}
+
+#[cfg(test)]
+mod output_test{
+    use super::*;
+
+    fn table(schema: Option<&str>, name: &str) -> Table {
+        Table { schema: schema.map(|schema| schema.to_string()), name: name.to_string() }
+    }
+
+    #[test]
+    fn parts(){
+        let mut output = Output::new();
+        assert_eq!(output.path(&Target::Part, &FileState::New), PathBuf::from("./1.sql"));
+        assert_eq!(output.path(&Target::Part, &FileState::Continue), PathBuf::from("./1.sql"));
+        assert_eq!(output.path(&Target::Part, &FileState::New), PathBuf::from("./2.sql"));
+        assert_eq!(output.metadata(), None);
+    }
+
+    #[test]
+    fn names(){
+        let users = table(Some("shop"), "users");
+        assert_eq!(file_name(&users), "shop.users");
+        assert_eq!(data_file(&users, 2, Format::Sql), "data/shop.users.0002.sql");
+        assert_eq!(data_file(&users, 1, Format::Csv), "shop.users.csv");
+        assert_eq!(data_file(&users, 2, Format::Tsv), "shop.users.0002.tsv");
+        assert_eq!(data_file(&users, 2, Format::Jsonl), "shop.users.0002.jsonl");
+        assert_eq!(file_name(&table(None, "a/b:c\td")), "a_b_c_d");
+        assert_eq!(quoted(&table(Some("shop"), "we`ird")), "`shop`.`we``ird`");
+    }
+
+    #[test]
+    fn mydumper(){
+        let users = table(None, "users");
+        let orders = table(None, "orders");
+        let mut output = Output::new();
+        let written = [
+            (Target::Schema(users.clone()), FileState::New),
+            (Target::Data(users.clone()), FileState::New),
+            (Target::Data(users.clone()), FileState::Continue),
+            (Target::Data(users.clone()), FileState::New),
+            (Target::Schema(orders.clone()), FileState::New),
+            (Target::SchemaPost(orders.clone()), FileState::New),
+            (Target::Views, FileState::New),
+            (Target::Views, FileState::Continue),
+        ];
+        let paths: Vec<PathBuf> = written.iter().map(|(target, file_state)| output.path(target, file_state)).collect();
+        assert_eq!(paths, vec![
+            PathBuf::from("schema/users-schema.sql"),
+            PathBuf::from("data/users.0001.sql"),
+            PathBuf::from("data/users.0001.sql"),
+            PathBuf::from("data/users.0002.sql"),
+            PathBuf::from("schema/orders-schema.sql"),
+            PathBuf::from("schema/orders-schema-post.sql"),
+            PathBuf::from("views.sql"),
+            PathBuf::from("views.sql"),
+        ]);
+
+        assert_eq!(output.metadata().unwrap(), "[config]\nlayout = mydumper\n\
+            \n[`users`]\nschema = schema/users-schema.sql\ndata_files = 2\n\
+            \n[`orders`]\nschema = schema/orders-schema.sql\npost = schema/orders-schema-post.sql\ndata_files = 0\n\
+            \n[files]\nviews = views.sql\n");
+    }
+
+    #[test]
+    fn formats(){
+        // files of rows of other formats don't have the metadata of the layout
+        let mut output = Output::for_format(Format::Csv);
+        assert_eq!(output.path(&Target::Data(table(None, "users")), &FileState::New), PathBuf::from("users.csv"));
+        assert_eq!(output.path(&Target::Data(table(None, "users")), &FileState::New), PathBuf::from("users.0002.csv"));
+        assert_eq!(output.metadata(), None);
+    }
+}
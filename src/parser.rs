@@ -1,25 +1,268 @@
 use tokenizer::Tokenizer;
 use tokenizer::SyntaxErr;
 use tokenizer::Token;
+use statement::Statement;
+use value::Fields;
+use splitter::Dialect;
 use std::io;
+use std::fmt;
 
+// number of significant tokens kept from the start of a statement
+const HEAD_SIZE: usize = 16;
 
 #[derive(Debug,PartialEq)]
 pub enum TokenStream {
-    Insert(Vec<u8>, Vec<u8>),
+    Insert(Vec<u8>, Vec<u8>, Option<Table>),
     ValuesTuple(Vec<u8>),
-    Block(Vec<u8>),
+    Block(Kind, Vec<u8>, Option<Table>),
     Comment(Vec<u8>),
     SpaceOrLineFeed(Vec<u8>),
+    // `COPY .. FROM stdin;` of postgres with the line feed after it,
+    // the rows follow a line each up to the `\.` line
+    Copy(Vec<u8>, Option<Table>),
+    CopyRow(Vec<u8>),
+    CopyEnd(Vec<u8>),
+}
+
+impl TokenStream {
+    /// Parses the raw bytes of an insert or a block. The insert only
+    /// has the values tuple that came with it, the rest follow as
+    /// `ValuesTuple`.
+    pub fn statement(&self) -> Option<Statement> {
+        match self {
+            TokenStream::Insert(tokens, _, _) |
+            TokenStream::Block(_, tokens, _) => Some(Statement::parse(tokens)),
+            _ => None,
+        }
+    }
+
+    /// Fields of the values tuple, for an insert the one that came with it.
+    pub fn fields(&self) -> Option<Fields<'_>> {
+        match self {
+            TokenStream::Insert(tokens, insert_stmt, _) => {
+                // `insert_stmt` has a space the insert doesn't
+                Some(Fields::new(&tokens[insert_stmt.len() - 1..]))
+            },
+            TokenStream::ValuesTuple(tokens) => Some(Fields::new(tokens)),
+            _ => None,
+        }
+    }
+}
+
+/// What a block statement creates, alters or drops.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Object {
+    Database,
+    Table,
+    View,
+    Trigger,
+    Procedure,
+    Function,
+    Event,
+    Index,
+    Other,
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Kind {
+    Create(Object),
+    Alter(Object),
+    Drop(Object),
+    // `ALTER TABLE .. DISABLE KEYS` and `ENABLE KEYS` around table data
+    Keys,
+    Set,
+    Lock,
+    Unlock,
+    Use,
+    Delimiter,
+    // inserts without a values list, like `INSERT .. SELECT`
+    Insert,
+    // `BEGIN TRANSACTION` and `COMMIT` around the statements of a sqlite dump
+    Transaction,
+    Other,
+}
+
+impl Kind {
+    pub fn is_ddl(&self) -> bool {
+        matches!(self, Kind::Create(_) | Kind::Alter(_) | Kind::Drop(_))
+    }
+}
+
+#[derive(Debug,PartialEq,Eq,Hash,Clone)]
+pub struct Table {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.schema {
+            Some(ref schema) => write!(f, "{}.{}", schema, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Strips the backticks of a quoted identifier, or the double quotes of postgres.
+pub fn unquote(identifier: &[u8]) -> String {
+    let value = String::from_utf8_lossy(identifier);
+    if value.len() > 1 && value.starts_with('`') && value.ends_with('`') {
+        value[1..value.len() - 1].replace("``", "`")
+    }else if value.len() > 1 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].replace("\"\"", "\"")
+    }else{
+        value.into_owned()
+    }
+}
+
+/// White space, comments and the markers of executable comments
+/// don't change what a statement means.
+pub fn significant(token: &Token) -> bool {
+    !matches!(token,
+        Token::Space |
+        Token::LineFeed(_) |
+        Token::Comment(_) |
+        Token::InlineComment(_) |
+        Token::ConditionalStart(_) |
+        Token::ConditionalEnd
+    )
+}
+
+pub fn identifier(token: &Token) -> Option<String> {
+    match token {
+        Token::Identifier(chunk) |
+        Token::Keyword(chunk) => Some(unquote(chunk)),
+        _ => None,
+    }
+}
+
+fn object(head: &[Token]) -> Object {
+    for token in head {
+        let object = match token {
+            Token::Keyword(_) if token.keyword("table") => Object::Table,
+            Token::Keyword(_) if token.keyword("view") => Object::View,
+            Token::Keyword(_) if token.keyword("trigger") => Object::Trigger,
+            Token::Keyword(_) if token.keyword("procedure") => Object::Procedure,
+            Token::Keyword(_) if token.keyword("function") => Object::Function,
+            Token::Keyword(_) if token.keyword("event") => Object::Event,
+            Token::Keyword(_) if token.keyword("index") => Object::Index,
+            Token::Keyword(_) if token.keyword("database") || token.keyword("schema") => Object::Database,
+            _ => continue,
+        };
+        return object
+    }
+
+    Object::Other
+}
+
+fn kind(head: &[Token]) -> Kind {
+    let verb = match head.first() {
+        Some(verb) => verb,
+        None => return Kind::Other,
+    };
+
+    if verb.keyword("create") {
+        Kind::Create(object(&head[1..]))
+    }else if verb.keyword("alter") {
+        let keys = head.windows(2).any(|pair| {
+            (pair[0].keyword("disable") || pair[0].keyword("enable")) && pair[1].keyword("keys")
+        });
+        if keys { Kind::Keys } else { Kind::Alter(object(&head[1..])) }
+    }else if verb.keyword("drop") {
+        Kind::Drop(object(&head[1..]))
+    }else if verb.keyword("set") {
+        Kind::Set
+    }else if verb.keyword("lock") {
+        Kind::Lock
+    }else if verb.keyword("unlock") {
+        Kind::Unlock
+    }else if verb.keyword("use") {
+        Kind::Use
+    }else if verb.keyword("delimiter") {
+        Kind::Delimiter
+    }else if verb.keyword("insert") || verb.keyword("replace") {
+        Kind::Insert
+    }else if verb.keyword("begin") || verb.keyword("commit") || verb.keyword("end") || verb.keyword("rollback") ||
+        (verb.keyword("start") && head.get(1).is_some_and(|token| token.keyword("transaction"))) {
+        Kind::Transaction
+    }else{
+        Kind::Other
+    }
+}
+
+// finds the table of CREATE/DROP/ALTER TABLE, LOCK TABLES, INSERT/REPLACE
+// INTO, postgres COPY and the table a trigger is created on. A lock of
+// several tables is shared, it has no table.
+fn table(kind: Kind, head: &[Token]) -> Option<Table> {
+    let verb = head.first()?;
+    let start = if verb.keyword("insert") || verb.keyword("replace") {
+        head.iter().position(|t| t.keyword("into"))? + 1
+    }else if verb.keyword("lock") {
+        if head.contains(&Token::Comma) {
+            return None
+        }
+        head.iter().take(2).position(|t| t.keyword("tables") || t.keyword("table"))? + 1
+    }else if verb.keyword("copy") {
+        1
+    }else if matches!(kind, Kind::Create(Object::Trigger) | Kind::Create(Object::Index) | Kind::Drop(Object::Index)) {
+        head.iter().position(|t| t.keyword("on"))? + 1
+    }else if verb.keyword("create") || verb.keyword("drop") || verb.keyword("alter") {
+        head.iter().take(3).position(|t| t.keyword("table"))? + 1
+    }else{
+        return None
+    };
+
+    let mut rest = head[start..].iter()
+        .skip_while(|t| t.keyword("if") || t.keyword("not") || t.keyword("exists") || t.keyword("only"));
+
+    let first = identifier(rest.next()?)?;
+    match (rest.next(), rest.next()) {
+        (Some(Token::Dot), Some(token)) => Some(Table {
+            schema: Some(first),
+            name: identifier(token)?,
+        }),
+        _ => Some(Table {
+            schema: None,
+            name: first,
+        }),
+    }
 }
 
 pub struct Parser<T> {
     tokenizer: Tokenizer<T>,
+    // set by the `DELIMITER` command of the mysql client
+    delimiter: Vec<u8>,
+    // reading the rows of a `COPY .. FROM stdin`
+    copying: bool,
 }
 
 impl<T> Parser<T> where T: io::Read{
     pub fn new(tokenizer: Tokenizer<T>) -> Self {
-        Self { tokenizer }
+        Self { tokenizer, delimiter: vec![b';'], copying: false }
+    }
+
+    pub fn delimiter(&self) -> &[u8] {
+        &self.delimiter
+    }
+
+    /// Offset in the dump of what comes after the last token stream.
+    pub fn position(&self) -> u64 {
+        self.tokenizer.position()
+    }
+
+    pub fn copying(&self) -> bool {
+        self.copying
+    }
+
+    pub fn in_conditional(&self) -> bool {
+        self.tokenizer.in_conditional()
+    }
+
+    /// Parses on from `position` of a dump, read before up to there.
+    pub fn resume(&mut self, position: u64, delimiter: Vec<u8>, copying: bool, conditional: bool) {
+        self.tokenizer.resume(position, conditional);
+        self.delimiter = delimiter;
+        self.copying = copying;
     }
     
     pub fn read_while(&mut self, token: &Token) -> Result<Vec<u8>, SyntaxErr> {
@@ -45,13 +288,42 @@ impl<T> Parser<T> where T: io::Read{
         Ok(collection)
     }
 
+    // rest of a parenthesized group after its `(`, nested groups
+    // like `NOW()` included
+    fn group(&mut self) -> Result<Vec<u8>, SyntaxErr> {
+        let mut collection = vec![];
+        let mut depth = 1;
+        loop {
+            match self.tokenizer.token()? {
+                Some(token) => {
+                    match token {
+                        Token::LP => depth += 1,
+                        Token::RP => depth -= 1,
+                        _ => {},
+                    }
+                    collection.extend(token.value());
+                    if depth == 0 {
+                        break
+                    }
+                },
+                None => {
+                    return Err(SyntaxErr{
+                        text:"invalid end of file"
+                    })
+                }
+            }
+        }
+
+        Ok(collection)
+    }
+
     pub fn values(&mut self) -> Result<Vec<u8>, SyntaxErr> {
         let mut collection = vec![];
         loop {
             match self.tokenizer.token()? {
                 Some(token @ Token::LP) => {
                     collection.extend(token.value());
-                    match self.read_while(&Token::RP) {
+                    match self.group() {
                         Ok(val) => {
                             collection.extend(val);
                         },
@@ -81,7 +353,7 @@ impl<T> Parser<T> where T: io::Read{
 
     pub fn values_tuple(&mut self) -> Result<Vec<u8>, SyntaxErr> {
         let mut collection = vec![];
-        let value = self.read_while(&Token::RP)?; 
+        let value = self.group()?;
         collection.extend(value);
         loop {
             match self.tokenizer.token()? {
@@ -105,8 +377,9 @@ impl<T> Parser<T> where T: io::Read{
         Ok(collection)
     }
 
-    fn insert(&mut self, token: Vec<u8>) ->  Result<(Vec<u8>, Vec<u8>), SyntaxErr> {
-        let mut collection = token;
+    fn insert(&mut self, token: Token) ->  Result<TokenStream, SyntaxErr> {
+        let mut head = vec![token.clone()];
+        let mut collection = token.value();
         let mut insert_stmt;
 
         loop {
@@ -119,7 +392,14 @@ impl<T> Parser<T> where T: io::Read{
 
                         collection.extend(self.values()?);                        
                         break;
+                    }else if token == Token::SemiColon {
+                        collection.extend(token.value());
+                        let kind = kind(&head);
+                        return Ok(TokenStream::Block(kind, collection, table(kind, &head)))
                     }else{
+                        if head.len() < HEAD_SIZE && significant(&token) {
+                            head.push(token.clone());
+                        }
                         collection.extend(token.value());
                     }
                 },
@@ -131,45 +411,172 @@ impl<T> Parser<T> where T: io::Read{
             }
         }
 
-        Ok((collection, insert_stmt))
+        Ok(TokenStream::Insert(collection, insert_stmt, table(Kind::Insert, &head)))
+    }
+
+    // anything that ends with the delimiter and start with create, drop or set etc etc
+    fn block(&mut self, token: Token) -> Result<TokenStream, SyntaxErr> {
+        let mut head = vec![];
+        let mut collection = vec![];
+        let mut next = Some(token);
+        // sqlite triggers have statements between `BEGIN` and `END`, with `CASE .. END` in them
+        let sqlite = self.tokenizer.dialect() == Dialect::Sqlite;
+        let mut depth = 0;
+
+        while let Some(token) = next {
+            if head.len() < HEAD_SIZE && significant(&token) {
+                head.push(token.clone());
+            }
+
+            if sqlite && (token.keyword("begin") || token.keyword("case")) && kind(&head) == Kind::Create(Object::Trigger) {
+                depth += 1;
+            }else if sqlite && token.keyword("end") && depth > 0 {
+                depth -= 1;
+            }
+
+            let delimits = matches!(token, Token::SemiColon | Token::Ignore(_));
+            collection.extend(token.value());
+            if delimits && depth == 0 && collection.ends_with(&self.delimiter) {
+                let kind = kind(&head);
+                return Ok(TokenStream::Block(kind, collection, table(kind, &head)))
+            }
+
+            next = self.tokenizer.token()?;
+        }
+
+        Err(SyntaxErr{
+            text:"invalid end of file"
+        })
+    }
+
+    // `DELIMITER ;;` is a client command, the rest of the line is the delimiter
+    fn delimiter_block(&mut self, token: Token) -> Result<TokenStream, SyntaxErr> {
+        let mut collection = token.value();
+        let mut delimiter = vec![];
+
+        while let Some(token) = self.tokenizer.token()? {
+            match token {
+                Token::LineFeed(b'\n') |
+                Token::LineFeed(b'\r') => {
+                    collection.extend(token.value());
+                    break
+                },
+                Token::Space |
+                Token::LineFeed(_) => collection.extend(token.value()),
+                _ => {
+                    let value = token.value();
+                    delimiter.extend(&value);
+                    collection.extend(value);
+                },
+            }
+        }
+
+        if !delimiter.is_empty() {
+            self.delimiter = delimiter;
+        }
+
+        Ok(TokenStream::Block(Kind::Delimiter, collection, None))
+    }
+
+    // `COPY` reading from stdin has its rows in the lines after it, other
+    // copies like `COPY t TO stdout` are regular statements
+    fn copy(&mut self, token: Token) -> Result<TokenStream, SyntaxErr> {
+        let mut head = vec![];
+        let mut collection = vec![];
+        // the last two words are `FROM stdin`
+        let (mut from, mut stdin) = (false, false);
+        let mut next = Some(token);
+
+        while let Some(token) = next {
+            if significant(&token) && token != Token::SemiColon {
+                stdin = from && token.keyword("stdin");
+                from = token.keyword("from");
+                if head.len() < HEAD_SIZE {
+                    head.push(token.clone());
+                }
+            }
+
+            let ends = token == Token::SemiColon;
+            collection.extend(token.value());
+            if ends {
+                let table = table(Kind::Other, &head);
+                if !stdin {
+                    return Ok(TokenStream::Block(Kind::Other, collection, table))
+                }
+
+                collection.extend(self.tokenizer.line());
+                self.copying = true;
+                return Ok(TokenStream::Copy(collection, table))
+            }
+
+            next = self.tokenizer.token()?;
+        }
+
+        Err(SyntaxErr{
+            text:"invalid end of file"
+        })
+    }
+
+    fn copy_row(&mut self) -> Result<Option<TokenStream>, SyntaxErr> {
+        let line = self.tokenizer.line();
+        if line.is_empty() {
+            return Err(SyntaxErr{
+                text: "COPY data without its end."
+            })
+        }
+
+        if line == b"\\.\n" || line == b"\\.\r\n" || line == b"\\." {
+            self.copying = false;
+            return Ok(Some(TokenStream::CopyEnd(line)))
+        }
+        Ok(Some(TokenStream::CopyRow(line)))
     }
 
     pub fn token_stream(&mut self) -> Result<Option<TokenStream>, SyntaxErr> {
+        if self.copying {
+            return self.copy_row()
+        }
+
+        let mysql = self.tokenizer.dialect() == Dialect::Mysql;
+        let postgres = self.tokenizer.dialect() == Dialect::Postgres;
         match self.tokenizer.token()? {
             Some(token) => {
                 match token {
                     Token::Keyword(_) => { 
-                        if token.keyword("insert") {
+                        if token.keyword("insert") || token.keyword("replace") {
                             // parse insert statement
                             // should end with with , or ;
                             // example: "insert into xyz values (),"
                             // example: "insert into xyz values ();"
-
-                            let (insert, insert_stmt) = self.insert(token.value())?;
-                            Ok(Some(TokenStream::Insert(insert, insert_stmt)))
+                            Ok(Some(self.insert(token)?))
+                        }else if token.keyword("delimiter") && mysql {
+                            Ok(Some(self.delimiter_block(token)?))
+                        }else if token.keyword("copy") && postgres {
+                            Ok(Some(self.copy(token)?))
                         }else{
                             // we assume its a block handle blocks
-                            // anything that ends with `;` and 
-                            // start with create, drop or set etc etc
-                            match self.read_while(&Token::SemiColon) {
-                                Ok(val) => {
-                                    let mut output = token.value();
-                                    output.extend(val);
-                                    Ok(Some(TokenStream::Block(output)))
-                                },
-                                Err(e) => Err(e)  
-                            }
+                            Ok(Some(self.block(token)?))
                         }
                     },
+                    // executable comments hold statements like
+                    // `/*!40000 ALTER TABLE `xyz` DISABLE KEYS */;`
+                    Token::ConditionalStart(_) => Ok(Some(self.block(token)?)),
                     Token::LP => {
                         let mut output = token.value();
                         output.extend(self.values_tuple()?);
                         Ok(Some(TokenStream::ValuesTuple(output)))
                     }
                     Token::Comment(_) | 
-                    Token::InlineComment(_) => {
+                    Token::InlineComment(_) |
+                    Token::ConditionalEnd => {
                         Ok(Some(TokenStream::Comment(token.value())))
                     },
+                    // psql commands like `\connect` take the rest of the line
+                    Token::Ignore(b'\\') if postgres => {
+                        let mut collection = token.value();
+                        collection.extend(self.tokenizer.line());
+                        Ok(Some(TokenStream::Block(Kind::Other, collection, None)))
+                    },
                     Token::RP |
                     Token::Dot |
                     Token::String(_) |
@@ -203,24 +610,18 @@ mod reader_test{
     use tokenizer::SyntaxErr;
     use super::Parser;
     use super::TokenStream;
+    use super::Kind;
+    use super::Object;
+    use super::Table;
+    use splitter::Dialect;
 
     type TS = Result<Option<TokenStream>, SyntaxErr>;    
     fn is_space(value: TS) -> bool {
-        match value {
-            Ok(Some(TokenStream::SpaceOrLineFeed(_))) => {
-                true
-            },
-            _ => false,
-        }
+        matches!(value, Ok(Some(TokenStream::SpaceOrLineFeed(_))))
     }
 
     fn is_comment(value: TS) -> bool {
-        match value {
-            Ok(Some(TokenStream::Comment(_))) => {
-                true
-            },
-            _ => false,
-        }
+        matches!(value, Ok(Some(TokenStream::Comment(_))))
     }
 
     fn valid_values_tuple(value: TS) -> (bool, &'static str) {
@@ -237,7 +638,7 @@ mod reader_test{
 
     fn valid_block(value: TS) -> (bool, &'static str) {
         match value {
-            Ok(Some(TokenStream::Block(tokens))) => {
+            Ok(Some(TokenStream::Block(_, tokens, _))) => {
                 match tokens[tokens.len() - 1] {
                     b';' => (true, ""),
                     _ => (false, "Last token should be semicolon"),
@@ -249,7 +650,7 @@ mod reader_test{
 
     fn valid_insert(value: TS) -> (bool, &'static str) {
         match value {
-            Ok(Some(TokenStream::Insert(tokens, _))) => {
+            Ok(Some(TokenStream::Insert(tokens, _, _))) => {
                 match tokens[tokens.len() - 1] {
                     b';' => (true, ""),
                     b',' => (true, ""),
@@ -278,7 +679,7 @@ mod reader_test{
 
         // create table
         let (state, msg) = valid_block(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
 
         // white space or line feed
         assert!(is_space(parser.token_stream()), "white space");
@@ -288,13 +689,13 @@ mod reader_test{
 
         // insert
         let (state, msg) = valid_insert(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
 
         assert!(is_space(parser.token_stream()), "white space");
         assert!(is_space(parser.token_stream()), "white space");
 
         let (state, msg) = valid_insert(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
 
         // line feeds
         assert!(is_space(parser.token_stream()), "white space");
@@ -304,7 +705,7 @@ mod reader_test{
 
         // set FOREIGN_KEY_CHECKS block
         let (state, msg) = valid_block(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
         
         // line feed
         assert!(is_space(parser.token_stream()), "white space");
@@ -314,7 +715,7 @@ mod reader_test{
 
         // create table block
         let (state, msg) = valid_block(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
 
         // line feed
         assert!(is_space(parser.token_stream()), "white space");
@@ -324,12 +725,221 @@ mod reader_test{
 
         // insert 
         let (state, msg) = valid_insert(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
 
         // value tuple
         let (state, msg) = valid_values_tuple(parser.token_stream());
-        assert!(state, msg);
+        assert!(state, "{}", msg);
     }
 
+    fn tables<T: ::std::io::Read>(mut parser: Parser<T>) -> Vec<Option<String>> {
+        let mut tables = vec![];
+        loop {
+            match parser.token_stream() {
+                Ok(Some(TokenStream::Insert(_, _, table))) |
+                Ok(Some(TokenStream::Block(_, _, table))) => {
+                    tables.push(table.map(|table| table.to_string()))
+                },
+                Ok(Some(_)) => {},
+                Ok(None) => break,
+                Err(e) => panic!("{}", e.text),
+            }
+        }
+        tables
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn table_names(){
+        let file = File::open("./example-files/mysqldump.txt").unwrap();
+        let parser = Parser::new(Tokenizer::new(Reader::new(file)));
+
+        let mut expected = vec![None, None, None];
+        for table in &["users", "audit_log", "orders"] {
+            let table = Some(table.to_string());
+            expected.push(table.clone()); // drop table
+            if table == Some("users".to_string()) {
+                expected.extend(vec![None, None]);
+            }
+            expected.push(table.clone()); // create table
+            if table == Some("users".to_string()) {
+                expected.push(None);
+            }
+            expected.push(table.clone()); // lock tables
+            expected.push(table.clone()); // disable keys
+            expected.push(table.clone()); // insert
+            expected.push(table.clone()); // enable keys
+            expected.push(None); // unlock tables
+        }
+        expected.extend(vec![None, None]);
+
+        assert_eq!(tables(parser), expected);
+    }
+
+    #[test]
+    fn qualified_table_names(){
+        let sql = b"CREATE TEMPORARY TABLE IF NOT EXISTS `shop`.`order``s` (`id` int);\n\
+            INSERT IGNORE INTO shop.audit_log (`id`) VALUES (1);\n\
+            LOCK TABLES `log_2024` WRITE;\n\
+            LOCK TABLES `log_2024` WRITE, `shop`.`items` READ LOCAL;\n\
+            ALTER TABLE `items` ADD KEY (`id`);\n\
+            CREATE UNIQUE INDEX `items_id` ON `shop`.`items` (`id`);\n\
+            SET NAMES utf8;\n";
+        let parser = Parser::new(Tokenizer::new(Reader::new(&sql[..])));
+
+        assert_eq!(tables(parser), vec![
+            Some("shop.order`s".to_string()),
+            Some("shop.audit_log".to_string()),
+            Some("log_2024".to_string()),
+            None,
+            Some("items".to_string()),
+            Some("shop.items".to_string()),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn nested_values(){
+        let sql = b"INSERT INTO `t` VALUES (1,'a\\\\',NOW()),(2,CONCAT('b',')'),'c');";
+        let mut parser = Parser::new(Tokenizer::new(Reader::new(&sql[..])));
+
+        match parser.token_stream() {
+            Ok(Some(TokenStream::Insert(tokens, _, _))) => {
+                assert_eq!(tokens, b"INSERT INTO `t` VALUES (1,'a\\\\',NOW()),".to_vec())
+            },
+            _ => panic!("expected insert"),
+        }
+
+        let tuple = parser.token_stream().unwrap().unwrap();
+        assert_eq!(tuple, TokenStream::ValuesTuple(b"(2,CONCAT('b',')'),'c');".to_vec()));
+        assert_eq!(tuple.fields().unwrap().count(), 3);
+    }
+
+    #[test]
+    fn postgres_dump(){
+        let sql = b"\\restrict abc\n\
+            CREATE FUNCTION public.hi() RETURNS text AS $body$ SELECT 'a;b'; $body$;\n\
+            CREATE TABLE public.\"Order\"\"s\" (note text DEFAULT 'it''s; \\');\n\
+            ALTER TABLE ONLY public.\"Order\"\"s\" ADD CONSTRAINT pk PRIMARY KEY (id);\n\
+            INSERT INTO t VALUES (E'a\\';b', $$;$$);\n\
+            COPY public.\"Order\"\"s\" (id, note) FROM stdin;\n\
+            1\t'it;s\n\
+            2\t\\N\n\
+            \\.\n";
+        let mut parser = Parser::new(Tokenizer::for_dialect(Reader::new(&sql[..]), Dialect::Postgres));
+
+        let mut streams = vec![];
+        loop {
+            match parser.token_stream() {
+                Ok(Some(TokenStream::SpaceOrLineFeed(_))) => {},
+                Ok(Some(stream)) => streams.push(stream),
+                Ok(None) => break,
+                Err(e) => panic!("{}", e.text),
+            }
+        }
+
+        let table = Some(Table { schema: Some("public".to_string()), name: "Order\"s".to_string() });
+        assert_eq!(streams, vec![
+            TokenStream::Block(Kind::Other, b"\\restrict abc\n".to_vec(), None),
+            TokenStream::Block(Kind::Create(Object::Function), b"CREATE FUNCTION public.hi() RETURNS text AS $body$ SELECT 'a;b'; $body$;".to_vec(), None),
+            TokenStream::Block(Kind::Create(Object::Table), b"CREATE TABLE public.\"Order\"\"s\" (note text DEFAULT 'it''s; \\');".to_vec(), table.clone()),
+            TokenStream::Block(Kind::Alter(Object::Table), b"ALTER TABLE ONLY public.\"Order\"\"s\" ADD CONSTRAINT pk PRIMARY KEY (id);".to_vec(), table.clone()),
+            TokenStream::Insert(b"INSERT INTO t VALUES (E'a\\';b', $$;$$);".to_vec(), b"INSERT INTO t VALUES ".to_vec(),
+                Some(Table { schema: None, name: "t".to_string() })),
+            TokenStream::Copy(b"COPY public.\"Order\"\"s\" (id, note) FROM stdin;\n".to_vec(), table),
+            TokenStream::CopyRow(b"1\t'it;s\n".to_vec()),
+            TokenStream::CopyRow(b"2\t\\N\n".to_vec()),
+            TokenStream::CopyEnd(b"\\.\n".to_vec()),
+        ]);
+    }
+
+    #[test]
+    fn sqlite_dump(){
+        let sql = b"BEGIN TRANSACTION;\n\
+            INSERT INTO \"t\" VALUES(1,'it''s \\',X'00');\n\
+            CREATE TRIGGER tr AFTER INSERT ON \"t\" BEGIN\n\
+            UPDATE t SET a = CASE WHEN new.a > 1 THEN 'x;' ELSE a END;\n\
+            DELETE FROM u;\n\
+            END;\n\
+            COMMIT;\n";
+        let mut parser = Parser::new(Tokenizer::for_dialect(Reader::new(&sql[..]), Dialect::Sqlite));
+
+        let mut streams = vec![];
+        loop {
+            match parser.token_stream() {
+                Ok(Some(TokenStream::SpaceOrLineFeed(_))) => {},
+                Ok(Some(stream)) => streams.push(stream),
+                Ok(None) => break,
+                Err(e) => panic!("{}", e.text),
+            }
+        }
+
+        let table = Some(Table { schema: None, name: "t".to_string() });
+        assert_eq!(streams, vec![
+            TokenStream::Block(Kind::Transaction, b"BEGIN TRANSACTION;".to_vec(), None),
+            TokenStream::Insert(b"INSERT INTO \"t\" VALUES(1,'it''s \\',X'00');".to_vec(), b"INSERT INTO \"t\" VALUES ".to_vec(), table.clone()),
+            TokenStream::Block(Kind::Create(Object::Trigger), b"CREATE TRIGGER tr AFTER INSERT ON \"t\" BEGIN\n\
+                UPDATE t SET a = CASE WHEN new.a > 1 THEN 'x;' ELSE a END;\n\
+                DELETE FROM u;\n\
+                END;".to_vec(), table),
+            TokenStream::Block(Kind::Transaction, b"COMMIT;".to_vec(), None),
+        ]);
+    }
+
+    #[test]
+    fn statement_kinds(){
+        let file = File::open("./example-files/routines.txt").unwrap();
+        let mut parser = Parser::new(Tokenizer::new(Reader::new(file)));
+
+        let mut kinds = vec![];
+        loop {
+            match parser.token_stream() {
+                Ok(Some(TokenStream::Insert(_, _, _))) => kinds.push(Kind::Insert),
+                Ok(Some(TokenStream::Block(kind, tokens, table))) => {
+                    if kind == Kind::Create(Object::Trigger) {
+                        assert!(tokens.ends_with(b"END */;;"));
+                        assert_eq!(table.unwrap().name, "users");
+                    }
+                    kinds.push(kind)
+                },
+                Ok(Some(_)) => {},
+                Ok(None) => break,
+                Err(e) => panic!("{}", e.text),
+            }
+        }
+
+        assert_eq!(kinds, vec![
+            Kind::Set,
+            Kind::Create(Object::Database),
+            Kind::Use,
+            Kind::Drop(Object::Table),
+            Kind::Create(Object::Table),
+            Kind::Lock,
+            Kind::Insert,
+            Kind::Unlock,
+            Kind::Set,
+            Kind::Delimiter,
+            Kind::Create(Object::Trigger),
+            Kind::Delimiter,
+            Kind::Set,
+            Kind::Drop(Object::Table),
+            Kind::Drop(Object::View),
+            Kind::Set,
+            Kind::Set,
+            Kind::Create(Object::View),
+            Kind::Set,
+            Kind::Drop(Object::Procedure),
+            Kind::Delimiter,
+            Kind::Create(Object::Procedure),
+            Kind::Delimiter,
+            Kind::Drop(Object::Function),
+            Kind::Delimiter,
+            Kind::Create(Object::Function),
+            Kind::Delimiter,
+            Kind::Drop(Object::View),
+            Kind::Set,
+            Kind::Create(Object::View),
+            Kind::Set,
+        ]);
+        assert_eq!(parser.delimiter(), b";");
+    }
+}
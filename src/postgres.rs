@@ -0,0 +1,397 @@
+use tokenizer::Tokenizer;
+use tokenizer::Token;
+use tokenizer::SyntaxErr;
+use reader::Reader;
+use parser::Table;
+use parser::Kind;
+use parser::Object;
+use parser::significant;
+use parser::unquote;
+use statement::Statement;
+use statement::Column;
+use statement::DataType;
+use statement::KeyKind;
+use value::Value;
+use value::Fields;
+use value::parse;
+use value::unescape;
+
+/// `"name"`, identifiers keep their case when quoted.
+pub fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn table_name(table: &Table) -> String {
+    match table.schema {
+        Some(ref schema) => format!("{}.{}", quote(schema), quote(&table.name)),
+        None => quote(&table.name),
+    }
+}
+
+fn names(names: &[String]) -> String {
+    names.iter().map(|name| quote(name)).collect::<Vec<_>>().join(", ")
+}
+
+/// `'text'`, backslashes are plain characters with `standard_conforming_strings`.
+pub fn literal(text: &[u8]) -> String {
+    let text: Vec<u8> = text.iter().cloned().filter(|&byte| byte != 0).collect();
+    format!("'{}'", String::from_utf8_lossy(&text).replace('\'', "''"))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// `0000-00-00` and `0000-00-00 00:00:00.000000` of MySQL, they aren't dates in postgres
+fn zero_date(text: &[u8]) -> bool {
+    match text.len() {
+        10 => text == b"0000-00-00",
+        19.. => text.starts_with(b"0000-00-00 00:00:00") && text[19..].iter().all(|&byte| byte == b'.' || byte == b'0'),
+        _ => false,
+    }
+}
+
+/// Columns written as `bytea`.
+pub fn binary(data_type: &DataType) -> bool {
+    matches!(data_type.name.as_str(), "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob")
+}
+
+fn column_type(column: &Column) -> String {
+    let data_type = &column.data_type;
+    let args = data_type.args.join(",");
+    let sized = |name: &str| if args.is_empty() { name.to_string() } else { format!("{}({})", name, args) };
+    match data_type.name.as_str() {
+        "tinyint" | "bool" | "boolean" | "year" => "smallint".to_string(),
+        "smallint" if data_type.unsigned => "integer".to_string(),
+        "smallint" => "smallint".to_string(),
+        "mediumint" => "integer".to_string(),
+        "int" | "integer" if data_type.unsigned => "bigint".to_string(),
+        "int" | "integer" => "integer".to_string(),
+        // identity columns have to be integers
+        "bigint" if data_type.unsigned && !column.auto_increment => "numeric(20)".to_string(),
+        "bigint" | "bit" => "bigint".to_string(),
+        "float" => "real".to_string(),
+        "double" | "real" => "double precision".to_string(),
+        "decimal" | "numeric" | "dec" | "fixed" => sized("numeric"),
+        "char" => sized("char"),
+        "varchar" => sized("varchar"),
+        "date" => "date".to_string(),
+        "datetime" | "timestamp" => sized("timestamp"),
+        "time" => sized("time"),
+        "json" => "jsonb".to_string(),
+        _ if binary(data_type) => "bytea".to_string(),
+        // text, enum, set and types without a counterpart
+        _ => "text".to_string(),
+    }
+}
+
+fn default(column: &Column) -> Option<String> {
+    let raw = column.default.as_ref()?;
+    let default = match parse(raw) {
+        Value::Null => "NULL".to_string(),
+        Value::String(ref text) if zero_date(text) => return None,
+        Value::String(ref text) if binary(&column.data_type) => format!("'\\x{}'", hex(text)),
+        Value::Hex(ref bytes) if binary(&column.data_type) => format!("'\\x{}'", hex(bytes)),
+        Value::String(ref text) | Value::Hex(ref text) => literal(text),
+        Value::Expression(expression) => {
+            let expression = String::from_utf8_lossy(expression).to_uppercase();
+            if expression.starts_with("CURRENT_TIMESTAMP") || expression.starts_with("NOW") {
+                "CURRENT_TIMESTAMP".to_string()
+            }else{
+                return None
+            }
+        },
+        value => String::from_utf8_lossy(&value.text()?).into_owned(),
+    };
+    Some(default)
+}
+
+/// `CREATE TABLE` with its indexes, and the statements that have to wait for the data
+/// of all tables: foreign keys and moving identity sequences past the loaded ids.
+pub fn create_table(sql: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let (name, if_not_exists, columns, keys) = match Statement::parse(sql) {
+        Statement::CreateTable { name, if_not_exists, columns, keys, .. } => (name, if_not_exists, columns, keys),
+        _ => return None,
+    };
+
+    let table = table_name(&name);
+    let exists = if if_not_exists { "IF NOT EXISTS " } else { "" };
+    let mut lines = vec![];
+    let mut after = String::new();
+    let mut post = String::new();
+    for column in &columns {
+        let mut line = format!("  {} {}", quote(&column.name), column_type(column));
+        if column.auto_increment {
+            line.push_str(" GENERATED BY DEFAULT AS IDENTITY");
+            post.push_str(&format!(
+                "SELECT setval(pg_get_serial_sequence({}, {}), COALESCE(MAX({}), 0) + 1, false) FROM {};\n",
+                literal(table.as_bytes()), literal(column.name.as_bytes()), quote(&column.name), table
+            ));
+        }else if let Some(default) = default(column) {
+            line.push_str(&format!(" DEFAULT {}", default));
+        }
+
+        if !column.nullable {
+            line.push_str(" NOT NULL");
+        }
+
+        if column.data_type.name == "enum" {
+            let values: Vec<String> = column.data_type.args.iter()
+                .filter_map(|arg| parse(arg.as_bytes()).text().map(|text| literal(&text)))
+                .collect();
+            line.push_str(&format!(" CHECK ({} IN ({}))", quote(&column.name), values.join(", ")));
+        }
+        lines.push(line);
+    }
+
+    for key in &keys {
+        match key.kind {
+            KeyKind::Primary => lines.push(format!("  PRIMARY KEY ({})", names(&key.columns))),
+            KeyKind::Unique => lines.push(format!("  UNIQUE ({})", names(&key.columns))),
+            // index names belong to the schema in postgres
+            KeyKind::Index => {
+                let index = key.name.clone().unwrap_or_else(|| key.columns.join("_"));
+                let index = if index.starts_with(&format!("{}_", name.name)) { index } else { format!("{}_{}", name.name, index) };
+                after.push_str(&format!("\nCREATE INDEX {}{} ON {} ({});", exists, quote(&index), table, names(&key.columns)))
+            },
+            KeyKind::Foreign => if let Some(ref reference) = key.references {
+                let constraint = match key.name {
+                    Some(ref name) => format!("CONSTRAINT {} ", quote(name)),
+                    None => String::new(),
+                };
+                post.push_str(&format!(
+                    "ALTER TABLE {} ADD {}FOREIGN KEY ({}) REFERENCES {} ({})",
+                    table, constraint, names(&key.columns), table_name(&reference.table), names(&reference.columns)
+                ));
+                if let Some(ref action) = reference.on_delete {
+                    post.push_str(&format!(" ON DELETE {}", action));
+                }
+                if let Some(ref action) = reference.on_update {
+                    post.push_str(&format!(" ON UPDATE {}", action));
+                }
+                post.push_str(";\n");
+            },
+            // no btree counterpart
+            KeyKind::Fulltext | KeyKind::Spatial => {},
+        }
+    }
+
+    let create = format!("CREATE TABLE {}{} (\n{}\n);{}", exists, table, lines.join(",\n"), after);
+    Some((create.into_bytes(), post.into_bytes()))
+}
+
+/// MySQL tokens as postgres, and SQLite: double quoted identifiers, single quoted
+/// strings and no executable comment markers, `ALGORITHM`, `DEFINER` or `SQL SECURITY`.
+pub fn translate(sql: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+    let mut tokenizer = Tokenizer::new(Reader::new(sql));
+    let mut tokens = vec![];
+    while let Some(token) = tokenizer.token()? {
+        tokens.push(token);
+    }
+
+    let next = |index: usize| (index + 1..tokens.len()).find(|&index| significant(&tokens[index]));
+    let mut sql = vec![];
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        let assigned = next(index).is_some_and(|next| tokens[next] == Token::Ignore(b'='));
+        if (token.keyword("algorithm") || token.keyword("definer")) && assigned {
+            // up to the white space after the value, `root`@`%`
+            index = next(next(index).unwrap_or(index)).unwrap_or(tokens.len());
+            while index < tokens.len() && significant(&tokens[index]) {
+                index += 1;
+            }
+            continue
+        }
+
+        if token.keyword("sql") && next(index).is_some_and(|next| tokens[next].keyword("security")) {
+            index = next(next(index).unwrap_or(index)).map_or(tokens.len(), |last| last + 1);
+            continue
+        }
+
+        match token {
+            // along with the space after the version
+            Token::ConditionalStart(_) => if tokens.get(index + 1) == Some(&Token::Space) {
+                index += 1;
+            },
+            Token::ConditionalEnd => {},
+            Token::Identifier(name) => sql.extend(quote(&unquote(name)).into_bytes()),
+            Token::String(text) if text.first() == Some(&b'\'') || text.first() == Some(&b'"') => {
+                let quote = text[0];
+                sql.extend(literal(&unescape(&text[1..text.len() - 1], quote)).into_bytes());
+            },
+            token => sql.extend(token.clone().value()),
+        }
+        index += 1;
+    }
+    Ok(sql)
+}
+
+// a trigger or routine in MySQL's language, kept as comments to be written again
+fn commented(sql: &[u8]) -> Vec<u8> {
+    let mut commented = b"-- postgres can't run this statement, write it again:".to_vec();
+    for line in sql.split(|byte| *byte == b'\n') {
+        commented.extend(b"\n-- ");
+        commented.extend(line);
+    }
+    commented
+}
+
+/// A statement other than `CREATE TABLE` and inserts, None when postgres
+/// has no use for it, like `LOCK TABLES`. Triggers and routines it can't
+/// run are commented out.
+pub fn statement(kind: Kind, sql: &[u8]) -> Result<Option<Vec<u8>>, SyntaxErr> {
+    let statement = match kind {
+        Kind::Drop(Object::Table) | Kind::Drop(Object::View) => {
+            match Statement::parse(sql) {
+                Statement::Drop { object, names, if_exists } => {
+                    let object = if object == Object::View { "VIEW" } else { "TABLE" };
+                    let names: Vec<String> = names.iter().map(table_name).collect();
+                    let exists = if if_exists { "IF EXISTS " } else { "" };
+                    Some(format!("DROP {} {}{} CASCADE;", object, exists, names.join(", ")).into_bytes())
+                },
+                _ => None,
+            }
+        },
+        Kind::Create(Object::View) |
+        Kind::Alter(Object::View) |
+        Kind::Create(Object::Index) |
+        Kind::Drop(Object::Index) |
+        Kind::Alter(Object::Table) |
+        Kind::Insert |
+        Kind::Other => Some(translate(sql)?),
+        Kind::Create(Object::Trigger) |
+        Kind::Drop(Object::Trigger) |
+        Kind::Create(Object::Procedure) |
+        Kind::Create(Object::Function) |
+        Kind::Create(Object::Event) |
+        Kind::Alter(Object::Procedure) |
+        Kind::Alter(Object::Function) |
+        Kind::Alter(Object::Event) |
+        Kind::Drop(Object::Procedure) |
+        Kind::Drop(Object::Function) |
+        Kind::Drop(Object::Event) => Some(commented(sql)),
+        _ => None,
+    };
+    Ok(statement)
+}
+
+/// `COPY "table" ("a", "b") FROM stdin;` for the rows of an insert.
+pub fn copy_header(insert_stmt: &[u8]) -> Vec<u8> {
+    let (table, columns) = match Statement::parse(insert_stmt) {
+        Statement::Insert { table, columns, .. } => (table, columns),
+        _ => return b"COPY FROM stdin;\n".to_vec(),
+    };
+
+    let columns = if columns.is_empty() { String::new() } else { format!(" ({})", names(&columns)) };
+    format!("COPY {}{} FROM stdin;\n", table_name(&table), columns).into_bytes()
+}
+
+fn copy_escape(text: &[u8], out: &mut Vec<u8>) {
+    for &byte in text {
+        match byte {
+            b'\\' => out.extend(b"\\\\"),
+            b'\t' => out.extend(b"\\t"),
+            b'\n' => out.extend(b"\\n"),
+            b'\r' => out.extend(b"\\r"),
+            0 => {},
+            byte => out.push(byte),
+        }
+    }
+}
+
+/// A values tuple as a line of `COPY` text, `binary` tells the `bytea` fields.
+pub fn copy_row(tuple: &[u8], binary: &[bool]) -> Vec<u8> {
+    let mut out = vec![];
+    for (index, value) in Fields::new(tuple).enumerate() {
+        if index > 0 {
+            out.push(b'\t');
+        }
+
+        match value {
+            Value::Null => out.extend(b"\\N"),
+            Value::String(ref text) if zero_date(text) => out.extend(b"\\N"),
+            Value::String(ref bytes) | Value::Hex(ref bytes) if binary.get(index) == Some(&true) => {
+                out.extend(b"\\\\x");
+                out.extend(hex(bytes).into_bytes());
+            },
+            value => if let Some(text) = value.text() {
+                copy_escape(&text, &mut out)
+            },
+        }
+    }
+    out.push(b'\n');
+    out
+}
+
+
+#[cfg(test)]
+mod postgres_test{
+    use parser::Kind;
+    use parser::Object;
+    use super::*;
+
+    #[test]
+    fn create_table(){
+        let sql = b"CREATE TABLE `orders` (\n\
+            `id` int unsigned NOT NULL AUTO_INCREMENT,\n\
+            `user_id` mediumint(8) NOT NULL default '0',\n\
+            `paid` tinyint(1) NOT NULL DEFAULT '0',\n\
+            `state` enum('new','it''s done') DEFAULT NULL,\n\
+            `note` varchar(20) CHARACTER SET latin1 DEFAULT 'it\\'s',\n\
+            `created_at` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,\n\
+            PRIMARY KEY (`id`),\n\
+            KEY `user_id` (`user_id`),\n\
+            CONSTRAINT `orders_user` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE\n\
+            ) ENGINE=MyISAM AUTO_INCREMENT=3 DEFAULT CHARSET=latin1;";
+
+        let (create, post) = super::create_table(sql).unwrap();
+        assert_eq!(String::from_utf8(create).unwrap(), "CREATE TABLE \"orders\" (\n  \
+            \"id\" bigint GENERATED BY DEFAULT AS IDENTITY NOT NULL,\n  \
+            \"user_id\" integer DEFAULT '0' NOT NULL,\n  \
+            \"paid\" smallint DEFAULT '0' NOT NULL,\n  \
+            \"state\" text DEFAULT NULL CHECK (\"state\" IN ('new', 'it''s done')),\n  \
+            \"note\" varchar(20) DEFAULT 'it''s',\n  \
+            \"created_at\" timestamp DEFAULT CURRENT_TIMESTAMP NOT NULL,\n  \
+            PRIMARY KEY (\"id\")\n);\n\
+            CREATE INDEX \"orders_user_id\" ON \"orders\" (\"user_id\");");
+        assert_eq!(String::from_utf8(post).unwrap(), "SELECT setval(pg_get_serial_sequence('\"orders\"', 'id'), \
+            COALESCE(MAX(\"id\"), 0) + 1, false) FROM \"orders\";\n\
+            ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user\" FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\") ON DELETE CASCADE;\n");
+
+        let (create, _) = super::create_table(b"CREATE TABLE IF NOT EXISTS `t` (`a` int, KEY `a` (`a`));").unwrap();
+        assert_eq!(String::from_utf8(create).unwrap(), "CREATE TABLE IF NOT EXISTS \"t\" (\n  \"a\" integer\n);\n\
+            CREATE INDEX IF NOT EXISTS \"t_a\" ON \"t\" (\"a\");");
+    }
+
+    #[test]
+    fn statements(){
+        let text = |kind, sql: &str| statement(kind, sql.as_bytes()).unwrap().map(|sql| String::from_utf8(sql).unwrap());
+        assert_eq!(text(Kind::Drop(Object::Table), "DROP TABLE IF EXISTS `users`;"), Some("DROP TABLE IF EXISTS \"users\" CASCADE;".to_string()));
+        assert_eq!(
+            text(Kind::Create(Object::View), "/*!50001 CREATE ALGORITHM=UNDEFINED */\n/*!50013 DEFINER=`root`@`localhost` SQL SECURITY DEFINER */\n/*!50001 VIEW `v` AS select `users`.`id` AS `id` from `users` where (`users`.`name` <> 'a\\'b') */;"),
+            Some("CREATE  \n  \nVIEW \"v\" AS select \"users\".\"id\" AS \"id\" from \"users\" where (\"users\".\"name\" <> 'a''b') ;".to_string())
+        );
+        assert_eq!(text(Kind::Lock, "LOCK TABLES `users` WRITE;"), None);
+        assert_eq!(
+            text(Kind::Create(Object::Trigger), "CREATE TRIGGER `t` BEFORE INSERT ON `users` FOR EACH ROW\nSET NEW.a = 1;"),
+            Some("-- postgres can't run this statement, write it again:\n-- CREATE TRIGGER `t` BEFORE INSERT ON `users` FOR EACH ROW\n-- SET NEW.a = 1;".to_string())
+        );
+        // a statement it can't read is an error, not cut short
+        assert!(statement(Kind::Create(Object::View), b"CREATE VIEW `v` AS select 'a from `users`;").is_err());
+    }
+
+    #[test]
+    fn copy(){
+        assert_eq!(copy_header(b"INSERT INTO `users` (`id`, `name`) VALUES "), b"COPY \"users\" (\"id\", \"name\") FROM stdin;\n".to_vec());
+        assert_eq!(
+            copy_row(b"(1,'a\\tb\\\\c\\nd',NULL,0x00ff,'it\\'s')", &[false, false, false, true, false]),
+            b"1\ta\\tb\\\\c\\nd\t\\N\t\\\\x00ff\tit's\n".to_vec()
+        );
+        // zero dates load as NULL, like the defaults they come from
+        assert_eq!(
+            copy_row(b"('0000-00-00','0000-00-00 00:00:00','0000-00-00 00:00:00.000','0000-00-00 x')", &[]),
+            b"\\N\t\\N\t\\N\t0000-00-00 x\n".to_vec()
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use filter::TableFilter;
+use parser::Table;
+use parser::unquote;
+use statement::Cursor;
+use tokenizer::Token;
+use value::Value;
+use value::parse;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Op {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+/// WHERE like condition on the fields of a row.
+#[derive(Debug,PartialEq,Clone)]
+pub enum Expr {
+    Column(String),
+    // column resolved to its position in the row
+    Field(usize),
+    Literal(Value<'static>),
+    Compare(Box<Expr>, Op, Box<Expr>),
+    // negated when the bool is set
+    IsNull(Box<Expr>, bool),
+    In(Box<Expr>, Vec<Expr>, bool),
+    Like(Box<Expr>, Box<Expr>, bool),
+    Between(Box<Expr>, Box<Expr>, Box<Expr>, bool),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+// literals outlive the text they are parsed from
+fn owned(value: Value) -> Option<Value<'static>> {
+    let value = match value {
+        Value::Null => Value::Null,
+        Value::Int(value) => Value::Int(value),
+        Value::Decimal(value) => Value::Float(value.parse().ok()?),
+        Value::Float(value) => Value::Float(value),
+        Value::String(value) => Value::String(Cow::Owned(value.into_owned())),
+        Value::Hex(value) => Value::Hex(Cow::Owned(value.into_owned())),
+        Value::Bit(value) => Value::Bit(value),
+        Value::Expression(_) => return None,
+    };
+    Some(value)
+}
+
+fn boxed(expr: Expr) -> Box<Expr> {
+    Box::new(expr)
+}
+
+struct ExprParser {
+    cursor: Cursor,
+}
+
+impl ExprParser {
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.cursor.keyword("or") || self.symbol(b"||") {
+            expr = Expr::Or(boxed(expr), boxed(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.cursor.keyword("and") || self.symbol(b"&&") {
+            expr = Expr::And(boxed(expr), boxed(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.cursor.keyword("not") || self.symbol(b"!") {
+            return Ok(Expr::Not(boxed(self.not()?)))
+        }
+        self.comparison()
+    }
+
+    // consumes a run of `Ignore` tokens like `>=` or `<>`
+    fn symbol(&mut self, symbol: &[u8]) -> bool {
+        let start = self.cursor.position();
+        let cursor = &mut self.cursor;
+        // `!` is also the start of `!=`
+        if symbol.iter().all(|&byte| cursor.token(&Token::Ignore(byte))) &&
+            !(symbol == b"!" && cursor.peek() == Some(&Token::Ignore(b'='))) {
+            return true
+        }
+
+        self.cursor.rewind(start);
+        false
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        for (symbol, op) in &[
+            (&b"<="[..], Op::LtEq),
+            (b">=", Op::GtEq),
+            (b"<>", Op::NotEq),
+            (b"!=", Op::NotEq),
+            (b"<", Op::Lt),
+            (b">", Op::Gt),
+            (b"=", Op::Eq),
+        ] {
+            if self.symbol(symbol) {
+                return Some(*op)
+            }
+        }
+        None
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.operand()?;
+        if let Some(op) = self.op() {
+            return Ok(Expr::Compare(boxed(left), op, boxed(self.operand()?)))
+        }
+
+        if self.cursor.keyword("is") {
+            let negated = self.cursor.keyword("not");
+            if !self.cursor.keyword("null") {
+                return Err("expected NULL after IS".to_string())
+            }
+            return Ok(Expr::IsNull(boxed(left), negated))
+        }
+
+        let negated = self.cursor.keyword("not");
+        if self.cursor.keyword("in") {
+            if !self.cursor.token(&Token::LP) {
+                return Err("expected ( after IN".to_string())
+            }
+            let mut list = vec![self.operand()?];
+            while self.cursor.token(&Token::Comma) {
+                list.push(self.operand()?);
+            }
+            if !self.cursor.token(&Token::RP) {
+                return Err("expected ) to close IN".to_string())
+            }
+            Ok(Expr::In(boxed(left), list, negated))
+        }else if self.cursor.keyword("like") {
+            Ok(Expr::Like(boxed(left), boxed(self.operand()?), negated))
+        }else if self.cursor.keyword("between") {
+            let low = self.operand()?;
+            if !self.cursor.keyword("and") {
+                return Err("expected AND in BETWEEN".to_string())
+            }
+            Ok(Expr::Between(boxed(left), boxed(low), boxed(self.operand()?), negated))
+        }else if negated {
+            Err("expected IN, LIKE or BETWEEN after NOT".to_string())
+        }else{
+            Ok(left)
+        }
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        if self.cursor.token(&Token::LP) {
+            let expr = self.or()?;
+            if !self.cursor.token(&Token::RP) {
+                return Err("expected )".to_string())
+            }
+            return Ok(expr)
+        }
+
+        if self.cursor.keyword("null") {
+            return Ok(Expr::Literal(Value::Null))
+        }
+        if self.cursor.keyword("true") {
+            return Ok(Expr::Literal(Value::Int(1)))
+        }
+        if self.cursor.keyword("false") {
+            return Ok(Expr::Literal(Value::Int(0)))
+        }
+
+        let sign = self.cursor.token(&Token::Ignore(b'-'));
+        match self.cursor.take() {
+            Some(Token::String(bytes)) => {
+                let mut literal = if sign { b"-".to_vec() } else { vec![] };
+                literal.extend(bytes);
+                match owned(parse(&literal)) {
+                    Some(value) => Ok(Expr::Literal(value)),
+                    None => Err(format!("invalid value {}", String::from_utf8_lossy(&literal))),
+                }
+            },
+            Some(token @ Token::Identifier(_)) |
+            Some(token @ Token::Keyword(_)) if !sign => {
+                let bytes = token.value();
+                Ok(Expr::Column(unquote(&bytes)))
+            },
+            Some(token) => Err(format!("unexpected {}", String::from_utf8_lossy(&token.value()))),
+            None => Err("unexpected end of the condition".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = ExprParser { cursor: Cursor::new(text.as_bytes()) };
+        let expr = parser.or()?;
+        match parser.cursor.take() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {}", String::from_utf8_lossy(&token.value()))),
+        }
+    }
+
+    /// Resolves column names to positions in the row.
+    pub fn bind(&self, columns: &[String]) -> Result<Expr, String> {
+        let bind = |expr: &Expr| expr.bind(columns).map(boxed);
+        let expr = match self {
+            Expr::Column(name) => {
+                match columns.iter().position(|column| column.eq_ignore_ascii_case(name)) {
+                    Some(index) => Expr::Field(index),
+                    None => return Err(format!("unknown column {}", name)),
+                }
+            },
+            Expr::Field(_) | Expr::Literal(_) => self.clone(),
+            Expr::Compare(left, op, right) => Expr::Compare(bind(left)?, *op, bind(right)?),
+            Expr::IsNull(expr, negated) => Expr::IsNull(bind(expr)?, *negated),
+            Expr::In(expr, list, negated) => {
+                let list = list.iter().map(|item| item.bind(columns)).collect::<Result<_, _>>()?;
+                Expr::In(bind(expr)?, list, *negated)
+            },
+            Expr::Like(expr, pattern, negated) => Expr::Like(bind(expr)?, bind(pattern)?, *negated),
+            Expr::Between(expr, low, high, negated) => {
+                Expr::Between(bind(expr)?, bind(low)?, bind(high)?, *negated)
+            },
+            Expr::And(left, right) => Expr::And(bind(left)?, bind(right)?),
+            Expr::Or(left, right) => Expr::Or(bind(left)?, bind(right)?),
+            Expr::Not(expr) => Expr::Not(bind(expr)?),
+        };
+        Ok(expr)
+    }
+
+    fn value<'a>(&'a self, row: &'a [Value<'a>]) -> Option<&'a Value<'a>> {
+        match self {
+            Expr::Field(index) => row.get(*index),
+            Expr::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    // unknown, NULL in SQL, is None
+    fn eval(&self, row: &[Value]) -> Option<bool> {
+        match self {
+            Expr::Compare(left, op, right) => {
+                let ordering = compare(left.value(row)?, right.value(row)?)?;
+                Some(match op {
+                    Op::Eq => ordering == Ordering::Equal,
+                    Op::NotEq => ordering != Ordering::Equal,
+                    Op::Lt => ordering == Ordering::Less,
+                    Op::LtEq => ordering != Ordering::Greater,
+                    Op::Gt => ordering == Ordering::Greater,
+                    Op::GtEq => ordering != Ordering::Less,
+                })
+            },
+            Expr::IsNull(expr, negated) => Some(expr.value(row)?.is_null() != *negated),
+            Expr::In(expr, list, negated) => {
+                let value = expr.value(row)?;
+                let mut unknown = false;
+                for item in list {
+                    match compare(value, item.value(row)?) {
+                        Some(Ordering::Equal) => return Some(!negated),
+                        Some(_) => {},
+                        None => unknown = true,
+                    }
+                }
+                if unknown { None } else { Some(*negated) }
+            },
+            Expr::Like(expr, pattern, negated) => {
+                let text = expr.value(row)?.text()?;
+                let pattern = pattern.value(row)?.text()?;
+                Some(like(&pattern, &text) != *negated)
+            },
+            Expr::Between(expr, low, high, negated) => {
+                let value = expr.value(row)?;
+                let inside = compare(value, low.value(row)?)? != Ordering::Less &&
+                    compare(value, high.value(row)?)? != Ordering::Greater;
+                Some(inside != *negated)
+            },
+            Expr::And(left, right) => {
+                match (left.eval(row), right.eval(row)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            },
+            Expr::Or(left, right) => {
+                match (left.eval(row), right.eval(row)) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            },
+            Expr::Not(expr) => expr.eval(row).map(|value| !value),
+            // a bare column or value is true when it isn't zero
+            _ => {
+                let value = self.value(row)?;
+                compare(value, &Value::Int(0)).map(|ordering| ordering != Ordering::Equal)
+            },
+        }
+    }
+
+    /// Whether a row is kept, rows the condition is unknown for are not.
+    pub fn matches(&self, row: &[Value]) -> bool {
+        self.eval(row) == Some(true)
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Decimal(value) => value.parse().ok(),
+        Value::Float(value) => Some(*value),
+        Value::Bit(value) => Some(*value as f64),
+        Value::String(text) => String::from_utf8_lossy(text).trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn numeric(value: &Value) -> bool {
+    matches!(value, Value::Int(_) | Value::Decimal(_) | Value::Float(_) | Value::Bit(_))
+}
+
+// numbers compare as numbers, the rest as case insensitive text
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    if left.is_null() || right.is_null() {
+        return None
+    }
+
+    if let (Value::Int(left), Value::Int(right)) = (left, right) {
+        return Some(left.cmp(right))
+    }
+
+    if numeric(left) || numeric(right) {
+        if let (Some(left), Some(right)) = (number(left), number(right)) {
+            return left.partial_cmp(&right)
+        }
+    }
+
+    let left = left.text()?.to_ascii_lowercase();
+    let right = right.text()?.to_ascii_lowercase();
+    Some(left.cmp(&right))
+}
+
+// `%` matches any run of bytes and `_` a single byte, case insensitive
+fn like(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'%') => (0..=text.len()).any(|skip| like(&pattern[1..], &text[skip..])),
+        Some(b'\\') if pattern.len() > 1 => {
+            !text.is_empty() && text[0] == pattern[1] && like(&pattern[2..], &text[1..])
+        },
+        Some(b'_') => !text.is_empty() && like(&pattern[1..], &text[1..]),
+        Some(byte) => {
+            !text.is_empty() && text[0].eq_ignore_ascii_case(byte) && like(&pattern[1..], &text[1..])
+        },
+    }
+}
+
+/// Conditions of `--where 'table: condition'`, a row has to
+/// match all of the ones for its table.
+#[derive(Debug,Default)]
+pub struct RowFilter {
+    conditions: Vec<(TableFilter, Expr)>,
+}
+
+impl RowFilter {
+    pub fn parse(conditions: &[&str]) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for condition in conditions {
+            let index = match condition.find(':') {
+                Some(index) => index,
+                None => return Err(format!("{}: expected table: condition", condition)),
+            };
+
+            let table = condition[..index].trim().to_string();
+            match Expr::parse(&condition[index + 1..]) {
+                Ok(expr) => filter.conditions.push((TableFilter::new(vec![table], vec![]), expr)),
+                Err(e) => return Err(format!("{}: {}", condition, e)),
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// The conditions for a table bound to its columns, None when it has none.
+    pub fn bind(&self, table: &Table, columns: &[String]) -> Result<Option<Expr>, String> {
+        let mut bound: Option<Expr> = None;
+        for (filter, expr) in &self.conditions {
+            if !filter.allows(table) {
+                continue
+            }
+
+            let expr = match expr.bind(columns) {
+                Ok(expr) => expr,
+                Err(e) => return Err(format!("{}: {}", table, e)),
+            };
+            bound = Some(match bound {
+                Some(bound) => Expr::And(boxed(bound), boxed(expr)),
+                None => expr,
+            });
+        }
+        Ok(bound)
+    }
+}
+
+
+#[cfg(test)]
+mod predicate_test{
+    use value::Fields;
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        vec!["id", "tenant_id", "created_at", "email", "deleted_at"].into_iter().map(String::from).collect()
+    }
+
+    fn keeps(condition: &str, row: &[u8]) -> bool {
+        let expr = Expr::parse(condition).unwrap().bind(&columns()).unwrap();
+        let values: Vec<Value> = Fields::new(row).collect();
+        expr.matches(&values)
+    }
+
+    #[test]
+    fn conditions(){
+        let row = b"(3,7,'2024-02-01 10:00:00','Ann@Example.com',NULL),";
+        assert!(keeps("created_at >= \"2024-01-01\"", row));
+        assert!(!keeps("created_at < '2024-01-01'", row));
+        assert!(keeps("tenant_id IN (4,7)", row));
+        assert!(!keeps("tenant_id NOT IN (4,'7')", row));
+        assert!(keeps("id = '3' AND email LIKE '%@example.com'", row));
+        assert!(keeps("id BETWEEN 1 AND 3 OR tenant_id = 1", row));
+        assert!(keeps("deleted_at IS NULL and not (id > 5)", row));
+        assert!(keeps("id != -3", row));
+        // comparing to NULL is unknown
+        assert!(!keeps("deleted_at = NULL", row));
+        assert!(!keeps("NOT deleted_at = 1", row));
+    }
+
+    #[test]
+    fn errors(){
+        assert!(Expr::parse("id >").is_err());
+        assert!(Expr::parse("id = 1 1").is_err());
+        assert!(Expr::parse("missing = 1").unwrap().bind(&columns()).is_err());
+        assert!(RowFilter::parse(&["id = 1"]).is_err());
+
+        let filter = RowFilter::parse(&["users: id = 1", "u*: tenant_id = 2"]).unwrap();
+        let users = Table { schema: None, name: "users".to_string() };
+        let orders = Table { schema: None, name: "orders".to_string() };
+        assert!(matches!(filter.bind(&users, &columns()), Ok(Some(Expr::And(_, _)))));
+        assert_eq!(filter.bind(&orders, &columns()), Ok(None));
+    }
+}
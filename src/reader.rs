@@ -9,6 +9,8 @@ pub struct Reader<T>{
     index: usize,
     reader: BufReader<T>,
     bytes_read: usize,
+    // bytes dropped from the buffer so far
+    consumed: u64,
 }
 
 impl<T> Reader<T> where T: io::Read {
@@ -19,6 +21,7 @@ impl<T> Reader<T> where T: io::Read {
             reader: BufReader::new(file),
             index: 0,
             bytes_read: 0,
+            consumed: 0,
         };
 
         reader.read_buf();
@@ -26,50 +29,59 @@ impl<T> Reader<T> where T: io::Read {
     }
 
     #[inline(always)]
-    fn raw_get(&mut self) -> Option<u8> {
-        if self.bytes_read == 0 {
-            return None
+    fn raw_get(&mut self, ahead: usize) -> Option<u8> {
+        if self.index + ahead >= self.bytes_read {
+            // out of index load next buffer
+            self.read_buf();
         }
 
-        if let Some(item) = self.buffer.get(self.index) {
-            return if self.bytes_read <= self.index {
-                None
-            }else{
-                Some(*item)
-            }
+        if self.index + ahead < self.bytes_read {
+            Some(self.buffer[self.index + ahead])
+        }else{
+            None
         }
-
-        // out of index load next buffer
-        self.read_buf();
-        self.raw_get()
     }
 
     pub fn get(&mut self) -> Option<u8> {
-        let byte = self.raw_get();
+        let byte = self.raw_get(0);
         self.index += 1;
         byte
     }
 
     pub fn peek(&mut self) -> Option<u8> {
-        self.raw_get()
+        self.raw_get(0)
     }
 
     pub fn peek_next(&mut self) -> Option<u8> {
-        self.index += 1;
-        let item = self.raw_get();
-        self.index -= 1;
-        item
+        self.raw_get(1)
     }
 
+    pub fn peek_at(&mut self, ahead: usize) -> Option<u8> {
+        self.raw_get(ahead)
+    }
 
+    // keeps the unread tail of the buffer so peeks can look
+    // across the boundary of two reads.
     fn read_buf(&mut self) {
-        match self.reader.read(&mut self.buffer) {
-            Ok(size) => {
-                self.bytes_read = size;
-                self.index = 0; // reset index
-            },
-            Err(err) => {
-                panic!("{:?}", err);
+        let start = self.index.min(self.bytes_read);
+        self.buffer.copy_within(start..self.bytes_read, 0);
+        self.bytes_read -= start;
+        self.index -= start;
+        self.consumed += start as u64;
+
+        loop {
+            match self.reader.read(&mut self.buffer[self.bytes_read..]) {
+                Ok(0) => break,
+                Ok(size) => {
+                    self.bytes_read += size;
+                    if self.bytes_read == DEFAULT_BUF_SIZE {
+                        break
+                    }
+                },
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => {
+                    panic!("{:?}", err);
+                }
             }
         }
     }
@@ -77,6 +89,16 @@ impl<T> Reader<T> where T: io::Read {
     pub fn increment_index(&mut self){
         self.index += 1;
     }
+
+    /// Counts from `position`, the input was read up to it before.
+    pub fn starts_at(&mut self, position: u64) {
+        self.consumed = position - self.index as u64;
+    }
+
+    /// Offset of the next byte in the input.
+    pub fn position(&self) -> u64 {
+        self.consumed + self.index.min(self.bytes_read) as u64
+    }
 }
 
 
@@ -118,8 +140,8 @@ mod reader_test{
         assert_eq!(reader.get(), Some(b'8'));
         assert_eq!(reader.get(), Some(b'9'));
         assert_eq!(reader.get(), Some(b'0'));
-        assert_eq!(reader.get().is_none(), true);
-        assert_eq!(reader.get().is_none(), true);
+        assert!(reader.get().is_none());
+        assert!(reader.get().is_none());
     }
 
     #[test]
@@ -142,4 +164,23 @@ mod reader_test{
         assert_eq!(reader.peek(), Some(b'3'));
         assert_eq!(reader.peek_next(), Some(b'4'));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn peek_across_buffer(){
+        let mut content = vec![b'a'; super::DEFAULT_BUF_SIZE - 1];
+        content.extend(b"/*!");
+        let mut reader = Reader::new(&content[..]);
+        for _ in 0..super::DEFAULT_BUF_SIZE - 1 {
+            reader.increment_index();
+        }
+
+        assert_eq!(reader.peek(), Some(b'/'));
+        assert_eq!(reader.peek_next(), Some(b'*'));
+        assert_eq!(reader.peek_at(2), Some(b'!'));
+        assert_eq!(reader.peek_at(3), None);
+        assert_eq!(reader.get(), Some(b'/'));
+        assert_eq!(reader.get(), Some(b'*'));
+        assert_eq!(reader.get(), Some(b'!'));
+        assert_eq!(reader.get(), None);
+    }
+}
@@ -0,0 +1,326 @@
+use tokenizer::Tokenizer;
+use tokenizer::Token;
+use tokenizer::SyntaxErr;
+use reader::Reader;
+use parser::Table;
+use parser::Kind;
+use parser::Object;
+use parser::significant;
+use parser::identifier;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+// words that can follow the keywords a table name follows
+const NOT_NAMES: &[&str] = &[
+    "if", "not", "exists", "on", "set", "select", "where", "dual", "ignore",
+    "low_priority", "delayed", "high_priority", "quick", "for", "as", "values",
+];
+
+/// `--rename-table`, `--rename-schema` and `--table-prefix`. Names are
+/// rewritten in the tokens of the statements, so everything else about
+/// them stays as it was dumped.
+#[derive(Debug,Default)]
+pub struct Renames {
+    tables: Vec<(Table, String)>,
+    schemas: HashMap<String, String>,
+    prefix: String,
+}
+
+fn pair(rule: &str) -> Result<(&str, &str), String> {
+    match rule.find('=') {
+        Some(index) if index > 0 && index < rule.len() - 1 => {
+            Ok((rule[..index].trim(), rule[index + 1..].trim()))
+        },
+        _ => Err(format!("{}: expected old=new", rule)),
+    }
+}
+
+// written the way the name it replaces was, quoted or not
+fn quote(name: &str, quoted: bool) -> Vec<u8> {
+    let plain = name.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_') &&
+        name.bytes().next().is_some_and(|byte| byte.is_ascii_alphabetic());
+    if plain && !quoted {
+        name.as_bytes().to_vec()
+    }else{
+        format!("`{}`", name.replace('`', "``")).into_bytes()
+    }
+}
+
+fn quoted(token: &Token) -> bool {
+    matches!(token, Token::Identifier(_))
+}
+
+fn name(token: &Token) -> Option<String> {
+    match token {
+        Token::Keyword(_) if NOT_NAMES.iter().any(|word| token.keyword(word)) => None,
+        _ => identifier(token),
+    }
+}
+
+impl Renames {
+    pub fn parse(tables: &[&str], schemas: &[&str], prefix: Option<&str>) -> Result<Self, String> {
+        let mut renames = Self::default();
+        for rule in tables {
+            let (old, new) = pair(rule)?;
+            let old = match old.find('.') {
+                Some(index) => Table { schema: Some(old[..index].to_string()), name: old[index + 1..].to_string() },
+                None => Table { schema: None, name: old.to_string() },
+            };
+            renames.tables.push((old, new.to_string()));
+        }
+
+        for rule in schemas {
+            let (old, new) = pair(rule)?;
+            renames.schemas.insert(old.to_string(), new.to_string());
+        }
+
+        renames.prefix = prefix.unwrap_or("").to_string();
+        Ok(renames)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.schemas.is_empty() && self.prefix.is_empty()
+    }
+
+    pub fn schema(&self, schema: &str) -> String {
+        match self.schemas.get(schema) {
+            Some(schema) => schema.clone(),
+            None => schema.to_string(),
+        }
+    }
+
+    // tables renamed one by one don't get the prefix
+    fn name(&self, table: &Table) -> String {
+        let renamed = self.tables.iter().find(|(old, _)| {
+            old.name == table.name && (old.schema.is_none() || old.schema == table.schema)
+        });
+
+        match renamed {
+            Some((_, new)) => new.clone(),
+            None => format!("{}{}", self.prefix, table.name),
+        }
+    }
+
+    pub fn table(&self, table: &Table) -> Table {
+        Table {
+            schema: table.schema.as_ref().map(|schema| self.schema(schema)),
+            name: self.name(table),
+        }
+    }
+
+    /// Renames the tables and schemas a statement names. Table names come after
+    /// `TABLE`, `VIEW`, `INTO`, `REFERENCES`, `FROM`, `JOIN` and `UPDATE`, the `ON`
+    /// of triggers and indexes, in the lists of `DROP TABLE` and `LOCK TABLES`,
+    /// and as the qualifier of columns in view and trigger bodies.
+    pub fn statement(&self, kind: Kind, sql: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+        let mut tokenizer = Tokenizer::new(Reader::new(sql));
+        let mut tokens = vec![];
+        while let Some(token) = tokenizer.token()? {
+            tokens.push(token);
+        }
+
+        let positions: Vec<usize> = (0..tokens.len()).filter(|&index| significant(&tokens[index])).collect();
+        let mut replaced: HashMap<usize, Vec<u8>> = HashMap::new();
+        let mut tables = HashSet::new();
+        let mut on_seen = false;
+        // `DROP TABLE a, b` and `LOCK TABLES a WRITE, b READ`
+        let list = matches!(kind, Kind::Drop(_) | Kind::Lock);
+        // open parentheses, true for the ones of a function call like
+        // `EXTRACT(YEAR FROM ..)`, where `FROM` isn't followed by a table
+        let mut parens: Vec<bool> = vec![];
+        let mut names = false;
+
+        let mut index = 0;
+        while index < positions.len() {
+            let token = &tokens[positions[index]];
+            let before = if index > 0 { Some(&tokens[positions[index - 1]]) } else { None };
+            match token {
+                Token::LP => {
+                    let called = before.is_some_and(|before| matches!(before, Token::Keyword(_) | Token::Identifier(_)));
+                    let subquery = positions.get(index + 1).is_some_and(|&next| tokens[next].keyword("select"));
+                    parens.push(called && !subquery);
+                },
+                Token::RP => {
+                    parens.pop();
+                },
+                _ => {},
+            }
+            let in_call = parens.last() == Some(&true);
+
+            let follows = token.keyword("table") || token.keyword("tables") || token.keyword("view") ||
+                token.keyword("references") || (token.keyword("from") && !in_call) || token.keyword("join") ||
+                (token.keyword("like") && kind == Kind::Create(Object::Table)) ||
+                (token.keyword("update") && !before.is_some_and(|before| before.keyword("key"))) ||
+                (token.keyword("into") && before.is_some_and(|before| {
+                    ["insert", "replace", "ignore", "low_priority", "delayed", "high_priority"].iter().any(|word| before.keyword(word))
+                })) ||
+                (token.keyword("on") && !on_seen && matches!(kind,
+                    Kind::Create(Object::Trigger) | Kind::Create(Object::Index) | Kind::Drop(Object::Index)));
+            if token.keyword("on") {
+                on_seen = true;
+            }
+
+            if list && parens.is_empty() && *token == Token::Comma && names {
+                index += 1;
+                index = self.name_at(&tokens, &positions, index, &mut replaced, &mut tables);
+                continue
+            }
+
+            if follows {
+                names = true;
+                index += 1;
+                // `FROM (`a` JOIN `b`)` and `IF NOT EXISTS`
+                while index < positions.len() && (tokens[positions[index]] == Token::LP ||
+                    ["if", "not", "exists"].iter().any(|word| tokens[positions[index]].keyword(word))) {
+                    if tokens[positions[index]] == Token::LP {
+                        parens.push(false);
+                    }
+                    index += 1;
+                }
+                index = self.name_at(&tokens, &positions, index, &mut replaced, &mut tables);
+                continue
+            }
+
+            if kind == Kind::Use || ((token.keyword("database") || token.keyword("schema")) && kind.is_ddl()) {
+                let mut next = index + 1;
+                while next < positions.len() && ["if", "not", "exists"].iter().any(|word| tokens[positions[next]].keyword(word)) {
+                    next += 1;
+                }
+                if let Some(position) = positions.get(next) {
+                    if let Some(schema) = name(&tokens[*position]) {
+                        replaced.insert(*position, quote(&self.schema(&schema), quoted(&tokens[*position])));
+                    }
+                }
+                break
+            }
+            index += 1;
+        }
+
+        // `users`.`id` and `shop`.`users`.`id` in the bodies of views and triggers
+        if matches!(kind, Kind::Create(Object::View) | Kind::Alter(Object::View) | Kind::Create(Object::Trigger)) {
+            for index in 0..positions.len() {
+                let dotted = |offset: usize| positions.get(index + offset).is_some_and(|&position| tokens[position] == Token::Dot);
+                let starts = index == 0 || tokens[positions[index - 1]] != Token::Dot;
+                if !starts || !dotted(1) || replaced.contains_key(&positions[index]) {
+                    continue
+                }
+
+                let first = match name(&tokens[positions[index]]) {
+                    Some(first) => first,
+                    None => continue,
+                };
+
+                if dotted(3) {
+                    let table = positions[index + 2];
+                    if let Some(name) = name(&tokens[table]) {
+                        let table_name = Table { schema: Some(first.clone()), name };
+                        replaced.insert(table, quote(&self.name(&table_name), quoted(&tokens[table])));
+                    }
+                    replaced.insert(positions[index], quote(&self.schema(&first), quoted(&tokens[positions[index]])));
+                }else if tables.contains(&first) {
+                    let table = Table { schema: None, name: first };
+                    replaced.insert(positions[index], quote(&self.name(&table), quoted(&tokens[positions[index]])));
+                }
+            }
+        }
+
+        let mut sql = vec![];
+        for (position, token) in tokens.into_iter().enumerate() {
+            match replaced.remove(&position) {
+                Some(name) => sql.extend(name),
+                None => sql.extend(token.value()),
+            }
+        }
+        Ok(sql)
+    }
+
+    // renames the optionally schema qualified name at a significant token,
+    // returns the index of the token after it
+    fn name_at(&self, tokens: &[Token], positions: &[usize], index: usize, replaced: &mut HashMap<usize, Vec<u8>>, tables: &mut HashSet<String>) -> usize {
+        let first_position = match positions.get(index) {
+            Some(&position) => position,
+            None => return index,
+        };
+
+        let first = match name(&tokens[first_position]) {
+            Some(first) => first,
+            None => return index,
+        };
+
+        let qualified = positions.get(index + 1).is_some_and(|&position| tokens[position] == Token::Dot);
+        let second = positions.get(index + 2).and_then(|&position| name(&tokens[position]));
+        match second {
+            Some(second) if qualified => {
+                let second_position = positions[index + 2];
+                let table = Table { schema: Some(first.clone()), name: second.clone() };
+                replaced.insert(first_position, quote(&self.schema(&first), quoted(&tokens[first_position])));
+                replaced.insert(second_position, quote(&self.name(&table), quoted(&tokens[second_position])));
+                tables.insert(second);
+                index + 3
+            },
+            _ => {
+                let table = Table { schema: None, name: first.clone() };
+                replaced.insert(first_position, quote(&self.name(&table), quoted(&tokens[first_position])));
+                tables.insert(first);
+                index + 1
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod rename_test{
+    use parser::Kind;
+    use parser::Object;
+    use super::*;
+
+    fn renames() -> Renames {
+        Renames::parse(&["users=members"], &["shop=staging_42"], Some("tmp_")).unwrap()
+    }
+
+    fn rename(kind: Kind, sql: &str) -> String {
+        String::from_utf8(renames().statement(kind, sql.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn tables(){
+        assert_eq!(
+            rename(Kind::Create(Object::Table), "CREATE TABLE IF NOT EXISTS `orders` (\n  `user_id` int,\n  CONSTRAINT `fk` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`)\n);"),
+            "CREATE TABLE IF NOT EXISTS `tmp_orders` (\n  `user_id` int,\n  CONSTRAINT `fk` FOREIGN KEY (`user_id`) REFERENCES `members` (`id`)\n);"
+        );
+        assert_eq!(rename(Kind::Drop(Object::Table), "DROP TABLE IF EXISTS `a`, shop.b;"), "DROP TABLE IF EXISTS `tmp_a`, staging_42.tmp_b;");
+        assert_eq!(rename(Kind::Lock, "LOCK TABLES `users` WRITE, `orders` READ LOCAL;"), "LOCK TABLES `members` WRITE, `tmp_orders` READ LOCAL;");
+        assert_eq!(rename(Kind::Insert, "INSERT IGNORE INTO `shop`.`users` VALUES "), "INSERT IGNORE INTO `staging_42`.`members` VALUES ");
+        assert_eq!(rename(Kind::Keys, "/*!40000 ALTER TABLE `users` DISABLE KEYS */;"), "/*!40000 ALTER TABLE `members` DISABLE KEYS */;");
+        assert_eq!(rename(Kind::Use, "USE `shop`;"), "USE `staging_42`;");
+        assert_eq!(
+            rename(Kind::Create(Object::Database), "CREATE DATABASE /*!32312 IF NOT EXISTS*/ `shop` /*!40100 DEFAULT CHARACTER SET utf8mb4 */;"),
+            "CREATE DATABASE /*!32312 IF NOT EXISTS*/ `staging_42` /*!40100 DEFAULT CHARACTER SET utf8mb4 */;"
+        );
+    }
+
+    #[test]
+    fn bodies(){
+        assert_eq!(
+            rename(Kind::Create(Object::View), "/*!50001 VIEW `active` AS select `users`.`id` AS `id` from `users` where (`users`.`logins` > 0) */;"),
+            "/*!50001 VIEW `tmp_active` AS select `members`.`id` AS `id` from `members` where (`members`.`logins` > 0) */;"
+        );
+        assert_eq!(
+            rename(Kind::Create(Object::Trigger), "CREATE TRIGGER `t` BEFORE UPDATE ON `users` FOR EACH ROW BEGIN\n  INSERT INTO `log` SET `id` = NEW.id;\nEND;;"),
+            "CREATE TRIGGER `t` BEFORE UPDATE ON `members` FOR EACH ROW BEGIN\n  INSERT INTO `tmp_log` SET `id` = NEW.id;\nEND;;"
+        );
+        // `FROM` of a function call isn't followed by a table, the one of a subquery is
+        assert_eq!(
+            rename(Kind::Create(Object::View), "VIEW `v` AS select extract(year from `created_at`) AS `y`, trim(leading 0 from `id`) AS `i`, \
+                substring(`name` from 2) AS `n` from `users` where `id` in (select `id` from `orders`)"),
+            "VIEW `tmp_v` AS select extract(year from `created_at`) AS `y`, trim(leading 0 from `id`) AS `i`, \
+                substring(`name` from 2) AS `n` from `members` where `id` in (select `id` from `tmp_orders`)"
+        );
+        // not a table
+        assert_eq!(rename(Kind::Insert, "INSERT INTO `a` VALUES (1) ON DUPLICATE KEY UPDATE `b` = 1;"), "INSERT INTO `tmp_a` VALUES (1) ON DUPLICATE KEY UPDATE `b` = 1;");
+        assert!(Renames::parse(&["users"], &[], None).is_err());
+        // a statement it can't read is an error, not cut short
+        assert!(renames().statement(Kind::Create(Object::View), b"VIEW `v` AS select 'a from `users`").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use tokenizer::Tokenizer;
+use tokenizer::Token;
+use tokenizer::SyntaxErr;
+use reader::Reader;
+use parser::Kind;
+use parser::Object;
+use parser::significant;
+
+/// What happens to the `DEFINER=` clauses of views, triggers and routines.
+#[derive(Debug,PartialEq,Clone,Default)]
+pub enum Definer {
+    #[default]
+    Keep,
+    Strip,
+    // `user`@`host` written in place of the dumped one
+    Set(Vec<u8>),
+}
+
+/// Rewrites of the statements that don't load on another server as dumped.
+#[derive(Debug,Default)]
+pub struct Rewrites {
+    pub definer: Definer,
+    pub strip_use: bool,
+    pub strip_create_database: bool,
+    // table options of `CREATE TABLE`
+    pub engine: Option<String>,
+    pub charset: Option<String>,
+    pub collation: Option<String>,
+    pub strip_auto_increment: bool,
+    pub strip_row_format: bool,
+}
+
+fn tokens(sql: &[u8]) -> Result<Vec<Token>, SyntaxErr> {
+    let mut tokenizer = Tokenizer::new(Reader::new(sql));
+    let mut tokens = vec![];
+    while let Some(token) = tokenizer.token()? {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// `utf8mb4` of `utf8mb4_0900_ai_ci`
+fn collation_charset(collation: &str) -> &str {
+    collation.split('_').next().unwrap_or(collation)
+}
+
+fn text(token: &Token) -> String {
+    let value = token.clone().value();
+    String::from_utf8_lossy(&value).trim_matches(|c| c == '`' || c == '\'' || c == '"').to_string()
+}
+
+fn account(part: &str) -> String {
+    let part = part.trim().trim_matches(|c| c == '`' || c == '\'' || c == '"');
+    format!("`{}`", part.replace('`', "``"))
+}
+
+impl Definer {
+    /// `--definer=app@%`
+    pub fn parse(user_host: &str) -> Result<Self, String> {
+        match user_host.rfind('@') {
+            Some(index) if index > 0 => {
+                let definer = format!("{}@{}", account(&user_host[..index]), account(&user_host[index + 1..]));
+                Ok(Definer::Set(definer.into_bytes()))
+            },
+            _ => Err(format!("{}: expected user@host", user_host)),
+        }
+    }
+}
+
+impl Rewrites {
+    pub fn is_empty(&self) -> bool {
+        self.definer == Definer::Keep && !self.strip_use && !self.strip_create_database &&
+            !self.rewrites_tables()
+    }
+
+    fn rewrites_tables(&self) -> bool {
+        self.engine.is_some() || self.charset.is_some() || self.collation.is_some() ||
+            self.strip_auto_increment || self.strip_row_format
+    }
+
+    // charset columns and tables end up with
+    fn target_charset(&self) -> Option<&str> {
+        self.charset.as_deref().or(self.collation.as_deref().map(collation_charset))
+    }
+
+    /// Statements left out of the output.
+    pub fn drops(&self, kind: Kind) -> bool {
+        match kind {
+            Kind::Use => self.strip_use,
+            Kind::Create(Object::Database) => self.strip_create_database,
+            _ => false,
+        }
+    }
+
+    pub fn statement(&self, kind: Kind, sql: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+        match kind {
+            Kind::Create(Object::Table) if self.rewrites_tables() => self.table(sql),
+            Kind::Create(_) | Kind::Alter(_) if self.definer != Definer::Keep => self.definer(sql),
+            _ => Ok(sql.to_vec()),
+        }
+    }
+
+    fn definer(&self, sql: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+        let tokens = tokens(sql)?;
+        let mut sql = vec![];
+        let mut index = 0;
+        while index < tokens.len() {
+            let definer = tokens[index].keyword("definer") &&
+                tokens.get(index + 1) == Some(&Token::Ignore(b'='));
+            if !definer {
+                sql.extend(tokens[index].clone().value());
+                index += 1;
+                continue
+            }
+
+            // the account runs up to the next white space, `root`@`%` or CURRENT_USER()
+            index += 2;
+            while index < tokens.len() && significant(&tokens[index]) &&
+                !matches!(tokens[index], Token::SemiColon | Token::Comma) {
+                index += 1;
+            }
+
+            match self.definer {
+                Definer::Set(ref definer) => {
+                    sql.extend(b"DEFINER=");
+                    sql.extend(definer);
+                },
+                // along with the space after it
+                _ => if tokens.get(index) == Some(&Token::Space) {
+                    index += 1;
+                },
+            }
+        }
+        Ok(sql)
+    }
+
+    // Rewrites the options after the columns, `ENGINE=MyISAM AUTO_INCREMENT=3 DEFAULT CHARSET=latin1`,
+    // and the `CHARACTER SET` and `COLLATE` of the columns that don't use the new charset.
+    fn table(&self, sql: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+        let tokens = tokens(sql)?;
+        let mut output: Vec<Option<Vec<u8>>> = vec![None; tokens.len()];
+        let positions: Vec<usize> = (0..tokens.len()).filter(|&index| significant(&tokens[index])).collect();
+        let keyword = |index: usize, word: &str| positions.get(index).is_some_and(|&position| tokens[position].keyword(word));
+        let equals = |index: usize| positions.get(index).is_some_and(|&position| tokens[position] == Token::Ignore(b'='));
+
+        // removes the tokens of an option along with the space before it
+        let remove = |output: &mut Vec<Option<Vec<u8>>>, start: usize, end: usize| {
+            let from = positions[start];
+            let from = if from > 0 && tokens[from - 1] == Token::Space { from - 1 } else { from };
+            for token in output.iter_mut().take(positions[end] + 1).skip(from) {
+                *token = Some(vec![]);
+            }
+        };
+
+        let mut depth = 0;
+        let mut columns_done = false;
+        let mut collated = false;
+        let mut last_option = None;
+        let mut index = 0;
+        while index < positions.len() {
+            let token = &tokens[positions[index]];
+            match token {
+                Token::LP => depth += 1,
+                Token::RP => {
+                    depth -= 1;
+                    columns_done = columns_done || depth == 0;
+                },
+                _ => {},
+            }
+
+            if !columns_done && depth == 1 {
+                // column charset, `CHARACTER SET latin1` or `CHARSET latin1`
+                let value = if keyword(index, "character") && keyword(index + 1, "set") {
+                    Some(index + 2)
+                }else if keyword(index, "charset") {
+                    Some(index + 1)
+                }else{
+                    None
+                };
+
+                if let (Some(value), Some(charset)) = (value, self.charset.as_ref()) {
+                    if let Some(&position) = positions.get(value) {
+                        if !text(&tokens[position]).eq_ignore_ascii_case(charset) {
+                            output[position] = Some(charset.as_bytes().to_vec());
+                        }
+                    }
+                }
+
+                if keyword(index, "collate") {
+                    let value = if equals(index + 1) { index + 2 } else { index + 1 };
+                    if let (Some(&position), Some(target)) = (positions.get(value), self.target_charset()) {
+                        let collation = text(&tokens[position]);
+                        if !collation_charset(&collation).eq_ignore_ascii_case(target) {
+                            match self.collation {
+                                Some(ref collation) => output[position] = Some(collation.as_bytes().to_vec()),
+                                None => remove(&mut output, index, value),
+                            }
+                        }
+                    }
+                    index = value;
+                }
+            }
+
+            if !columns_done || depth != 0 || *token == Token::RP {
+                index += 1;
+                continue
+            }
+
+            // table option, with or without `DEFAULT` and `=`
+            let start = index;
+            let name = if keyword(index, "default") { index + 1 } else { index };
+            let (option, words) = if keyword(name, "character") && keyword(name + 1, "set") {
+                ("charset", 2)
+            }else{
+                match positions.get(name).map(|&position| &tokens[position]) {
+                    Some(token) if token.keyword("charset") => ("charset", 1),
+                    Some(token) if token.keyword("collate") => ("collate", 1),
+                    Some(token) if token.keyword("engine") => ("engine", 1),
+                    Some(token) if token.keyword("auto_increment") => ("auto_increment", 1),
+                    Some(token) if token.keyword("row_format") => ("row_format", 1),
+                    _ => ("", 1),
+                }
+            };
+
+            let value = if equals(name + words) { name + words + 1 } else { name + words };
+            let position = match positions.get(value) {
+                Some(&position) if !option.is_empty() && !matches!(tokens[position], Token::SemiColon | Token::Comma) => position,
+                _ => {
+                    index += 1;
+                    continue
+                },
+            };
+
+            match option {
+                "engine" => if let Some(ref engine) = self.engine {
+                    output[position] = Some(engine.as_bytes().to_vec());
+                },
+                "charset" => if let Some(ref charset) = self.charset {
+                    output[position] = Some(charset.as_bytes().to_vec());
+                },
+                "collate" => {
+                    collated = true;
+                    match (&self.collation, &self.charset) {
+                        (Some(collation), _) => output[position] = Some(collation.as_bytes().to_vec()),
+                        (None, Some(charset)) if !collation_charset(&text(&tokens[position])).eq_ignore_ascii_case(charset) => {
+                            remove(&mut output, start, value)
+                        },
+                        _ => {},
+                    }
+                },
+                "auto_increment" if self.strip_auto_increment => remove(&mut output, start, value),
+                "row_format" if self.strip_row_format => remove(&mut output, start, value),
+                _ => {},
+            }
+
+            last_option = Some(position);
+            index = value + 1;
+        }
+
+        let mut sql = vec![];
+        for (position, token) in tokens.into_iter().enumerate() {
+            match output[position].take() {
+                Some(replaced) => sql.extend(replaced),
+                None => sql.extend(token.value()),
+            }
+
+            if Some(position) == last_option && !collated {
+                if let Some(ref collation) = self.collation {
+                    sql.extend(b" COLLATE=");
+                    sql.extend(collation.as_bytes());
+                }
+            }
+        }
+        Ok(sql)
+    }
+}
+
+
+#[cfg(test)]
+mod rewrite_test{
+    use parser::Kind;
+    use parser::Object;
+    use super::*;
+
+    fn rewrite(rewrites: &Rewrites, kind: Kind, sql: &str) -> String {
+        String::from_utf8(rewrites.statement(kind, sql.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn definer(){
+        let strip = Rewrites { definer: Definer::Strip, ..Rewrites::default() };
+        let set = Rewrites { definer: Definer::parse("app@%").unwrap(), ..Rewrites::default() };
+        let trigger = "/*!50003 CREATE*/ /*!50017 DEFINER=`root`@`%`*/ /*!50003 TRIGGER `t` BEFORE INSERT ON `users` FOR EACH ROW SET NEW.a = 1 */;;";
+        assert_eq!(
+            rewrite(&strip, Kind::Create(Object::Trigger), trigger),
+            "/*!50003 CREATE*/ /*!50017 */ /*!50003 TRIGGER `t` BEFORE INSERT ON `users` FOR EACH ROW SET NEW.a = 1 */;;"
+        );
+        assert_eq!(
+            rewrite(&set, Kind::Create(Object::Trigger), trigger),
+            "/*!50003 CREATE*/ /*!50017 DEFINER=`app`@`%`*/ /*!50003 TRIGGER `t` BEFORE INSERT ON `users` FOR EACH ROW SET NEW.a = 1 */;;"
+        );
+
+        let procedure = "CREATE DEFINER=CURRENT_USER() PROCEDURE `p`() SQL SECURITY DEFINER SELECT 1;";
+        assert_eq!(rewrite(&strip, Kind::Create(Object::Procedure), procedure), "CREATE PROCEDURE `p`() SQL SECURITY DEFINER SELECT 1;");
+        assert_eq!(rewrite(&set, Kind::Create(Object::Procedure), procedure), "CREATE DEFINER=`app`@`%` PROCEDURE `p`() SQL SECURITY DEFINER SELECT 1;");
+
+        assert!(Definer::parse("app").is_err());
+        // a statement it can't read is an error, not cut short
+        assert!(strip.statement(Kind::Create(Object::Procedure), b"CREATE DEFINER=`root`@`%` PROCEDURE `p`() SELECT 'a;").is_err());
+    }
+
+    #[test]
+    fn table_options(){
+        let create = "CREATE TABLE `hello` (\n  `id` int NOT NULL AUTO_INCREMENT,\n  \
+            `name` varchar(10) CHARACTER SET latin1 COLLATE latin1_bin DEFAULT NULL,\n  \
+            `code` varchar(10) COLLATE utf8mb4_bin NOT NULL\n\
+            ) ENGINE=MyISAM AUTO_INCREMENT=332 DEFAULT CHARSET=latin1 ROW_FORMAT=DYNAMIC;";
+        let rewrites = Rewrites {
+            engine: Some("InnoDB".to_string()),
+            charset: Some("utf8mb4".to_string()),
+            collation: Some("utf8mb4_0900_ai_ci".to_string()),
+            strip_auto_increment: true,
+            strip_row_format: true,
+            ..Rewrites::default()
+        };
+        assert_eq!(
+            rewrite(&rewrites, Kind::Create(Object::Table), create),
+            "CREATE TABLE `hello` (\n  `id` int NOT NULL AUTO_INCREMENT,\n  \
+            `name` varchar(10) CHARACTER SET utf8mb4 COLLATE utf8mb4_0900_ai_ci DEFAULT NULL,\n  \
+            `code` varchar(10) COLLATE utf8mb4_bin NOT NULL\n\
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci;"
+        );
+
+        // a collation of the old charset can't stay
+        let charset = Rewrites { charset: Some("utf8mb4".to_string()), ..Rewrites::default() };
+        assert_eq!(
+            rewrite(&charset, Kind::Create(Object::Table), "CREATE TABLE `a` (`b` int) ENGINE=InnoDB DEFAULT CHARSET latin1 COLLATE=latin1_swedish_ci;"),
+            "CREATE TABLE `a` (`b` int) ENGINE=InnoDB DEFAULT CHARSET utf8mb4;"
+        );
+        assert_eq!(rewrite(&charset, Kind::Create(Object::View), "CREATE VIEW `v` AS SELECT 1;"), "CREATE VIEW `v` AS SELECT 1;");
+    }
+
+    #[test]
+    fn drops(){
+        let rewrites = Rewrites { strip_use: true, strip_create_database: true, ..Rewrites::default() };
+        assert!(rewrites.drops(Kind::Use));
+        assert!(rewrites.drops(Kind::Create(Object::Database)));
+        assert!(!rewrites.drops(Kind::Create(Object::Table)));
+        assert!(!Rewrites::default().drops(Kind::Use));
+    }
+}
@@ -0,0 +1,148 @@
+use filter::TableFilter;
+use parser::Table;
+
+/// How much of a table `--sample` and `--sample-rows` keep.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Amount {
+    Fraction(f64),
+    Rows(usize),
+}
+
+/// `--sample=0.05`, `--sample-rows=10000` or per table, `--sample=users=0.05`.
+/// Rules for a table win over the ones for every table.
+#[derive(Debug,Default)]
+pub struct Sampling {
+    tables: Vec<(TableFilter, Amount)>,
+    all: Option<Amount>,
+    pub seed: u64,
+}
+
+impl Sampling {
+    pub fn parse(fractions: &[&str], rows: &[&str], seed: u64) -> Result<Self, String> {
+        let mut sampling = Sampling { seed, ..Self::default() };
+        for (rule, fraction) in fractions.iter().map(|rule| (rule, true)).chain(rows.iter().map(|rule| (rule, false))) {
+            let (table, amount) = match rule.find('=') {
+                Some(index) => (Some(rule[..index].trim()), rule[index + 1..].trim()),
+                None => (None, rule.trim()),
+            };
+
+            let amount = if fraction {
+                match amount.parse::<f64>() {
+                    Ok(amount) if (0.0..=1.0).contains(&amount) => Amount::Fraction(amount),
+                    _ => return Err(format!("{}: sample should be between 0 and 1", rule)),
+                }
+            }else{
+                match amount.parse::<usize>() {
+                    Ok(amount) => Amount::Rows(amount),
+                    Err(_) => return Err(format!("{}: sample rows should be a number", rule)),
+                }
+            };
+
+            match table {
+                Some(table) => sampling.tables.push((TableFilter::new(vec![table.to_string()], vec![]), amount)),
+                None => sampling.all = Some(amount),
+            }
+        }
+        Ok(sampling)
+    }
+
+    pub fn amount(&self, table: &Table) -> Option<Amount> {
+        self.tables.iter()
+            .find(|(filter, _)| filter.allows(table))
+            .map(|(_, amount)| *amount)
+            .or(self.all)
+    }
+}
+
+// splitmix64, small and the same everywhere
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Keeps a uniform sample of `size` items out of a stream of unknown
+/// length, in the order they came in.
+#[derive(Debug)]
+pub struct Reservoir<T> {
+    size: usize,
+    seen: usize,
+    items: Vec<(usize, T)>,
+    state: u64,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(size: usize, seed: u64, table: &Table) -> Self {
+        // each table gets its own sequence
+        let state = table.name.bytes().fold(seed, |state, byte| {
+            state.rotate_left(5) ^ u64::from(byte)
+        });
+        Reservoir { size, seen: 0, items: vec![], state }
+    }
+
+    pub fn offer(&mut self, item: T) {
+        let position = self.seen;
+        self.seen += 1;
+        if self.items.len() < self.size {
+            self.items.push((position, item));
+            return
+        }
+
+        let index = (next(&mut self.state) % self.seen as u64) as usize;
+        if index < self.size {
+            self.items[index] = (position, item);
+        }
+    }
+
+    pub fn into_items(mut self) -> Vec<T> {
+        self.items.sort_by_key(|(position, _)| *position);
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod sample_test{
+    use parser::Table;
+    use super::*;
+
+    fn table(name: &str) -> Table {
+        Table { schema: None, name: name.to_string() }
+    }
+
+    #[test]
+    fn rules(){
+        let sampling = Sampling::parse(&["0.05", "logs=0.5"], &["users=10"], 3).unwrap();
+        assert_eq!(sampling.amount(&table("orders")), Some(Amount::Fraction(0.05)));
+        assert_eq!(sampling.amount(&table("logs")), Some(Amount::Fraction(0.5)));
+        assert_eq!(sampling.amount(&table("users")), Some(Amount::Rows(10)));
+
+        assert_eq!(Sampling::parse(&[], &["users=10"], 0).unwrap().amount(&table("orders")), None);
+        assert!(Sampling::parse(&["2"], &[], 0).is_err());
+        assert!(Sampling::parse(&[], &["users=ten"], 0).is_err());
+    }
+
+    #[test]
+    fn reservoir(){
+        let sample = |seed| {
+            let mut reservoir = Reservoir::new(10, seed, &table("users"));
+            for row in 0..1000 {
+                reservoir.offer(row);
+            }
+            reservoir.into_items()
+        };
+
+        let rows = sample(1);
+        assert_eq!(rows.len(), 10);
+        assert!(rows.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(rows, sample(1));
+        assert_ne!(rows, sample(2));
+
+        let mut reservoir = Reservoir::new(10, 1, &table("users"));
+        reservoir.offer(1);
+        reservoir.offer(2);
+        assert_eq!(reservoir.into_items(), vec![1, 2]);
+    }
+}
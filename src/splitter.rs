@@ -1,13 +1,131 @@
+mod rows;
+mod route;
+mod envelope;
+
 use parser::TokenStream;
 use parser::Parser;
+use parser::Table;
+use parser::Kind;
+use parser::Object;
+use statement::Statement;
+use filter::TableFilter;
+use mask::Masks;
+use predicate::RowFilter;
+use subset::Selection;
+use sample::Sampling;
+use rename::Renames;
+use rewrite::Rewrites;
+use postgres;
+use sqlite;
+use delimited::Delimited;
+use checkpoint::Checkpoint;
+use output::data_file;
+use dataset;
+use dataset::Dataset;
+use value::Fields;
 use tokenizer::Tokenizer;
 use tokenizer::SyntaxErr;
 use reader::Reader;
 use std::io;
+use std::collections::VecDeque;
+use self::rows::Rows;
+use self::route::Router;
+use self::envelope::Envelope;
 
 pub struct SplitterSettings<T>{
     pub write: usize,
     pub file: T,
+    pub filter: TableFilter,
+    pub mode: Mode,
+    pub layout: Layout,
+    pub masks: Masks,
+    pub row_filter: RowFilter,
+    pub selection: Selection,
+    pub sampling: Sampling,
+    pub renames: Renames,
+    pub rewrites: Rewrites,
+    pub to: Dialect,
+    pub format: Format,
+    pub delimited: Delimited,
+    pub strict: bool,
+    // SQL dialect of the dump
+    pub dialect: Dialect,
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Mode {
+    All,
+    // DDL, views, routines and triggers
+    SchemaOnly,
+    // inserts and the session statements around them
+    DataOnly,
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Layout {
+    // numbered files of about the same size
+    Chunks,
+    // schema, data, views, routines and triggers in their own
+    // files so they can be loaded in stages, like mydumper does
+    Mydumper,
+}
+
+/// SQL dialect written out, or read with `--dialect`.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Dialect {
+    Mysql,
+    // inserts become `COPY .. FROM stdin` blocks
+    Postgres,
+    // data of each file in a transaction
+    Sqlite,
+}
+
+/// How the rows are written out. Formats other than SQL
+/// only have the rows, in files of their table.
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Format {
+    Sql,
+    Csv,
+    Tsv,
+    // an object for each row, `{"id":1,"name":"Ann"}`
+    Jsonl,
+    // a Parquet file for each table, its schema from `CREATE TABLE`
+    Parquet,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Sql => "sql",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Jsonl => "jsonl",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// The file a chunk belongs to.
+#[derive(Debug,PartialEq,Eq,Hash,Clone)]
+pub enum Target {
+    // numbered file of the chunks layout
+    Part,
+    Schema(Table),
+    // statements to run after the data is loaded, like `ALTER TABLE .. ADD KEY`
+    SchemaPost(Table),
+    Data(Table),
+    Views,
+    Routines,
+    Triggers,
+    // `LOAD DATA` statements for the files of rows
+    LoadData,
+}
+
+impl Target {
+    // files of the statements mysqldump sets the session up for
+    fn session(&self) -> bool {
+        matches!(self, Target::Views | Target::Routines | Target::Triggers)
+    }
 }
 
 
@@ -22,36 +140,179 @@ impl std::fmt::Display for FileState {
         use splitter::FileState;
 
         match self{
-            FileState::New => write!(f, "{}", "New"),
-            FileState::Continue => write!(f, "{}", "Continue")
+            FileState::New => write!(f, "New"),
+            FileState::Continue => write!(f, "Continue")
         }        
     }
 }
 
+/// What the last chunk holds, for the manifest of the split.
+#[derive(Debug,Default,Clone)]
+pub struct Tally {
+    // bytes of the dump it came from
+    pub source: (u64, u64),
+    // table of its rows
+    pub table: Option<Table>,
+    pub rows: u64,
+    // statements it starts, an insert continued from the file before counts again
+    pub statements: u64,
+}
+
 pub struct Splitter<T>{
     parser: Parser<T>,
     total_bytes: usize,
     max_write_size:usize,
     last_insert: Vec<u8>,
+    filter: TableFilter,
+    // dropping the statement of a filtered table,
+    // its values tuples and white space that follows
+    skipping: bool,
+    mode: Mode,
+    // delimiter of the statements being sent
+    delimiter: Vec<u8>,
+    layout: Layout,
+    // files the statements and rows go to
+    router: Router,
+    // what every file starts and ends with
+    envelope: Envelope,
+    // what happens to the rows on their way out
+    rows: Rows,
+    renames: Renames,
+    rewrites: Rewrites,
+    to: Dialect,
+    format: Format,
+    // the insert has rows written, and no `;` yet
+    insert_open: bool,
+    // file of the rows of the insert being written
+    row_target: Option<Target>,
+    // the last values tuple ended with a `,`
+    in_values: bool,
+    values_space: Vec<u8>,
+    // where the white space before the next values tuple starts in the dump
+    space_offset: u64,
+    // statement read while the sampled rows before it were written
+    held: Option<TokenStream>,
+    // statements written at the end of the dump, like foreign keys
+    deferred: Vec<u8>,
+    deferred_statements: u64,
+    // statements to write before reading on
+    queue: VecDeque<(Target, Vec<u8>)>,
+    // Parquet file being written, and the end of one to write before reading on
+    dataset: Option<(Target, Dataset)>,
+    closed: Option<(Target, Vec<u8>)>,
+    // the file has a `COPY` with rows, and no `\.` yet
+    copy_open: bool,
+    // table of the rows being written
+    rows_table: Option<Table>,
+    // bytes of the dump the last token stream was read from
+    source: (u64, u64),
+    tally: Tally,
 }
 
 pub enum SplitterState{
     SyntaxErr(SyntaxErr),
     // Reached output limit. send the chunk
-    Chunk(FileState, Vec<u8>),
+    Chunk(Target, FileState, Vec<u8>),
     // reached the EOF.
     Done,
 }
 
 impl<T> Splitter<T> where T: io::Read {
-    pub fn new(settings: SplitterSettings<T>) -> Self {
-        let tokenizer = Tokenizer::new(Reader::new(settings.file));
+    pub fn new(mut settings: SplitterSettings<T>) -> Self {
+        let rows = Rows::new(&mut settings);
+        let tokenizer = Tokenizer::for_dialect(Reader::new(settings.file), settings.dialect);
         Self {
             parser: Parser::new(tokenizer),
             total_bytes: 0,
             last_insert: vec![],
             max_write_size: settings.write,
+            filter: settings.filter,
+            skipping: false,
+            mode: settings.mode,
+            delimiter: vec![b';'],
+            layout: settings.layout,
+            router: Router::new(settings.layout, settings.format),
+            envelope: Envelope::new(settings.layout, settings.to, settings.dialect),
+            rows,
+            renames: settings.renames,
+            rewrites: settings.rewrites,
+            to: settings.to,
+            format: settings.format,
+            insert_open: false,
+            row_target: None,
+            in_values: false,
+            values_space: vec![],
+            space_offset: 0,
+            held: None,
+            deferred: vec![],
+            deferred_statements: 0,
+            queue: VecDeque::new(),
+            dataset: None,
+            closed: None,
+            copy_open: false,
+            rows_table: None,
+            source: (0, 0),
+            tally: Tally::default(),
+        }
+    }
+
+    /// What the last chunk holds.
+    pub fn tally(&self) -> &Tally {
+        &self.tally
+    }
+
+    /// State to resume the split from, None while statements or rows
+    /// are held back to be written later.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        let holding = !self.values_space.is_empty() || !self.queue.is_empty() || self.closed.is_some() ||
+            self.held.is_some() || self.dataset.is_some() || !self.deferred.is_empty() ||
+            self.rows.holding() || self.envelope.holding() || self.router.holding();
+        if holding {
+            return None
+        }
+
+        Some(Checkpoint {
+            offset: self.parser.position(),
+            total_bytes: self.total_bytes,
+            insert: self.last_insert.clone(),
+            delimiter: self.delimiter.clone(),
+            preamble: self.envelope.preamble().to_vec(),
+            envelope: self.envelope.begin().cloned(),
+            started: self.router.target().is_some(),
+            rows: self.row_target.is_some(),
+            in_values: self.in_values,
+            skipping: self.skipping,
+            copy_open: self.copy_open,
+            copying: self.parser.copying(),
+            conditional: self.parser.in_conditional(),
+            ..Checkpoint::default()
+        })
+    }
+
+    /// Goes on with a split into numbered files from its checkpoint,
+    /// the dump has to be read from the offset of it.
+    pub fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.parser.resume(checkpoint.offset, checkpoint.delimiter.clone(), checkpoint.copying, checkpoint.conditional);
+        self.source = (checkpoint.offset, checkpoint.offset);
+        self.total_bytes = checkpoint.total_bytes;
+        self.last_insert = checkpoint.insert.clone();
+        self.delimiter = checkpoint.delimiter.clone();
+        self.envelope.resume(checkpoint.preamble.clone(), checkpoint.envelope.clone());
+        if checkpoint.started {
+            self.router.resume();
+        }
+        if checkpoint.rows {
+            self.row_target = Some(Target::Part);
         }
+        self.in_values = checkpoint.in_values;
+        self.skipping = checkpoint.skipping;
+        self.copy_open = checkpoint.copy_open;
+    }
+
+    /// Statements repeated at the start of every file, the session `SET`s
+    /// of the mydumper layout or the pragmas of a sqlite dump.
+    pub fn preamble(&self) -> &[u8] {
+        self.envelope.preamble()
     }
 
     fn file_state(&self, starting_total: usize) -> FileState {
@@ -62,13 +323,65 @@ impl<T> Splitter<T> where T: io::Read {
         }
     }
 
-    fn send(&mut self, tokens: Vec<u8>, starting_total: usize) -> SplitterState {
-        self.total_bytes += tokens.len();
-        if self.reached_limit(self.total_bytes) {
+    fn send(&mut self, target: Target, mut tokens: Vec<u8>, starting_total: usize) -> SplitterState {
+        let file_state = match target {
+            Target::Part | Target::Data(_) => self.file_state(starting_total),
+            _ => self.router.file_state(&target),
+        };
+
+        if file_state == FileState::New {
+            tokens = self.envelope.open(&target, &self.delimiter, tokens);
+        }
+
+        if let (FileState::New, Target::Data(ref table)) = (&file_state, &target) {
+            let file = data_file(table, self.router.data_file(table), self.format);
+            if let Some(statement) = self.rows.load_data(&file, table) {
+                self.queue.push_back((Target::LoadData, statement));
+            }
+        }
+
+        if let Target::Part | Target::Data(_) = target {
+            self.total_bytes += tokens.len();
+            if self.reached_limit(self.total_bytes) {
+                self.total_bytes = 0;
+                self.envelope.close(&target, &mut tokens);
+            }
+        }
+
+        self.router.sent(&target);
+        SplitterState::Chunk(target, file_state, tokens)
+    }
+
+    // switches the data file when the inserts move on to another table
+    fn data_target(&mut self, table: Option<Table>) -> Option<Target> {
+        let target = self.router.data_target(table);
+        if self.router.moved_on() {
+            self.total_bytes = 0;
+        }
+        target
+    }
+
+    fn route(&mut self, kind: Kind, tokens: Vec<u8>, table: Option<Table>) -> Option<SplitterState> {
+        // only the session statements before the first
+        // object make it to the preamble
+        if self.router.preamble(kind) {
+            self.envelope.add_preamble(&tokens);
+            return None
+        }
+
+        let routed = self.router.route(kind, tokens, table, &self.delimiter, self.source);
+        if self.router.moved_on() {
             self.total_bytes = 0;
         }
+        if let Some(source) = routed.source {
+            self.tally.source = source;
+        }
 
-        SplitterState::Chunk(self.file_state(starting_total), tokens)
+        let mut chunks = routed.chunks.into_iter();
+        let (target, tokens) = chunks.next()?;
+        self.queue.extend(chunks);
+        let starting_total = self.total_bytes;
+        Some(self.send(target, tokens, starting_total))
     }
 
     fn reached_limit(&self, total: usize) -> bool{
@@ -79,43 +392,1104 @@ impl<T> Splitter<T> where T: io::Read {
         chunk.extend(&self.last_insert)
     }
 
-    fn close_values_tuple(&self, chunk: &mut Vec<u8>){
+    fn allowed(&self, table: &Option<Table>) -> bool {
+        match table {
+            Some(table) => self.filter.allows(table),
+            None => true,
+        }
+    }
+
+    // a `LOCK TABLES` of several tables stays when all of them do
+    fn locks_allowed(&self, kind: Kind, tokens: &[u8], table: &Option<Table>) -> bool {
+        if kind != Kind::Lock || table.is_some() {
+            return true
+        }
+        match Statement::parse(tokens) {
+            Statement::Lock { tables } => tables.iter().all(|table| self.filter.allows(table)),
+            _ => true,
+        }
+    }
+
+    fn keeps(&self, kind: Kind) -> bool {
+        match self.mode {
+            Mode::All => true,
+            Mode::SchemaOnly => {
+                !matches!(kind, Kind::Insert | Kind::Lock | Kind::Unlock | Kind::Keys)
+            },
+            Mode::DataOnly => !kind.is_ddl(),
+        }
+    }
+
+    // the header of a file of rows, only a new file gets one
+    fn rows_head(&self, starting_total: usize) -> Vec<u8> {
+        if starting_total == 0 {
+            self.last_insert.clone()
+        }else{
+            vec![]
+        }
+    }
+
+    // Writes a row the `--where` condition, the subset and the sample keep. Rows go out
+    // without the `,` or `;` after them, the next kept row adds the `,` and the end
+    // of the insert or of the file adds the `;`. so dropped rows never leave an
+    // empty or unterminated insert behind.
+    fn filter_row(&mut self, target: Target, head: Vec<u8>, tuple: &[u8], starting_total: usize) -> Option<SplitterState> {
+        let last = tuple.last() == Some(&b';');
+        let row = &tuple[..tuple.len() - 1];
+        if !self.rows.keeps(row) {
+            if last && self.insert_open {
+                self.insert_open = false;
+                let end = self.rows.insert_end().to_vec();
+                return Some(self.send(target, end, starting_total))
+            }
+            return None
+        }
+
+        let row = self.rows.convert(row)?;
+        Some(self.write_row(target, head, row, last, starting_total))
+    }
+
+    fn write_row(&mut self, target: Target, head: Vec<u8>, row: Vec<u8>, last: bool, starting_total: usize) -> SplitterState {
+        if self.format == Format::Parquet {
+            return self.write_dataset(target, &row, starting_total)
+        }
+
+        // sampled rows are written before the next insert is read
+        self.tally.rows = 1;
+        self.tally.table = self.rows_table.clone();
+        if !self.insert_open && self.format == Format::Sql {
+            self.tally.statements = 1;
+        }
+
+        let mut chunk = vec![];
+        if !self.insert_open {
+            if self.format == Format::Sql && self.to == Dialect::Sqlite {
+                self.envelope.begin_data(&target, &mut chunk);
+            }
+            chunk.extend(head);
+        }else if self.format == Format::Sql && self.to != Dialect::Postgres {
+            chunk.push(b',');
+        }
+        chunk.extend(row);
+
+        self.insert_open = true;
+        let end = self.rows.insert_end();
+        if last || self.reached_limit(starting_total + chunk.len() + end.len()) {
+            chunk.extend(end);
+            self.insert_open = false;
+        }
+        self.send(target, chunk, starting_total)
+    }
+
+    // Rows of a `COPY` are split like values tuples, a file that fills up in
+    // the middle ends the copy and the next one starts it over.
+    fn write_copy(&mut self, target: Target, mut chunk: Vec<u8>, starting_total: usize) -> SplitterState {
+        self.copy_open = true;
+        if self.reached_limit(starting_total + chunk.len()) {
+            chunk.extend(b"\\.\n");
+            self.copy_open = false;
+        }
+        self.send(target, chunk, starting_total)
+    }
+
+    // Rows of a Parquet file are counted by the size of their values tuples,
+    // the file is closed with its footer once they reach the limit.
+    fn write_dataset(&mut self, target: Target, row: &[u8], starting_total: usize) -> SplitterState {
+        if self.dataset.is_none() {
+            let fields = if self.rows.parquet_fields().is_empty() {
+                // columns of a table the dump doesn't define go by position
+                (1..=Fields::new(row).count()).map(|position| dataset::Field::text(&position.to_string())).collect()
+            }else{
+                self.rows.parquet_fields().to_vec()
+            };
+            match Dataset::new(fields, self.rows.strict()) {
+                Ok(dataset) => self.dataset = Some((target.clone(), dataset)),
+                Err(text) => return SplitterState::SyntaxErr(SyntaxErr{ text }),
+            }
+        }
+
+        let (_, dataset) = self.dataset.as_mut().expect("opened above");
+        let mut bytes = match dataset.push(row) {
+            Ok(bytes) => bytes,
+            Err(text) => return SplitterState::SyntaxErr(SyntaxErr{ text }),
+        };
+
+        let file_state = self.file_state(starting_total);
+        self.total_bytes += row.len();
+        if self.reached_limit(self.total_bytes) {
+            self.total_bytes = 0;
+            if let Some((_, dataset)) = self.dataset.take() {
+                match dataset.finish() {
+                    Ok(end) => bytes.extend(end),
+                    Err(text) => return SplitterState::SyntaxErr(SyntaxErr{ text }),
+                }
+            }
+        }
+
+        self.router.sent(&target);
+        SplitterState::Chunk(target, file_state, bytes)
+    }
+
+    // the Parquet file being written ends when the rows move on to another table
+    fn close_dataset(&mut self) -> Result<(), SyntaxErr> {
+        if let Some((target, dataset)) = self.dataset.take() {
+            let end = dataset.finish().map_err(|text| SyntaxErr{ text })?;
+            self.closed = Some((target, end));
+        }
+        Ok(())
+    }
+
+    fn next_sampled_row(&mut self) -> Option<SplitterState> {
+        let (target, head, row, last) = self.rows.next_sampled()?;
+        let starting_total = self.total_bytes;
+        match self.write_row(target, head, row, last, starting_total) {
+            // the white space after the inserts went out before the rows
+            SplitterState::Chunk(target, file_state, mut tokens) if last && self.format != Format::Parquet => {
+                tokens.push(b'\n');
+                Some(SplitterState::Chunk(target, file_state, tokens))
+            },
+            state => Some(state),
+        }
+    }
+
+    // foreign keys and the like wait for the data of all tables
+    fn defer(&mut self, table: Option<Table>, tokens: Vec<u8>) {
+        if tokens.is_empty() {
+            return
+        }
+
+        match (self.layout, table) {
+            (Layout::Mydumper, Some(table)) => self.queue.push_back((Target::SchemaPost(table), tokens)),
+            _ => {
+                self.deferred.extend(tokens);
+                self.deferred_statements += 1;
+            },
+        }
+    }
+
+    fn close_values_tuple(&self, chunk: &mut [u8]){
         let len = chunk.len() - 1;
         chunk[len] = b';';
     }
-    
+
+    // the statement renamed, rewritten and translated, None when
+    // the dialect written has nothing like it
+    fn statement(&mut self, kind: Kind, mut tokens: Vec<u8>, table: &mut Option<Table>) -> Result<Option<Vec<u8>>, SyntaxErr> {
+        if !self.renames.is_empty() {
+            tokens = self.renames.statement(kind, &tokens)?;
+            *table = table.take().map(|table| self.renames.table(&table));
+        }
+
+        if !self.rewrites.is_empty() {
+            tokens = self.rewrites.statement(kind, &tokens)?;
+        }
+
+        Ok(match (self.to, kind) {
+            (Dialect::Mysql, _) => Some(tokens),
+            (Dialect::Postgres, Kind::Create(Object::Table)) => {
+                postgres::create_table(&tokens).map(|(create, post)| {
+                    self.defer(table.clone(), post);
+                    create
+                })
+            },
+            (Dialect::Postgres, _) => postgres::statement(kind, &tokens)?,
+            (_, Kind::Create(Object::Table)) => sqlite::create_table(&tokens),
+            (_, _) => sqlite::statement(kind, &tokens)?,
+        })
+    }
+
     pub fn process(&mut self) -> SplitterState {
-        let starting_total = self.total_bytes;
-        match self.parser.token_stream() {
-            Ok(Some(item)) => {
-                match item {
-                    TokenStream::Insert(insert_with_values, insert_stmt) => {
-                        self.last_insert = insert_stmt;                        
-                        self.send(insert_with_values, starting_total)
-                    },
-                    TokenStream::ValuesTuple(tokens) => {
-                        let mut ret = vec![];
-                        // starting with fresh collection
-                        // push last insert statement
-                        if starting_total == 0 {
-                            self.copy_last_insert(&mut ret);
-                        }
-
-                        ret.extend(tokens);
-                        // maxed out in value tuple close statement
-                        if self.reached_limit(starting_total + ret.len()) {
-                            self.close_values_tuple(&mut ret)
-                        }
-                        
-                        self.send(ret, starting_total)
+        loop {
+            self.tally = Tally { source: self.source, ..Tally::default() };
+            if let Some(target) = self.envelope.take_commit() {
+                return SplitterState::Chunk(target, FileState::Continue, b"COMMIT;\n".to_vec())
+            }
+
+            if let Some((target, bytes)) = self.closed.take() {
+                return SplitterState::Chunk(target, FileState::Continue, bytes)
+            }
+
+            if let Some(state) = self.next_sampled_row() {
+                return state
+            }
+
+            if let Some((target, tokens)) = self.queue.pop_front() {
+                self.tally.statements = match target {
+                    Target::Part => ::std::mem::take(&mut self.deferred_statements),
+                    _ => 1,
+                };
+                let starting_total = self.total_bytes;
+                return self.send(target, tokens, starting_total)
+            }
+
+            let starting_total = self.total_bytes;
+            let offset = self.parser.position();
+            let item = match self.held.take() {
+                Some(item) => item,
+                None => match self.parser.token_stream() {
+                    Ok(Some(item)) => item,
+                    Ok(None) => match self.end() {
+                        Some(state) => return state,
+                        None => continue,
                     },
-                    TokenStream::Block(tokens) => self.send(tokens, starting_total),
-                    TokenStream::Comment(tokens) |
-                    TokenStream::SpaceOrLineFeed(tokens) => self.send(tokens, starting_total),
+                    Err(e) => return SplitterState::SyntaxErr(e),
+                },
+            };
+
+            if self.parser.position() != offset {
+                self.source = (offset, self.parser.position());
+                self.tally.source = self.source;
+            }
+
+            if self.rows.ends_sample(&item) {
+                self.rows.flush_sample();
+                self.held = Some(item);
+                continue
+            }
+
+            let state = match item {
+                TokenStream::Insert(insert_with_values, insert_stmt, table) => self.insert(insert_with_values, insert_stmt, table),
+                TokenStream::ValuesTuple(tokens) => self.values_tuple(tokens, starting_total),
+                TokenStream::Block(kind, tokens, table) => self.block(kind, tokens, table, starting_total),
+                TokenStream::Copy(tokens, table) => self.copy(tokens, table),
+                TokenStream::CopyRow(tokens) => self.copy_row(tokens, starting_total),
+                TokenStream::CopyEnd(tokens) => self.copy_end(tokens, starting_total),
+                TokenStream::Comment(tokens) => self.comment(tokens, starting_total),
+                TokenStream::SpaceOrLineFeed(tokens) => self.space(tokens, starting_total),
+            };
+
+            if let Some(state) = state {
+                return state
+            }
+        }
+    }
+
+    // the dump is read, what was held back goes out before it's done
+    fn end(&mut self) -> Option<SplitterState> {
+        if self.router.holding() {
+            let chunks = self.router.settle();
+            self.queue.extend(chunks);
+        }else if self.rows.sampling() {
+            self.rows.flush_sample();
+        }else if !self.deferred.is_empty() {
+            let mut deferred = b"\n".to_vec();
+            deferred.append(&mut self.deferred);
+            self.queue.push_back((Target::Part, deferred));
+        }else if self.dataset.is_some() {
+            if let Err(e) = self.close_dataset() {
+                return Some(SplitterState::SyntaxErr(e))
+            }
+        }else if !self.envelope.commit_at_end() {
+            return Some(SplitterState::Done)
+        }
+        None
+    }
+
+    fn insert(&mut self, mut insert_with_values: Vec<u8>, mut insert_stmt: Vec<u8>, mut table: Option<Table>) -> Option<SplitterState> {
+        self.skipping = !self.allowed(&table) || !self.keeps(Kind::Insert);
+        if self.skipping {
+            return None
+        }
+
+        let columns = match self.rows.bind(&insert_stmt, &table) {
+            Ok(columns) => columns,
+            Err(e) => return Some(SplitterState::SyntaxErr(e)),
+        };
+
+        // everything before is about the table as dumped
+        if !self.renames.is_empty() {
+            let renamed = match self.renames.statement(Kind::Insert, &insert_stmt) {
+                Ok(renamed) => renamed,
+                Err(e) => return Some(SplitterState::SyntaxErr(e)),
+            };
+            let values = insert_with_values.split_off(insert_stmt.len() - 1);
+            insert_with_values = renamed[..renamed.len() - 1].to_vec();
+            insert_with_values.extend(values);
+            insert_stmt = renamed;
+            table = table.map(|table| self.renames.table(&table));
+        }
+
+        self.rows_table = table.clone();
+        let target = self.data_target(table)?;
+
+        let reopens = match self.dataset {
+            Some((ref open, ref dataset)) => {
+                *open != target || (!self.rows.parquet_fields().is_empty() && dataset.fields() != self.rows.parquet_fields())
+            },
+            None => false,
+        };
+        if reopens {
+            if let Err(e) = self.close_dataset() {
+                return Some(SplitterState::SyntaxErr(e))
+            }
+            self.total_bytes = 0;
+        }
+
+        // the column list is in parentheses too, rows start after `VALUES`
+        let head = insert_stmt.len() - 1;
+        let insert_stmt = match self.rows.head(insert_stmt, columns) {
+            Ok(insert_stmt) => insert_stmt,
+            Err(e) => return Some(SplitterState::SyntaxErr(e)),
+        };
+        self.rows.sample_into(&target, &insert_stmt);
+
+        self.row_target = Some(target.clone());
+        self.last_insert = insert_stmt;
+        let starting_total = self.total_bytes;
+
+        self.in_values = insert_with_values.last() == Some(&b',');
+        if self.rows.filters() {
+            self.insert_open = false;
+            let tuple = insert_with_values.split_off(head);
+            let head = match (self.format, self.to) {
+                (Format::Csv, _) | (Format::Tsv, _) | (Format::Jsonl, _) | (Format::Parquet, _) => self.rows_head(starting_total),
+                (Format::Sql, Dialect::Mysql) => insert_with_values,
+                (Format::Sql, Dialect::Postgres) => self.last_insert.clone(),
+                // the first tuple has the white space after `VALUES`
+                (Format::Sql, Dialect::Sqlite) => self.last_insert[..self.last_insert.len() - 1].to_vec(),
+            };
+            return self.filter_row(target, head, &tuple, starting_total)
+        }
+
+        let tuple = self.rows.mask(insert_with_values.split_off(head));
+        insert_with_values.extend(tuple);
+
+        // the first tuple can fill the file as well
+        if self.reached_limit(starting_total + insert_with_values.len()) &&
+            insert_with_values.last() == Some(&b',') {
+            self.close_values_tuple(&mut insert_with_values)
+        }
+
+        self.tally.rows = 1;
+        self.tally.statements = 1;
+        self.tally.table = self.rows_table.clone();
+        Some(self.send(target, insert_with_values, starting_total))
+    }
+
+    fn values_tuple(&mut self, mut tokens: Vec<u8>, starting_total: usize) -> Option<SplitterState> {
+        if self.skipping {
+            return None
+        }
+
+        self.in_values = tokens.last() == Some(&b',');
+        if !self.values_space.is_empty() {
+            self.tally.source.0 = self.space_offset;
+            let mut space = ::std::mem::take(&mut self.values_space);
+            space.extend(tokens);
+            tokens = space;
+        }
+
+        let target = if self.router.per_table() {
+            self.row_target.clone()?
+        }else{
+            Target::Part
+        };
+
+        if self.rows.filters() {
+            let head = match self.format {
+                Format::Sql => self.last_insert.clone(),
+                Format::Csv | Format::Tsv | Format::Jsonl | Format::Parquet => self.rows_head(starting_total),
+            };
+            return self.filter_row(target, head, &tokens, starting_total)
+        }
+
+        let mut ret = vec![];
+        // starting with fresh collection
+        // push last insert statement
+        if starting_total == 0 {
+            self.copy_last_insert(&mut ret);
+            self.tally.statements = 1;
+        }
+        self.tally.rows = 1;
+        self.tally.table = self.rows_table.clone();
+
+        ret.extend(self.rows.mask(tokens));
+        // maxed out in value tuple close statement
+        if self.reached_limit(starting_total + ret.len()) {
+            self.close_values_tuple(&mut ret)
+        }
+
+        Some(self.send(target, ret, starting_total))
+    }
+
+    fn block(&mut self, kind: Kind, tokens: Vec<u8>, mut table: Option<Table>, starting_total: usize) -> Option<SplitterState> {
+        if kind == Kind::Create(Object::Table) && self.rows.needs_columns() {
+            self.rows.define(&tokens);
+        }
+
+        self.skipping = !self.allowed(&table) || !self.locks_allowed(kind, &tokens, &table) || !self.keeps(kind) ||
+            self.rewrites.drops(kind) || self.format != Format::Sql;
+        if self.skipping {
+            return None
+        }
+        self.tally.statements = 1;
+
+        let tokens = match self.statement(kind, tokens, &mut table) {
+            Ok(Some(tokens)) => tokens,
+            Ok(None) => {
+                self.skipping = true;
+                return None
+            },
+            Err(e) => return Some(SplitterState::SyntaxErr(e)),
+        };
+        let tokens = self.envelope.statement(tokens);
+
+        let begin = self.envelope.opens(kind, &tokens);
+        self.envelope.pragma(kind, &tokens, self.router.target().is_some());
+        // the file before has committed when it filled up
+        if self.envelope.ends(kind, &tokens) && starting_total == 0 {
+            return None
+        }
+
+        let state = match self.layout {
+            Layout::Chunks => Some(self.send(Target::Part, tokens, starting_total)),
+            Layout::Mydumper => self.route(kind, tokens, table),
+        };
+
+        if let Some(begin) = begin {
+            self.envelope.opened(begin);
+        }
+
+        if kind == Kind::Delimiter {
+            self.delimiter = self.parser.delimiter().to_vec();
+        }
+        state
+    }
+
+    fn copy(&mut self, tokens: Vec<u8>, table: Option<Table>) -> Option<SplitterState> {
+        self.skipping = !self.allowed(&table) || !self.keeps(Kind::Insert);
+        if self.skipping {
+            return None
+        }
+
+        self.rows_table = table.clone();
+        let target = self.data_target(table)?;
+        self.row_target = Some(target.clone());
+        self.last_insert = tokens.clone();
+        self.tally.statements = 1;
+        self.tally.table = self.rows_table.clone();
+        let starting_total = self.total_bytes;
+        Some(self.write_copy(target, tokens, starting_total))
+    }
+
+    fn copy_row(&mut self, tokens: Vec<u8>, starting_total: usize) -> Option<SplitterState> {
+        if self.skipping {
+            return None
+        }
+
+        let target = self.row_target.clone()?;
+        let mut chunk = vec![];
+        if !self.copy_open {
+            self.copy_last_insert(&mut chunk);
+            self.tally.statements = 1;
+        }
+        chunk.extend(tokens);
+        self.tally.rows = 1;
+        self.tally.table = self.rows_table.clone();
+        Some(self.write_copy(target, chunk, starting_total))
+    }
+
+    fn copy_end(&mut self, tokens: Vec<u8>, starting_total: usize) -> Option<SplitterState> {
+        // the file that filled up has ended the copy already
+        if self.skipping || !self.copy_open {
+            return None
+        }
+
+        self.copy_open = false;
+        let target = self.row_target.clone()?;
+        Some(self.send(target, tokens, starting_total))
+    }
+
+    fn comment(&mut self, tokens: Vec<u8>, starting_total: usize) -> Option<SplitterState> {
+        if self.format != Format::Sql || self.layout == Layout::Mydumper {
+            return None
+        }
+        Some(self.send(Target::Part, tokens, starting_total))
+    }
+
+    fn space(&mut self, tokens: Vec<u8>, starting_total: usize) -> Option<SplitterState> {
+        if self.skipping || self.format != Format::Sql {
+            return None
+        }
+
+        // white space between values tuples goes with the next
+        // one, so a file never starts in the middle of an insert
+        if self.in_values {
+            if self.values_space.is_empty() {
+                self.space_offset = self.source.0;
+            }
+            self.values_space.extend(tokens);
+            return None
+        }
+
+        match self.layout {
+            Layout::Chunks => Some(self.send(Target::Part, tokens, starting_total)),
+            Layout::Mydumper => {
+                // white space stays with the statement
+                // before it as long as its file is open
+                if self.router.holds_space(&tokens) {
+                    return None
                 }
+                let target = match self.router.target() {
+                    Some(Target::Data(_)) if starting_total == 0 => return None,
+                    Some(target) => target.clone(),
+                    None => return None,
+                };
+                Some(self.send(target, tokens, starting_total))
             },
-            Ok(None) => SplitterState::Done,
-            Err(e) => SplitterState::SyntaxErr(e),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub mod splitter_test{
+    use super::*;
+    use subset::Subset;
+    use rewrite::Definer;
+    use std::fs;
+    use std::io::Cursor;
+
+    pub fn settings(dump: &str) -> SplitterSettings<Cursor<Vec<u8>>> {
+        SplitterSettings {
+            write: 1024 * 1024,
+            file: Cursor::new(dump.as_bytes().to_vec()),
+            filter: TableFilter::default(),
+            mode: Mode::All,
+            layout: Layout::Chunks,
+            masks: Masks::default(),
+            row_filter: RowFilter::default(),
+            selection: Selection::default(),
+            sampling: Sampling::default(),
+            renames: Renames::default(),
+            rewrites: Rewrites::default(),
+            to: Dialect::Mysql,
+            format: Format::Sql,
+            delimited: Delimited::default(),
+            strict: false,
+            dialect: Dialect::Mysql,
+        }
+    }
+
+    // the files written, in the order they were opened
+    pub fn split(settings: SplitterSettings<Cursor<Vec<u8>>>) -> Vec<(Target, String)> {
+        let mut splitter = Splitter::new(settings);
+        let mut files: Vec<(Target, String)> = vec![];
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(target, file_state, tokens) => {
+                    let tokens = String::from_utf8(tokens).unwrap();
+                    match files.iter_mut().rev().find(|(opened, _)| *opened == target) {
+                        Some((_, file)) if file_state == FileState::Continue => file.push_str(&tokens),
+                        _ => files.push((target, tokens)),
+                    }
+                },
+                SplitterState::Done => break,
+                SplitterState::SyntaxErr(e) => panic!("{}", e.text),
+            }
+        }
+        files
+    }
+
+    fn table(name: &str) -> Table {
+        Table { schema: None, name: name.to_string() }
+    }
+
+    fn dump() -> String {
+        fs::read_to_string("./example-files/mysqldump.txt").unwrap()
+    }
+
+    // the only file written, for the settings writing everything to one
+    fn single(settings: SplitterSettings<Cursor<Vec<u8>>>) -> String {
+        let mut files = split(settings);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, Target::Part);
+        files.remove(0).1
+    }
+
+    #[test]
+    fn chunks(){
+        let dump = dump();
+        let files = split(SplitterSettings { write: 1024, ..settings(&dump) });
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|(target, _)| *target == Target::Part));
+        // statements are written as they are, files end between them
+        let joined: String = files.iter().map(|(_, file)| file.as_str()).collect();
+        assert_eq!(joined, dump);
+        assert!(files[0].1.ends_with("/*!40000 ALTER TABLE `users` DISABLE KEYS */;"));
+        assert!(files[1].1.starts_with("\nINSERT INTO `users`"));
+    }
+
+    #[test]
+    fn tables(){
+        let dump = dump();
+        let filter = TableFilter::new(vec!["users".to_string(), "orders".to_string()], vec![]);
+        let file = single(SplitterSettings { filter, ..settings(&dump) });
+        assert!(file.contains("CREATE TABLE `users`"));
+        assert!(file.contains("INSERT INTO `orders`"));
+        assert!(!file.contains("CREATE TABLE `audit_log`"));
+        assert!(!file.contains("INSERT INTO `audit_log`"));
+
+        let filter = TableFilter::new(vec![], vec!["orders".to_string()]);
+        let file = single(SplitterSettings { filter, ..settings(&dump) });
+        assert!(file.contains("INSERT INTO `audit_log`"));
+        assert!(!file.contains("CREATE TABLE `orders`"));
+        assert!(!file.contains("INSERT INTO `orders`"));
+        // the statements of the table go with it, the white space after them too
+        assert!(!file.contains("LOCK TABLES `orders`"));
+        assert!(file.ends_with("UNLOCK TABLES;\n/*!40014 SET FOREIGN_KEY_CHECKS=@OLD_FOREIGN_KEY_CHECKS */;\n\
+            /*!40101 SET CHARACTER_SET_CLIENT=@OLD_CHARACTER_SET_CLIENT */;\n\n-- Dump completed on 2024-05-01 10:00:00\n"));
+    }
+
+    #[test]
+    fn schema_and_data_only(){
+        let dump = dump();
+        let file = single(SplitterSettings { mode: Mode::SchemaOnly, ..settings(&dump) });
+        assert_eq!(file.matches("CREATE TABLE").count(), 3);
+        assert!(!file.contains("INSERT"));
+        assert!(!file.contains("LOCK TABLES"));
+        assert!(!file.contains("DISABLE KEYS"));
+
+        let file = single(SplitterSettings { mode: Mode::DataOnly, ..settings(&dump) });
+        assert_eq!(file.matches("INSERT INTO").count(), 3);
+        assert!(file.contains("LOCK TABLES `users` WRITE;"));
+        assert!(file.contains("/*!40101 SET @saved_cs_client     = @@character_set_client */;"));
+        assert!(!file.contains("CREATE TABLE"));
+        assert!(!file.contains("DROP TABLE"));
+    }
+
+    #[test]
+    fn delimiter(){
+        let dump = fs::read_to_string("./example-files/routines.txt").unwrap();
+        let files = split(SplitterSettings { write: 1, ..settings(&dump) });
+        // a file in the middle of the routines switches to their delimiter first
+        let trigger = files.iter().find(|(_, file)| file.contains("TRIGGER `users_before_insert`")).unwrap();
+        assert!(trigger.1.starts_with("DELIMITER ;;\n/*!50003 CREATE*/"));
+        let function = files.iter().find(|(_, file)| file.contains("FUNCTION `login_count`")).unwrap();
+        assert!(function.1.starts_with("DELIMITER ;;\nCREATE DEFINER"));
+    }
+
+    #[test]
+    fn mydumper(){
+        let dump = dump();
+        let files = split(SplitterSettings { layout: Layout::Mydumper, ..settings(&dump) });
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![
+            &Target::Schema(table("users")),
+            &Target::Data(table("users")),
+            &Target::Schema(table("audit_log")),
+            &Target::Data(table("audit_log")),
+            &Target::Schema(table("orders")),
+            &Target::Data(table("orders")),
+        ]);
+
+        // the session statements at the top start every file
+        let preamble = "/*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;\n/*!50503 SET NAMES utf8mb4 */;\n\
+            /*!40014 SET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0 */;\n";
+        assert!(files.iter().all(|(_, file)| file.starts_with(preamble)));
+        assert!(files[2].1.contains("DROP TABLE IF EXISTS `audit_log`;\nCREATE TABLE `audit_log`"));
+        assert!(!files[2].1.contains("CREATE TABLE `users`"));
+        assert_eq!(files[3].1.trim_end(), format!("{}INSERT INTO `audit_log` VALUES (1,1,'login'),(2,2,'login'),(3,1,'logout');", preamble));
+        // comments, locks and the keys statements are left out
+        assert!(files.iter().all(|(_, file)| !file.contains("--") && !file.contains("LOCK TABLES") && !file.contains("KEYS")));
+    }
+
+    #[test]
+    fn mydumper_routines(){
+        let dump = fs::read_to_string("./example-files/routines.txt").unwrap();
+        let files = split(SplitterSettings { layout: Layout::Mydumper, ..settings(&dump) });
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![
+            &Target::Schema(table("users")),
+            &Target::Data(table("users")),
+            &Target::Triggers,
+            &Target::Views,
+            &Target::Routines,
+        ]);
+
+        // routines are written with the delimiter set around each of them
+        let triggers = &files[2].1;
+        assert!(triggers.starts_with("/*!40101 SET NAMES utf8mb4 */;\n/*!50003 SET @saved_cs_client      = @@character_set_client */ ;\n\
+            DELIMITER ;;\n/*!50003 CREATE*/"));
+        assert!(triggers.contains("END */;;\nDELIMITER ;"));
+        let routines = &files[4].1;
+        assert_eq!(routines.matches("DELIMITER ;;\n").count(), 2);
+        assert!(routines.contains("/*!50003 DROP PROCEDURE IF EXISTS `reset_logins` */;"));
+        assert!(!routines.contains("USE `shop`"));
+    }
+
+    #[test]
+    fn mydumper_views(){
+        // the structure mysqldump writes for views, a placeholder
+        // with the tables and the view itself at the end
+        let placeholder = |name: &str| format!("DROP TABLE IF EXISTS `{0}`;\n/*!50001 DROP VIEW IF EXISTS `{0}`*/;\n\
+            SET @saved_cs_client     = @@character_set_client;\n/*!50503 SET character_set_client = utf8mb4 */;\n\
+            /*!50001 CREATE VIEW `{0}` AS SELECT \n 1 AS `id`*/;\nSET character_set_client = @saved_cs_client;\n\n", name);
+        let view = |name: &str| format!("/*!50001 DROP VIEW IF EXISTS `{0}`*/;\n/*!50001 SET @saved_cs_client          = @@character_set_client */;\n\
+            /*!50001 SET character_set_client      = utf8mb4 */;\n/*!50001 CREATE ALGORITHM=UNDEFINED */\n\
+            /*!50001 VIEW `{0}` AS select `users`.`id` AS `id` from `users` */;\n/*!50001 SET character_set_client      = @saved_cs_client */;\n\n", name);
+
+        let dump = fs::read_to_string("./example-files/mysqldump.txt").unwrap();
+        let footer = dump.find("/*!40014 SET FOREIGN_KEY_CHECKS=@OLD_FOREIGN_KEY_CHECKS */;").unwrap();
+        let dump = format!("{}{}{}{}{}{}", &dump[..footer], placeholder("active_users"), placeholder("recent_orders"),
+            view("active_users"), view("recent_orders"), &dump[footer..]);
+
+        let files = split(SplitterSettings { layout: Layout::Mydumper, ..settings(&dump) });
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![
+            &Target::Schema(table("users")),
+            &Target::Data(table("users")),
+            &Target::Schema(table("audit_log")),
+            &Target::Data(table("audit_log")),
+            &Target::Schema(table("orders")),
+            &Target::Data(table("orders")),
+            &Target::Views,
+        ]);
+
+        let preamble = "/*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;\n/*!50503 SET NAMES utf8mb4 */;\n\
+            /*!40014 SET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0 */;\n";
+        assert!(files[0].1.starts_with(&format!("{}DROP TABLE IF EXISTS `users`;\n", preamble)));
+        assert!(files[0].1.contains("CREATE TABLE `users`"));
+
+        let views = &files[6].1;
+        assert!(views.starts_with(preamble));
+        for name in &["active_users", "recent_orders"] {
+            let body = placeholder(name);
+            assert!(views.contains(body.trim_end()), "{}", views);
+            let body = view(name);
+            assert!(views.contains(body.trim_end()), "{}", views);
+        }
+    }
+
+    #[test]
+    fn masks(){
+        let dump = dump();
+        let masks = Masks::parse("[users]\nemail = \"redact\"\n\n[orders]\nnote = \"redact\"").unwrap();
+        let file = single(SplitterSettings { masks, ..settings(&dump) });
+        assert!(!file.contains("ann@example.com"));
+        // columns are found from the `CREATE TABLE` or the column list of the insert
+        assert!(file.contains("(1,'***************','Ann','2024-01-02 10:00:00')"));
+        assert!(file.contains("(2,1,5.00,NULL,'2024-01-15')"));
+        assert!(!file.contains("first"));
+        assert!(file.contains("INSERT INTO `audit_log` VALUES (1,1,'login'),(2,2,'login'),(3,1,'logout');"));
+
+        // a hashed bigint key stays unique, a narrower one would collide
+        let masks = Masks::parse("[audit_log]\nid = \"hash(sha256)\"").unwrap();
+        let file = single(SplitterSettings { masks, ..settings(&dump) });
+        assert!(!file.contains("VALUES (1,1,'login')") && file.contains(",1,'login'),("));
+        let masks = Masks::parse("[audit_log]\nuser_id = \"hash(sha256)\"").unwrap();
+        let mut splitter = Splitter::new(SplitterSettings { masks, ..settings(&dump) });
+        let failed = loop {
+            match splitter.process() {
+                SplitterState::Chunk(..) => {},
+                SplitterState::SyntaxErr(e) => break Some(e.text),
+                SplitterState::Done => break None,
+            }
+        };
+        assert!(failed.is_some_and(|text| text.contains("narrower than bigint")));
+    }
+
+    #[test]
+    fn row_filter(){
+        let dump = dump();
+        let row_filter = RowFilter::parse(&["orders: total > 10", "users: id <> 2"]).unwrap();
+        let file = single(SplitterSettings { row_filter, ..settings(&dump) });
+        assert!(file.contains("INSERT INTO `orders` (`id`, `user_id`, `total`, `note`, `created_at`) VALUES \
+            (1,1,19.99,'first\\norder','2023-12-30'),(3,2,120.50,'','2024-02-01');\n"));
+        assert!(file.contains("INSERT INTO `users` VALUES (1,'ann@example.com','Ann','2024-01-02 10:00:00'),\
+            (3,'o\\'neil@example.com','O\\'Neil','2024-03-04 12:30:00');\n"));
+        assert!(file.contains("INSERT INTO `audit_log` VALUES (1,1,'login'),(2,2,'login'),(3,1,'logout');"));
+
+        // inserts left without rows are dropped
+        let row_filter = RowFilter::parse(&["audit_log: action = 'none'"]).unwrap();
+        let file = single(SplitterSettings { row_filter, ..settings(&dump) });
+        assert!(!file.contains("INSERT INTO `audit_log`"));
+        assert!(file.contains("/*!40000 ALTER TABLE `audit_log` ENABLE KEYS */;"));
+
+        let row_filter = RowFilter::parse(&["users: tenant_id = 4"]).unwrap();
+        let mut splitter = Splitter::new(SplitterSettings { row_filter, ..settings(&dump) });
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(..) => {},
+                SplitterState::Done => panic!("the users table has no tenant_id"),
+                SplitterState::SyntaxErr(e) => break assert_eq!(e.text, "--where names a column the table doesn't have."),
+            }
+        }
+    }
+
+    #[test]
+    fn subset(){
+        let dump = dump();
+        let mut subset = Subset::parse(&["users=0.5"]).unwrap();
+        subset.seed = 7;
+        let selection = subset.plan(&mut Cursor::new(dump.clone())).unwrap();
+        let file = single(SplitterSettings { selection, ..settings(&dump) });
+        // the orders and audit log rows of the kept user come along, no others
+        assert!(file.contains("INSERT INTO `users` VALUES (2,'bob@example.com','Bob',NULL);\n"));
+        assert!(file.contains("INSERT INTO `audit_log` VALUES (2,2,'login');\n"));
+        assert!(file.contains("VALUES (3,2,120.50,'','2024-02-01');\n"));
+        assert!(!file.contains("ann@example.com"));
+        assert!(!file.contains("'logout'"));
+    }
+
+    #[test]
+    fn sample(){
+        let dump = dump();
+        let sampling = Sampling::parse(&[], &["users=2"], 42).unwrap();
+        let file = single(SplitterSettings { sampling, ..settings(&dump) });
+        let users = file.lines().find(|line| line.starts_with("INSERT INTO `users`")).unwrap();
+        assert_eq!(users.matches("example.com").count(), 2);
+        assert!(users.ends_with(");"));
+        assert!(file.contains("INSERT INTO `orders`"));
+        // the sampled rows are written once the inserts of the table end,
+        // before the statements after them
+        let written = file.find("INSERT INTO `users`").unwrap();
+        assert!(written < file.find("/*!40000 ALTER TABLE `users` ENABLE KEYS */;").unwrap());
+
+        // the same seed picks the same rows
+        let sampling = Sampling::parse(&["orders=0.5"], &[], 42).unwrap();
+        let first = single(SplitterSettings { sampling, ..settings(&dump) });
+        let sampling = Sampling::parse(&["orders=0.5"], &[], 42).unwrap();
+        assert_eq!(first, single(SplitterSettings { sampling, ..settings(&dump) }));
+        assert!(first.contains("INSERT INTO `audit_log` VALUES (1,1,'login'),(2,2,'login'),(3,1,'logout');"));
+    }
+
+    #[test]
+    fn shared_locks(){
+        let dump = "CREATE TABLE `a` (`id` int);\nCREATE TABLE `b` (`id` int);\n\
+            LOCK TABLES `a` WRITE, `b` WRITE;\nINSERT INTO `a` VALUES (1);\nINSERT INTO `b` VALUES (2);\nUNLOCK TABLES;\n";
+        let file = single(SplitterSettings { filter: TableFilter::new(vec!["a".to_string()], vec![]), ..settings(dump) });
+        assert!(!file.contains("LOCK TABLES `a`") && !file.contains("`b`"));
+        assert!(file.contains("INSERT INTO `a` VALUES (1);"));
+        let file = single(SplitterSettings { filter: TableFilter::new(vec!["a".to_string(), "b".to_string()], vec![]), ..settings(dump) });
+        assert!(file.contains("LOCK TABLES `a` WRITE, `b` WRITE;"));
+    }
+
+    #[test]
+    fn renames(){
+        let dump = dump();
+        let renames = Renames::parse(&["users=customers"], &[], Some("old_")).unwrap();
+        let file = single(SplitterSettings { renames, ..settings(&dump) });
+        assert!(file.contains("CREATE TABLE `customers` ("));
+        assert!(file.contains("INSERT INTO `customers` VALUES (1,'ann@example.com'"));
+        assert!(file.contains("LOCK TABLES `old_orders` WRITE;"));
+        assert!(file.contains("REFERENCES `customers` (`id`)"));
+        assert!(!file.contains("TABLE `users`") && !file.contains("INSERT INTO `users`"));
+
+        // tables are filtered by the names as dumped, the files are named as renamed
+        let renames = Renames::parse(&["users=customers"], &[], None).unwrap();
+        let files = split(SplitterSettings {
+            renames,
+            layout: Layout::Mydumper,
+            filter: TableFilter::new(vec!["users".to_string()], vec![]),
+            ..settings(&dump)
+        });
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![&Target::Schema(table("customers")), &Target::Data(table("customers"))]);
+    }
+
+    #[test]
+    fn rewrites(){
+        let routines = fs::read_to_string("./example-files/routines.txt").unwrap();
+        let rewrites = Rewrites { definer: Definer::Strip, strip_use: true, strip_create_database: true, ..Rewrites::default() };
+        let file = single(SplitterSettings { rewrites, ..settings(&routines) });
+        assert!(!file.contains("DEFINER="));
+        assert!(!file.contains("USE `shop`;"));
+        assert!(!file.contains("CREATE DATABASE"));
+        assert!(file.contains("PROCEDURE `reset_logins`(IN user_id INT)"));
+
+        let rewrites = Rewrites { definer: Definer::parse("app@%").unwrap(), ..Rewrites::default() };
+        let file = single(SplitterSettings { rewrites, ..settings(&routines) });
+        assert!(file.contains("CREATE DEFINER=`app`@`%` FUNCTION `login_count`(uid INT)"));
+        assert!(!file.contains("`root`"));
+        assert!(file.contains("USE `shop`;"));
+
+        let dump = dump();
+        let rewrites = Rewrites { engine: Some("MyISAM".to_string()), strip_auto_increment: true, ..Rewrites::default() };
+        let file = single(SplitterSettings { rewrites, ..settings(&dump) });
+        assert!(file.contains(") ENGINE=MyISAM DEFAULT CHARSET=utf8mb4;"));
+        assert!(!file.contains("AUTO_INCREMENT="));
+    }
+
+    #[test]
+    fn postgres(){
+        let dump = dump();
+        let file = single(SplitterSettings { to: Dialect::Postgres, ..settings(&dump) });
+        assert!(file.contains("COPY \"audit_log\" FROM stdin;\n1\t1\tlogin\n2\t2\tlogin\n3\t1\tlogout\n\\.\n"));
+        assert!(file.contains("2\t1\t5.00\t\\N\t2024-01-15\n"));
+        assert!(!file.contains("LOCK TABLES"));
+        // foreign keys are added once all the rows are in
+        let foreign_key = file.find("FOREIGN KEY (\"user_id\") REFERENCES \"users\"").unwrap();
+        assert!(foreign_key > file.rfind("\\.\n").unwrap());
+    }
+
+    #[test]
+    fn sqlite(){
+        let dump = dump();
+        let file = single(SplitterSettings { to: Dialect::Sqlite, ..settings(&dump) });
+        assert!(file.contains("BEGIN;\nINSERT INTO \"audit_log\" VALUES (1,1,'login'),(2,2,'login'),(3,1,'logout');"));
+        // the data of the file before is committed by the next statement
+        assert_eq!(file.matches("BEGIN;").count(), file.matches("COMMIT;").count());
+        assert!(!file.contains("LOCK TABLES"));
+
+        let files = split(SplitterSettings { to: Dialect::Sqlite, layout: Layout::Mydumper, ..settings(&dump) });
+        for (target, file) in files {
+            if let Target::Data(_) = target {
+                assert!(file.contains("BEGIN;\nINSERT INTO"));
+                assert!(file.trim_end().ends_with("COMMIT;"));
+            }
+        }
+    }
+
+    #[test]
+    fn formats(){
+        let dump = dump();
+        let delimited = Delimited::new(Format::Csv, true, None, false);
+        let files = split(SplitterSettings { format: Format::Csv, delimited, ..settings(&dump) });
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![&Target::Data(table("users")), &Target::Data(table("audit_log")), &Target::Data(table("orders"))]);
+        assert_eq!(files[1].1, "id,user_id,action\n1,1,login\n2,2,login\n3,1,logout\n");
+        assert!(files[2].1.contains("1,1,19.99,\"first\norder\",2023-12-30\n2,1,5.00,\\N,2024-01-15\n"));
+
+        // each file of rows is loaded by a statement of its own
+        let delimited = Delimited::new(Format::Tsv, false, None, true);
+        let files = split(SplitterSettings { format: Format::Tsv, delimited, ..settings(&dump) });
+        let audit_log = files.iter().find(|(target, _)| *target == Target::Data(table("audit_log"))).unwrap();
+        assert_eq!(audit_log.1, "1\t1\tlogin\n2\t2\tlogin\n3\t1\tlogout\n");
+        let load_data = files.iter().find(|(target, _)| *target == Target::LoadData).unwrap();
+        assert_eq!(load_data.1.matches("LOAD DATA LOCAL INFILE").count(), 3);
+        assert!(load_data.1.contains("'audit_log.tsv' INTO TABLE `audit_log`\n"));
+
+        let files = split(SplitterSettings { format: Format::Jsonl, ..settings(&dump) });
+        assert_eq!(files.len(), 3);
+        assert!(files[0].1.starts_with("{\"id\":1,\"email\":\"ann@example.com\",\"name\":\"Ann\",\"created_at\":\"2024-01-02 10:00:00\"}\n"));
+        assert!(files[2].1.contains("{\"id\":2,\"user_id\":1,\"total\":\"5.00\",\"note\":null,\"created_at\":\"2024-01-15\"}\n"));
+    }
+
+    #[test]
+    fn parquet(){
+        let dump = dump();
+        let mut splitter = Splitter::new(SplitterSettings { format: Format::Parquet, ..settings(&dump) });
+        let mut files: Vec<(Target, Vec<u8>)> = vec![];
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(target, FileState::New, tokens) => files.push((target, tokens)),
+                SplitterState::Chunk(target, FileState::Continue, tokens) => {
+                    let file = files.iter_mut().rev().find(|(written, _)| *written == target).unwrap();
+                    file.1.extend(tokens);
+                },
+                SplitterState::Done => break,
+                SplitterState::SyntaxErr(e) => panic!("{}", e.text),
+            }
+        }
+
+        // one file for each table, each with the magic number at both ends
+        let targets: Vec<&Target> = files.iter().map(|(target, _)| target).collect();
+        assert_eq!(targets, vec![&Target::Data(table("users")), &Target::Data(table("audit_log")), &Target::Data(table("orders"))]);
+        for (_, file) in &files {
+            assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+        }
+    }
+
+    #[test]
+    fn pg_dump(){
+        let dump = "SET client_encoding = 'UTF8';\n\n\
+            CREATE TABLE public.users (\n    id integer NOT NULL,\n    name text\n);\n\n\
+            COPY public.users (id, name) FROM stdin;\n1\tAnn\n2\tBob\n3\t\\N\n\\.\n\n\
+            COPY public.orders (id) FROM stdin;\n1\n\\.\n";
+        let file = single(SplitterSettings { dialect: Dialect::Postgres, ..settings(dump) });
+        assert_eq!(file, dump);
+
+        // a file that fills up in the middle of the rows ends the copy,
+        // the next one starts it over
+        let files = split(SplitterSettings { dialect: Dialect::Postgres, write: 50, ..settings(dump) });
+        let copy = files.iter().position(|(_, file)| file.contains("1\tAnn\n")).unwrap();
+        assert!(files[copy].1.ends_with("1\tAnn\n2\tBob\n\\.\n"));
+        assert!(files[copy + 1].1.starts_with("COPY public.users (id, name) FROM stdin;\n3\t\\N\n\\.\n"));
+
+        let filter = TableFilter::new(vec![], vec!["orders".to_string()]);
+        let file = single(SplitterSettings { dialect: Dialect::Postgres, filter, ..settings(dump) });
+        assert!(file.contains("3\t\\N\n\\.\n"));
+        assert!(!file.contains("public.orders"));
+    }
+
+    #[test]
+    fn sqlite_dump(){
+        let dump = "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n\
+            CREATE TABLE users (id integer, name text);\n\
+            INSERT INTO users VALUES(1,'Ann');\nINSERT INTO users VALUES(2,'Bob');\n\
+            INSERT INTO users VALUES(3,'Cid');\nCOMMIT;\n";
+        let file = single(SplitterSettings { dialect: Dialect::Sqlite, ..settings(dump) });
+        assert_eq!(file, dump);
+
+        // every file loads on its own, in a transaction of its own
+        let files = split(SplitterSettings { dialect: Dialect::Sqlite, write: 60, ..settings(dump) });
+        assert!(files.len() > 2);
+        for (_, file) in &files[1..] {
+            assert!(file.starts_with("PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n"));
+            assert!(file.trim_end().ends_with("COMMIT;"));
+        }
+    }
+
+    #[test]
+    fn tally(){
+        let dump = "CREATE TABLE `users` (`id` int);\n\
+            INSERT INTO `users` VALUES (1),(2),\n(3);\nDROP VIEW `v`;\n";
+        let mut splitter = Splitter::new(SplitterSettings { write: 40, ..settings(dump) });
+        let (mut rows, mut statements, mut end) = (0, 0, 0);
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(..) => {
+                    let tally = splitter.tally();
+                    rows += tally.rows;
+                    statements += tally.statements;
+                    end = end.max(tally.source.1);
+                    if tally.rows > 0 {
+                        assert_eq!(tally.table, Some(table("users")));
+                    }
+                },
+                SplitterState::SyntaxErr(e) => panic!("{:?}", e),
+                SplitterState::Done => break,
+            }
+        }
+
+        assert_eq!(rows, 3);
+        // the insert continues into the next file, and counts again there
+        assert_eq!(statements, 4);
+        assert_eq!(end, dump.len() as u64);
+    }
+
+    #[test]
+    fn resume(){
+        let dump = dump();
+        let mut splitter = Splitter::new(SplitterSettings { write: 2000, ..settings(&dump) });
+        let mut chunks = vec![];
+        let mut checkpoints = vec![];
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(_, file_state, tokens) => {
+                    chunks.push((file_state, tokens));
+                    if let Some(checkpoint) = splitter.checkpoint() {
+                        checkpoints.push((chunks.len(), checkpoint));
+                    }
+                },
+                SplitterState::SyntaxErr(e) => panic!("{:?}", e),
+                SplitterState::Done => break,
+            }
+        }
+
+        let (written, checkpoint) = &checkpoints[checkpoints.len() / 2];
+        let mut file = Cursor::new(dump.clone().into_bytes());
+        file.set_position(checkpoint.offset);
+        let mut splitter = Splitter::new(SplitterSettings { write: 2000, file, ..settings("") });
+        splitter.resume(checkpoint);
+        let mut resumed = vec![];
+        loop {
+            match splitter.process() {
+                SplitterState::Chunk(_, file_state, tokens) => resumed.push((file_state, tokens)),
+                SplitterState::SyntaxErr(e) => panic!("{:?}", e),
+                SplitterState::Done => break,
+            }
+        }
+        assert_eq!(resumed, chunks[*written..].to_vec());
+    }
+}
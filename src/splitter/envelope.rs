@@ -0,0 +1,170 @@
+use parser::Kind;
+use super::{Dialect, Layout, Target};
+
+/// What every file starts and ends with so it loads on its own: the session
+/// statements of the mydumper layout, the delimiter, and the transactions of
+/// a sqlite dump or of the data written for sqlite.
+pub struct Envelope {
+    layout: Layout,
+    to: Dialect,
+    dialect: Dialect,
+    // session statements written at the start of every file of the
+    // mydumper layout, or the pragmas of a sqlite dump
+    preamble: Vec<u8>,
+    // `BEGIN TRANSACTION` of a sqlite dump and the pragmas before it, every
+    // file starts with them and ends with a `COMMIT` until the dump commits
+    begin: Option<Vec<u8>>,
+    // file with an open sqlite transaction, and one to commit before reading on
+    transaction: Option<Target>,
+    commit: Option<Target>,
+}
+
+impl Envelope {
+    pub fn new(layout: Layout, to: Dialect, dialect: Dialect) -> Self {
+        Envelope { layout, to, dialect, preamble: vec![], begin: None, transaction: None, commit: None }
+    }
+
+    pub fn preamble(&self) -> &[u8] {
+        &self.preamble
+    }
+
+    pub fn add_preamble(&mut self, tokens: &[u8]) {
+        self.preamble.extend(tokens);
+        self.preamble.push(b'\n');
+    }
+
+    pub fn begin(&self) -> Option<&Vec<u8>> {
+        self.begin.as_ref()
+    }
+
+    // a split resumed inside the transaction of the dump
+    pub fn resume(&mut self, preamble: Vec<u8>, begin: Option<Vec<u8>>) {
+        self.preamble = preamble;
+        self.begin = begin;
+    }
+
+    pub fn holding(&self) -> bool {
+        self.commit.is_some() || self.transaction.is_some()
+    }
+
+    /// What a new file starts with before the statement in it.
+    pub fn open(&self, target: &Target, delimiter: &[u8], mut tokens: Vec<u8>) -> Vec<u8> {
+        // new file has to switch to the delimiter of the statements in it
+        if *target == Target::Part && delimiter != b";" && self.to == Dialect::Mysql {
+            let mut chunk = b"DELIMITER ".to_vec();
+            chunk.extend(delimiter);
+            chunk.push(b'\n');
+            chunk.extend(tokens);
+            tokens = chunk;
+        }
+
+        if let (Target::Part, Some(begin)) = (target, &self.begin) {
+            let mut chunk = begin.clone();
+            chunk.push(b'\n');
+            chunk.extend(tokens);
+            tokens = chunk;
+        }
+
+        if self.layout == Layout::Mydumper {
+            let mut chunk = self.preamble.clone();
+            chunk.extend(tokens);
+            tokens = chunk;
+        }
+        tokens
+    }
+
+    /// Ends the transactions of a file that filled up.
+    pub fn close(&mut self, target: &Target, tokens: &mut Vec<u8>) {
+        if self.transaction.as_ref() == Some(target) {
+            self.transaction = None;
+            tokens.extend(b"\nCOMMIT;\n");
+        }
+        if *target == Target::Part && self.begin.is_some() {
+            tokens.extend(b"\nCOMMIT;\n");
+        }
+    }
+
+    // the data of a file goes in one transaction, the one of the
+    // file before is committed when the mydumper layout moves on
+    pub fn begin_data(&mut self, target: &Target, chunk: &mut Vec<u8>) {
+        if self.transaction.as_ref() == Some(target) {
+            return
+        }
+
+        if let Some(open) = self.transaction.take() {
+            self.commit = Some(open);
+        }
+        chunk.extend(b"BEGIN;\n");
+        self.transaction = Some(target.clone());
+    }
+
+    // file whose transaction is committed before reading on
+    pub fn take_commit(&mut self) -> Option<Target> {
+        self.commit.take()
+    }
+
+    /// Commits the data written at the end of the dump, false when there is none.
+    pub fn commit_at_end(&mut self) -> bool {
+        match self.transaction.take() {
+            Some(open) => {
+                self.commit = Some(open);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// A statement after the data of the numbered files
+    /// commits it first.
+    pub fn statement(&mut self, tokens: Vec<u8>) -> Vec<u8> {
+        if self.layout == Layout::Chunks && self.transaction.take().is_some() {
+            let mut chunk = b"COMMIT;\n".to_vec();
+            chunk.extend(tokens);
+            return chunk
+        }
+        tokens
+    }
+
+    // the transaction of a sqlite dump, the numbered files each get it
+    fn dump_transaction(&self, kind: Kind) -> bool {
+        kind == Kind::Transaction && self.dialect == Dialect::Sqlite && self.layout == Layout::Chunks
+    }
+
+    /// `BEGIN TRANSACTION` of a sqlite dump, and the pragmas before it,
+    /// to start the files after it with.
+    pub fn opens(&self, kind: Kind, tokens: &[u8]) -> Option<Vec<u8>> {
+        if !self.dump_transaction(kind) || ends(tokens) {
+            return None
+        }
+
+        let mut begin = self.preamble.clone();
+        begin.extend(tokens);
+        Some(begin)
+    }
+
+    // pragmas before it, like `PRAGMA foreign_keys=OFF`, are for the session
+    pub fn pragma(&mut self, kind: Kind, tokens: &[u8], started: bool) {
+        if self.dialect == Dialect::Sqlite && self.layout == Layout::Chunks && self.begin.is_none() &&
+            !started && kind == Kind::Other && tokens.len() > 6 && tokens[..6].eq_ignore_ascii_case(b"pragma") {
+            self.add_preamble(tokens);
+        }
+    }
+
+    /// The `COMMIT` of a sqlite dump, the files after it aren't in its transaction.
+    pub fn ends(&mut self, kind: Kind, tokens: &[u8]) -> bool {
+        if self.dump_transaction(kind) && ends(tokens) {
+            self.begin = None;
+            return true
+        }
+        false
+    }
+
+    pub fn opened(&mut self, begin: Vec<u8>) {
+        self.begin = Some(begin);
+    }
+}
+
+// `COMMIT`, `END` or `ROLLBACK` end it, `BEGIN` or `START` open it
+fn ends(tokens: &[u8]) -> bool {
+    tokens.first().is_some_and(|byte| matches!(byte.to_ascii_lowercase(), b'c' | b'e' | b'r'))
+}
@@ -0,0 +1,281 @@
+use parser::Table;
+use parser::Kind;
+use parser::Object;
+use std::collections::HashSet;
+use std::collections::HashMap;
+use statement::Statement;
+use super::{FileState, Format, Layout, Target};
+
+// `SET character_set_client = @saved_cs_client` puts back
+// what was set for the statement before it
+fn restores(tokens: &[u8]) -> bool {
+    match Statement::parse(tokens) {
+        Statement::Set { variables } => !variables.is_empty() && variables.iter().all(|variable| {
+            !variable.name.starts_with('@') && variable.value.starts_with(b"@") && !variable.value.starts_with(b"@@")
+        }),
+        _ => false,
+    }
+}
+
+/// Statements routed to their files, the first one with where in
+/// the dump it came from when that isn't the last statement read.
+#[derive(Debug,Default)]
+pub struct Routed {
+    pub chunks: Vec<(Target, Vec<u8>)>,
+    pub source: Option<(u64, u64)>,
+}
+
+/// The files statements and rows go to: a numbered file, or one of the
+/// mydumper layout picked by what the statement creates.
+pub struct Router {
+    layout: Layout,
+    format: Format,
+    // target of the last statement
+    target: Option<Target>,
+    // table of the data file being written, and
+    // whether the inserts just moved on from another
+    data_table: Option<Table>,
+    moved_on: bool,
+    opened: HashSet<Target>,
+    // a `DROP TABLE` mysqldump might have written before the
+    // placeholder of a view, with where it was in the dump
+    placeholder: Option<(Table, Vec<u8>, (u64, u64))>,
+    // session statements after the first object, waiting for the one they are for
+    session: Vec<u8>,
+    // files of rows written for each table
+    data_files: HashMap<Table, usize>,
+}
+
+impl Router {
+    pub fn new(layout: Layout, format: Format) -> Self {
+        Router {
+            layout,
+            format,
+            target: None,
+            data_table: None,
+            moved_on: false,
+            opened: HashSet::new(),
+            placeholder: None,
+            session: vec![],
+            data_files: HashMap::new(),
+        }
+    }
+
+    /// Target of the last statement, None before the first one.
+    pub fn target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    pub fn sent(&mut self, target: &Target) {
+        self.target = Some(target.clone());
+    }
+
+    // a split resumed in the middle of the numbered files
+    pub fn resume(&mut self) {
+        self.target = Some(Target::Part);
+    }
+
+    // rows go to files of their table
+    pub fn per_table(&self) -> bool {
+        self.layout == Layout::Mydumper || self.format != Format::Sql
+    }
+
+    // a file other than a numbered or data one is new the first time it is written
+    pub fn file_state(&mut self, target: &Target) -> FileState {
+        if self.opened.insert(target.clone()) {
+            FileState::New
+        }else{
+            FileState::Continue
+        }
+    }
+
+    // number of the next file of rows of the table
+    pub fn data_file(&mut self, table: &Table) -> usize {
+        let part = self.data_files.entry(table.clone()).or_insert(0);
+        *part += 1;
+        *part
+    }
+
+    // switches the data file when the inserts move on to another table
+    pub fn data_target(&mut self, table: Option<Table>) -> Option<Target> {
+        if !self.per_table() {
+            return Some(Target::Part)
+        }
+
+        let table = match table {
+            Some(table) => table,
+            None => return self.target.clone(),
+        };
+
+        if self.data_table.as_ref() != Some(&table) {
+            self.data_table = Some(table.clone());
+            self.moved_on = true;
+        }
+        Some(Target::Data(table))
+    }
+
+    /// The inserts moved on to the data file of another table
+    /// since it was asked last, it starts out empty.
+    pub fn moved_on(&mut self) -> bool {
+        ::std::mem::take(&mut self.moved_on)
+    }
+
+    // where a statement goes in the mydumper layout, None drops it
+    fn layout_target(&mut self, kind: Kind, table: Option<Table>) -> Option<Target> {
+        match kind {
+            Kind::Create(Object::Table) |
+            Kind::Drop(Object::Table) => table.map(Target::Schema),
+            Kind::Alter(Object::Table) |
+            Kind::Alter(Object::Index) |
+            Kind::Create(Object::Index) |
+            Kind::Drop(Object::Index) => table.map(Target::SchemaPost),
+            Kind::Create(Object::View) |
+            Kind::Alter(Object::View) |
+            Kind::Drop(Object::View) => Some(Target::Views),
+            Kind::Create(Object::Trigger) |
+            Kind::Alter(Object::Trigger) |
+            Kind::Drop(Object::Trigger) => Some(Target::Triggers),
+            Kind::Create(Object::Procedure) |
+            Kind::Create(Object::Function) |
+            Kind::Create(Object::Event) |
+            Kind::Alter(Object::Procedure) |
+            Kind::Alter(Object::Function) |
+            Kind::Alter(Object::Event) |
+            Kind::Drop(Object::Procedure) |
+            Kind::Drop(Object::Function) |
+            Kind::Drop(Object::Event) => Some(Target::Routines),
+            Kind::Insert => self.data_target(table),
+            // statements that don't name anything stay
+            // with the statement before them
+            Kind::Create(Object::Other) |
+            Kind::Alter(Object::Other) |
+            Kind::Drop(Object::Other) |
+            Kind::Other => self.target.clone(),
+            // files are loaded into a database picked by the importer,
+            // the delimiter is set around each routine and locks
+            // don't help parallel loading.
+            Kind::Create(Object::Database) |
+            Kind::Alter(Object::Database) |
+            Kind::Drop(Object::Database) |
+            Kind::Use |
+            Kind::Delimiter |
+            Kind::Lock |
+            Kind::Unlock |
+            Kind::Transaction |
+            Kind::Keys |
+            Kind::Set => None,
+        }
+    }
+
+    /// Session statements before the first object, repeated in every file.
+    pub fn preamble(&self, kind: Kind) -> bool {
+        kind == Kind::Set && self.target.is_none() && self.placeholder.is_none()
+    }
+
+    /// Routes a statement of the mydumper layout, statements the ones
+    /// after it tell where they go wait for them.
+    pub fn route(&mut self, kind: Kind, mut tokens: Vec<u8>, table: Option<Table>, delimiter: &[u8], source: (u64, u64)) -> Routed {
+        let mut routed = Routed::default();
+        if kind == Kind::Set {
+            // the ones after the first object wait for the statement after them
+            let previous = self.target.clone().filter(Target::session);
+            if let (Some(previous), None, true) = (previous, &self.placeholder, self.session.is_empty() && restores(&tokens)) {
+                routed.chunks.push((previous, tokens));
+            }else{
+                self.session.extend(tokens);
+                self.session.push(b'\n');
+            }
+            return routed
+        }
+
+        let target = match self.layout_target(kind, table) {
+            Some(target) => target,
+            None => return routed,
+        };
+        if delimiter != b";" {
+            if let Target::Routines | Target::Triggers = target {
+                let mut chunk = b"DELIMITER ".to_vec();
+                chunk.extend(delimiter);
+                chunk.push(b'\n');
+                chunk.extend(tokens);
+                chunk.extend(b"\nDELIMITER ;");
+                tokens = chunk;
+            }
+        }
+
+        let mut previous = self.target.clone();
+        if let Some((held, mut placeholder, held_source)) = self.placeholder.take() {
+            let view = match Statement::parse(&tokens) {
+                Statement::Drop { object: Object::View, names, .. } => names.into_iter().next(),
+                Statement::CreateView { name, .. } => Some(name),
+                _ => None,
+            };
+            if view.is_some_and(|view| view.name == held.name) {
+                routed.source = Some((held_source.0, source.1));
+                placeholder.extend(tokens);
+                tokens = placeholder;
+            }else{
+                routed.source = Some(held_source);
+                previous = Some(Target::Schema(held.clone()));
+                routed.chunks.push((Target::Schema(held), placeholder));
+            }
+        }
+
+        // the `DROP TABLE` waits for the statement after it
+        let drop = match (kind, &target) {
+            (Kind::Drop(Object::Table), Target::Schema(table)) => Some(table.clone()),
+            _ => None,
+        };
+
+        // the views, routines and triggers are created with the character
+        // set and sql mode mysqldump sets around them
+        let session = ::std::mem::take(&mut self.session);
+        if !session.is_empty() {
+            if drop.is_none() && target.session() {
+                let mut chunk = session;
+                chunk.extend(tokens);
+                tokens = chunk;
+            }else if let Some(previous) = previous.filter(Target::session) {
+                routed.chunks.push((previous, session));
+            }
+        }
+
+        match drop {
+            Some(table) => self.placeholder = Some((table, tokens, source)),
+            None => routed.chunks.push((target, tokens)),
+        }
+        routed
+    }
+
+    /// White space that goes with the statements waiting, false
+    /// when it stays with the statement before it.
+    pub fn holds_space(&mut self, tokens: &[u8]) -> bool {
+        if let Some((_, ref mut placeholder, _)) = self.placeholder {
+            placeholder.extend(tokens);
+            return true
+        }
+        // the session statements held have their line feeds
+        !self.session.is_empty()
+    }
+
+    pub fn holding(&self) -> bool {
+        self.placeholder.is_some() || !self.session.is_empty()
+    }
+
+    /// The dump ended with statements waiting for the one after them.
+    pub fn settle(&mut self) -> Vec<(Target, Vec<u8>)> {
+        let mut chunks = vec![];
+        let mut previous = self.target.clone();
+        if let Some((table, placeholder, _)) = self.placeholder.take() {
+            previous = Some(Target::Schema(table.clone()));
+            chunks.push((Target::Schema(table), placeholder));
+        }
+
+        let session = ::std::mem::take(&mut self.session);
+        match previous {
+            Some(previous) if previous.session() && !session.is_empty() => chunks.push((previous, session)),
+            _ => {},
+        }
+        chunks
+    }
+}
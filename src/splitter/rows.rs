@@ -0,0 +1,403 @@
+use parser::Table;
+use parser::TokenStream;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use mask::Masks;
+use mask::Rule;
+use mask::mask;
+use mask::largest;
+use statement::Statement;
+use statement::Column;
+use statement::Key;
+use statement::KeyKind;
+use value::rewrite;
+use value::Fields;
+use predicate::RowFilter;
+use predicate::Expr;
+use subset::Selection;
+use subset::RowCounter;
+use subset::sampled;
+use subset::row_bytes;
+use sample::Sampling;
+use sample::Amount;
+use sample::Reservoir;
+use postgres;
+use sqlite;
+use delimited::Delimited;
+use json;
+use json::FieldType;
+use dataset;
+use tokenizer::SyntaxErr;
+use std::mem;
+use super::{Dialect, Format, SplitterSettings, Target};
+
+// rows of a `--sample-rows` table, written once its inserts are over
+struct SampledRows {
+    table: Table,
+    target: Target,
+    head: Vec<u8>,
+    reservoir: Reservoir<Vec<u8>>,
+}
+
+/// What happens to the rows of an insert on their way out: the `--where`
+/// condition, subset and sample that keep them, the masks and the dialect
+/// or format they are written in.
+pub struct Rows {
+    masks: Masks,
+    row_filter: RowFilter,
+    selection: Selection,
+    row_counter: RowCounter,
+    sampling: Sampling,
+    to: Dialect,
+    format: Format,
+    delimited: Delimited,
+    // `--strict`, values have to fit the types of their columns
+    strict: bool,
+    // columns and keys of the tables created so far
+    definitions: HashMap<Table, (Vec<Column>, Vec<Key>)>,
+    // masks of the insert being written, by field position, with the
+    // largest number of the column when it is one
+    row_masks: Vec<(usize, Rule, Option<i64>)>,
+    // `--where` condition of the insert being written
+    condition: Option<Expr>,
+    // table of the insert being written, when the
+    // subset picks its rows one by one
+    selected_table: Option<Table>,
+    // table and `--sample` fraction of the insert being written
+    sample: Option<(Table, f64)>,
+    // reservoir the rows of the insert being written go to with `--sample-rows`
+    sample_rows: Option<(Reservoir<Vec<u8>>, Table)>,
+    reservoir: Option<SampledRows>,
+    // sampled rows being written, with their file and insert statement
+    sampled_rows: Option<(Target, Vec<u8>, VecDeque<Vec<u8>>)>,
+    // fields of the insert being written that are `bytea` in postgres, blobs in sqlite
+    binary_fields: Vec<bool>,
+    // columns of the insert being written
+    columns: Vec<String>,
+    // columns of the insert being written as `--format=jsonl` has them
+    json_columns: Vec<(String, FieldType)>,
+    // columns of the insert being written as `--format=parquet` has them
+    parquet_fields: Vec<dataset::Field>,
+}
+
+impl Rows {
+    pub fn new<T>(settings: &mut SplitterSettings<T>) -> Self {
+        Rows {
+            masks: mem::take(&mut settings.masks),
+            row_filter: mem::take(&mut settings.row_filter),
+            selection: mem::take(&mut settings.selection),
+            row_counter: RowCounter::default(),
+            sampling: mem::take(&mut settings.sampling),
+            to: settings.to,
+            format: settings.format,
+            delimited: mem::take(&mut settings.delimited),
+            strict: settings.strict,
+            definitions: HashMap::new(),
+            row_masks: vec![],
+            condition: None,
+            selected_table: None,
+            sample: None,
+            sample_rows: None,
+            reservoir: None,
+            sampled_rows: None,
+            binary_fields: vec![],
+            columns: vec![],
+            json_columns: vec![],
+            parquet_fields: vec![],
+        }
+    }
+
+    pub fn needs_columns(&self) -> bool {
+        !self.masks.is_empty() || !self.row_filter.is_empty() || self.to != Dialect::Mysql || self.format != Format::Sql
+    }
+
+    pub fn define(&mut self, tokens: &[u8]) {
+        if let Statement::CreateTable { name, columns, keys, .. } = Statement::parse(tokens) {
+            self.definitions.insert(name, (columns, keys));
+        }
+    }
+
+    fn defined(&self, table: &Table) -> Option<&(Vec<Column>, Vec<Key>)> {
+        // the create statement might not name the schema the insert does
+        self.definitions.get(table).or_else(|| {
+            self.definitions.iter()
+                .find(|(defined, _)| defined.name == table.name)
+                .map(|(_, definition)| definition)
+        })
+    }
+
+    fn definition(&self, table: &Table) -> Option<&Vec<Column>> {
+        self.defined(table).map(|(columns, _)| columns)
+    }
+
+    // masks of the fields of an insert, hashed numbers are cut to the range of
+    // their column, which would make those of a key narrower than `bigint` collide
+    fn row_masks(&self, table: &Table, columns: &[String]) -> Result<Vec<(usize, Rule, Option<i64>)>, SyntaxErr> {
+        let defined = self.defined(table);
+        let mut row_masks = vec![];
+        for (position, rule) in self.masks.positions(table, columns) {
+            let name = &columns[position];
+            let largest = defined
+                .and_then(|(definition, _)| definition.iter().find(|column| column.name.eq_ignore_ascii_case(name)))
+                .and_then(|column| largest(&column.data_type));
+            let key = defined.is_some_and(|(_, keys)| keys.iter().any(|key| {
+                matches!(key.kind, KeyKind::Primary | KeyKind::Unique | KeyKind::Foreign) &&
+                    key.columns.iter().any(|column| column.eq_ignore_ascii_case(name))
+            }));
+            if let (Rule::Hash(_), true, Some(largest)) = (&rule, key, largest) {
+                if largest < i64::MAX {
+                    return Err(SyntaxErr{
+                        text: "--mask-config hashes a numeric key column narrower than bigint, its values would collide."
+                    })
+                }
+            }
+            row_masks.push((position, rule, largest));
+        }
+        Ok(row_masks)
+    }
+
+    // columns of an insert, from its column list or the `CREATE TABLE` before it
+    fn insert_columns(&self, insert_stmt: &[u8], table: &Table) -> Vec<String> {
+        if let Statement::Insert { columns, .. } = Statement::parse(insert_stmt) {
+            if !columns.is_empty() {
+                return columns
+            }
+        }
+
+        match self.definition(table) {
+            Some(columns) => columns.iter().map(|column| column.name.clone()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Sets up the rows of an insert of the table as dumped, and
+    /// returns the columns they have.
+    pub fn bind(&mut self, insert_stmt: &[u8], table: &Option<Table>) -> Result<Vec<String>, SyntaxErr> {
+        let columns = match *table {
+            Some(ref table) if self.needs_columns() => self.insert_columns(insert_stmt, table),
+            _ => vec![],
+        };
+
+        self.row_masks = match *table {
+            Some(ref table) => self.row_masks(table, &columns)?,
+            None => vec![],
+        };
+
+        self.condition = match *table {
+            Some(ref table) => match self.row_filter.bind(table, &columns) {
+                Ok(condition) => condition,
+                Err(_) => return Err(SyntaxErr{
+                    text: "--where names a column the table doesn't have."
+                }),
+            },
+            None => None,
+        };
+
+        self.selected_table = table.clone().filter(|table| self.selection.constrains(table));
+
+        let amount = table.as_ref().and_then(|table| self.sampling.amount(table));
+        self.sample = match (amount, table) {
+            (Some(Amount::Fraction(fraction)), Some(table)) => Some((table.clone(), fraction)),
+            _ => None,
+        };
+
+        self.sample_rows = match (amount, table) {
+            (Some(Amount::Rows(size)), Some(table)) if self.reservoir.is_none() => {
+                Some((Reservoir::new(size, self.sampling.seed, table), table.clone()))
+            },
+            _ => None,
+        };
+
+        if self.to != Dialect::Mysql {
+            self.binary_fields = match table.as_ref().and_then(|table| self.definition(table)) {
+                Some(definition) => columns.iter().map(|name| {
+                    definition.iter().any(|column| column.name == *name && postgres::binary(&column.data_type))
+                }).collect(),
+                None => vec![],
+            };
+        }
+        if self.format == Format::Jsonl {
+            let definition = table.as_ref().and_then(|table| self.definition(table));
+            self.json_columns = columns.iter().map(|name| {
+                let field_type = definition
+                    .and_then(|definition| definition.iter().find(|column| column.name == *name))
+                    .map_or(FieldType::Unknown, |column| json::field_type(&column.data_type));
+                (name.clone(), field_type)
+            }).collect();
+        }
+
+        if self.format == Format::Parquet {
+            self.parquet_fields = match table.as_ref().and_then(|table| self.definition(table)) {
+                Some(definition) => columns.iter().map(|name| {
+                    match definition.iter().find(|column| column.name == *name) {
+                        Some(column) => dataset::Field::new(column, self.strict),
+                        None => dataset::Field::text(name),
+                    }
+                }).collect(),
+                None => columns.iter().map(|name| dataset::Field::text(name)).collect(),
+            };
+        }
+        Ok(columns)
+    }
+
+    /// The insert statement the rows are written after, or the header
+    /// of a file of rows.
+    pub fn head(&mut self, insert_stmt: Vec<u8>, columns: Vec<String>) -> Result<Vec<u8>, SyntaxErr> {
+        match (self.format, self.to) {
+            (Format::Csv, _) | (Format::Tsv, _) => {
+                let header = self.delimited.header(&columns);
+                self.columns = columns;
+                Ok(header)
+            },
+            (Format::Jsonl, _) | (Format::Parquet, _) => Ok(vec![]),
+            (Format::Sql, Dialect::Mysql) => Ok(insert_stmt),
+            (Format::Sql, Dialect::Postgres) => Ok(postgres::copy_header(&insert_stmt)),
+            (Format::Sql, Dialect::Sqlite) => sqlite::insert_head(&insert_stmt),
+        }
+    }
+
+    // `--sample-rows` keeps the rows of the insert being written
+    pub fn sample_into(&mut self, target: &Target, head: &[u8]) {
+        if let Some((reservoir, table)) = self.sample_rows.take() {
+            self.reservoir = Some(SampledRows {
+                table,
+                target: target.clone(),
+                head: head.to_vec(),
+                reservoir,
+            });
+        }
+    }
+
+    /// The rows go out one by one instead of as the dump has them.
+    pub fn filters(&self) -> bool {
+        self.condition.is_some() || self.selected_table.is_some() ||
+            self.sample.is_some() || self.reservoir.is_some() || self.to != Dialect::Mysql || self.format != Format::Sql
+    }
+
+    pub fn keeps(&mut self, row: &[u8]) -> bool {
+        let selected = match self.selected_table {
+            Some(ref table) => {
+                let position = self.row_counter.next(table);
+                self.selection.keeps(table, position)
+            },
+            None => true,
+        };
+
+        let sampled = match self.sample {
+            Some((ref table, fraction)) => sampled(self.sampling.seed, table, row_bytes(row), fraction),
+            None => true,
+        };
+
+        selected && sampled && match self.condition {
+            Some(ref condition) => condition.matches(&Fields::new(row).collect::<Vec<_>>()),
+            None => true,
+        }
+    }
+
+    pub fn mask(&self, tuple: Vec<u8>) -> Vec<u8> {
+        if self.row_masks.is_empty() {
+            return tuple
+        }
+
+        rewrite(&tuple, |position, raw| {
+            self.row_masks.iter()
+                .find(|(index, _, _)| *index == position)
+                .map(|(_, rule, largest)| mask(rule, raw, *largest))
+        })
+    }
+
+    /// A kept row masked and written the way the output has it,
+    /// None when `--sample-rows` holds it back.
+    pub fn convert(&mut self, row: &[u8]) -> Option<Vec<u8>> {
+        let row = self.mask(row.to_vec());
+        let row = match (self.format, self.to) {
+            (Format::Csv, _) | (Format::Tsv, _) => self.delimited.row(&row),
+            (Format::Jsonl, _) => json::row(&row, &self.json_columns),
+            (Format::Parquet, _) => row,
+            (Format::Sql, Dialect::Mysql) => row,
+            (Format::Sql, Dialect::Postgres) => postgres::copy_row(&row, &self.binary_fields),
+            (Format::Sql, Dialect::Sqlite) => sqlite::row(&row, &self.binary_fields),
+        };
+
+        match self.reservoir {
+            Some(ref mut sample) => {
+                sample.reservoir.offer(row);
+                None
+            },
+            None => Some(row),
+        }
+    }
+
+    // what ends the rows of an insert
+    pub fn insert_end(&self) -> &'static [u8] {
+        if self.format != Format::Sql {
+            return b""
+        }
+
+        match self.to {
+            Dialect::Postgres => b"\\.\n",
+            Dialect::Mysql | Dialect::Sqlite => b";",
+        }
+    }
+
+    // columns of the Parquet file of the insert being written,
+    // empty for a table the dump doesn't define
+    pub fn parquet_fields(&self) -> &[dataset::Field] {
+        &self.parquet_fields
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// The `LOAD DATA` statement of a file of rows, with `--load-data`.
+    pub fn load_data(&self, file: &str, table: &Table) -> Option<Vec<u8>> {
+        if self.delimited.load_data {
+            Some(self.delimited.load_data(file, table, &self.columns))
+        }else{
+            None
+        }
+    }
+
+    // rows held back by `--sample-rows`
+    pub fn sampling(&self) -> bool {
+        self.reservoir.is_some()
+    }
+
+    pub fn holding(&self) -> bool {
+        self.reservoir.is_some() || self.sampled_rows.is_some()
+    }
+
+    // the `--sample-rows` rows are written when the inserts of their table end
+    pub fn ends_sample(&self, item: &TokenStream) -> bool {
+        match (&self.reservoir, item) {
+            (Some(sample), TokenStream::Insert(_, _, table)) => table.as_ref() != Some(&sample.table),
+            (Some(_), TokenStream::Block(..)) => true,
+            _ => false,
+        }
+    }
+
+    pub fn flush_sample(&mut self) {
+        if let Some(sample) = self.reservoir.take() {
+            let rows: VecDeque<Vec<u8>> = sample.reservoir.into_items().into();
+            if !rows.is_empty() {
+                self.sampled_rows = Some((sample.target, sample.head, rows));
+            }
+        }
+    }
+
+    /// The next sampled row to write, with its file, its insert statement
+    /// and whether it is the last one.
+    pub fn next_sampled(&mut self) -> Option<(Target, Vec<u8>, Vec<u8>, bool)> {
+        let (target, head, row, last) = {
+            let (target, head, rows) = self.sampled_rows.as_mut()?;
+            let row = rows.pop_front()?;
+            (target.clone(), head.clone(), row, rows.is_empty())
+        };
+
+        if last {
+            self.sampled_rows = None;
+        }
+        Some((target, head, row, last))
+    }
+}
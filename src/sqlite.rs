@@ -0,0 +1,328 @@
+use parser::Kind;
+use parser::Object;
+use statement::Statement;
+use statement::Column;
+use statement::DataType;
+use statement::KeyKind;
+use statement::Verb;
+use value::Value;
+use value::parse;
+use value::rewrite;
+use postgres::quote;
+use postgres::literal;
+use postgres::hex;
+use postgres::binary;
+use postgres::translate;
+use tokenizer::SyntaxErr;
+#[cfg(feature = "sqlite")]
+use rusqlite;
+
+// SQLite has no schemas to qualify names with, only attached databases
+fn names(names: &[String]) -> String {
+    names.iter().map(|name| quote(name)).collect::<Vec<_>>().join(", ")
+}
+
+fn blob(bytes: &[u8]) -> String {
+    format!("X'{}'", hex(bytes))
+}
+
+// declared types only pick the affinity of the column
+fn column_type(data_type: &DataType) -> &'static str {
+    match data_type.name.as_str() {
+        "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint" |
+        "bool" | "boolean" | "bit" | "year" => "INTEGER",
+        "float" | "double" | "real" => "REAL",
+        "decimal" | "numeric" | "dec" | "fixed" => "NUMERIC",
+        _ if binary(data_type) => "BLOB",
+        // text, dates, enum, set and json
+        _ => "TEXT",
+    }
+}
+
+fn default(column: &Column) -> Option<String> {
+    let raw = column.default.as_ref()?;
+    let default = match parse(raw) {
+        Value::Null => "NULL".to_string(),
+        Value::String(ref bytes) | Value::Hex(ref bytes) if binary(&column.data_type) => blob(bytes),
+        Value::String(ref text) | Value::Hex(ref text) => literal(text),
+        Value::Expression(expression) => {
+            let expression = String::from_utf8_lossy(expression).to_uppercase();
+            if expression.starts_with("CURRENT_TIMESTAMP") || expression.starts_with("NOW") {
+                "CURRENT_TIMESTAMP".to_string()
+            }else{
+                return None
+            }
+        },
+        value => String::from_utf8_lossy(&value.text()?).into_owned(),
+    };
+    Some(default)
+}
+
+/// `CREATE TABLE` with its foreign keys inline and its indexes after it.
+pub fn create_table(sql: &[u8]) -> Option<Vec<u8>> {
+    let (name, columns, keys) = match Statement::parse(sql) {
+        Statement::CreateTable { name, columns, keys, .. } => (name, columns, keys),
+        _ => return None,
+    };
+
+    // an auto increment column can only be the rowid
+    let rowid = keys.iter()
+        .find(|key| key.kind == KeyKind::Primary)
+        .filter(|key| key.columns.len() == 1)
+        .and_then(|key| columns.iter().find(|column| column.name == key.columns[0] && column.auto_increment))
+        .map(|column| column.name.clone());
+
+    let table = quote(&name.name);
+    let mut lines = vec![];
+    let mut after = String::new();
+    for column in &columns {
+        let mut line = format!("  {} {}", quote(&column.name), column_type(&column.data_type));
+        if rowid.as_ref() == Some(&column.name) {
+            line.push_str(" PRIMARY KEY AUTOINCREMENT");
+        }else if let Some(default) = default(column) {
+            line.push_str(&format!(" DEFAULT {}", default));
+        }
+
+        if !column.nullable {
+            line.push_str(" NOT NULL");
+        }
+
+        if column.data_type.name == "enum" {
+            let values: Vec<String> = column.data_type.args.iter()
+                .filter_map(|arg| parse(arg.as_bytes()).text().map(|text| literal(&text)))
+                .collect();
+            line.push_str(&format!(" CHECK ({} IN ({}))", quote(&column.name), values.join(", ")));
+        }
+        lines.push(line);
+    }
+
+    for key in &keys {
+        match key.kind {
+            KeyKind::Primary if rowid.is_some() => {},
+            KeyKind::Primary => lines.push(format!("  PRIMARY KEY ({})", names(&key.columns))),
+            KeyKind::Unique => lines.push(format!("  UNIQUE ({})", names(&key.columns))),
+            // index names belong to the database
+            KeyKind::Index => {
+                let index = key.name.clone().unwrap_or_else(|| key.columns.join("_"));
+                let index = if index.starts_with(&format!("{}_", name.name)) { index } else { format!("{}_{}", name.name, index) };
+                after.push_str(&format!("\nCREATE INDEX {} ON {} ({});", quote(&index), table, names(&key.columns)))
+            },
+            // checked only with `PRAGMA foreign_keys`, tables can be referenced before they exist
+            KeyKind::Foreign => if let Some(ref reference) = key.references {
+                let mut line = format!(
+                    "  FOREIGN KEY ({}) REFERENCES {} ({})",
+                    names(&key.columns), quote(&reference.table.name), names(&reference.columns)
+                );
+                if let Some(ref action) = reference.on_delete {
+                    line.push_str(&format!(" ON DELETE {}", action));
+                }
+                if let Some(ref action) = reference.on_update {
+                    line.push_str(&format!(" ON UPDATE {}", action));
+                }
+                lines.push(line);
+            },
+            KeyKind::Fulltext | KeyKind::Spatial => {},
+        }
+    }
+
+    Some(format!("CREATE TABLE {} (\n{}\n);{}", table, lines.join(",\n"), after).into_bytes())
+}
+
+/// A statement other than `CREATE TABLE` and inserts, None when SQLite has
+/// no use for it, like `LOCK TABLES` and `SET`, or can't run it, like routines.
+pub fn statement(kind: Kind, sql: &[u8]) -> Result<Option<Vec<u8>>, SyntaxErr> {
+    let statement = match kind {
+        Kind::Drop(Object::Table) | Kind::Drop(Object::View) => {
+            match Statement::parse(sql) {
+                // one name at a time
+                Statement::Drop { object, names, if_exists } => {
+                    let object = if object == Object::View { "VIEW" } else { "TABLE" };
+                    let exists = if if_exists { "IF EXISTS " } else { "" };
+                    let drops: Vec<String> = names.iter()
+                        .map(|name| format!("DROP {} {}{};", object, exists, quote(&name.name)))
+                        .collect();
+                    Some(drops.join("\n").into_bytes())
+                },
+                _ => None,
+            }
+        },
+        Kind::Create(Object::View) |
+        Kind::Create(Object::Index) |
+        Kind::Insert => Some(translate(sql)?),
+        _ => None,
+    };
+    Ok(statement)
+}
+
+/// `INSERT INTO "table" ("a", "b") VALUES ` for the rows of an insert.
+pub fn insert_head(insert_stmt: &[u8]) -> Result<Vec<u8>, SyntaxErr> {
+    let (verb, table, columns) = match Statement::parse(insert_stmt) {
+        Statement::Insert { verb, table, columns, .. } => (verb, table, columns),
+        _ => return translate(insert_stmt),
+    };
+
+    let verb = match verb {
+        Verb::Insert => "INSERT",
+        Verb::InsertIgnore => "INSERT OR IGNORE",
+        Verb::Replace => "REPLACE",
+    };
+    let columns = if columns.is_empty() { String::new() } else { format!(" ({})", names(&columns)) };
+    Ok(format!("{} INTO {}{} VALUES ", verb, quote(&table.name), columns).into_bytes())
+}
+
+/// A values tuple with `''` quoted strings and `X''` blobs, `binary` tells the
+/// blob fields. Hex literals of other columns are text, like MySQL reads them.
+pub fn row(tuple: &[u8], binary: &[bool]) -> Vec<u8> {
+    rewrite(tuple, |position, raw| {
+        match parse(raw) {
+            Value::String(ref bytes) if binary.get(position) == Some(&true) => Some(blob(bytes).into_bytes()),
+            // no escapes to undo
+            Value::String(_) if raw.first() == Some(&b'\'') && !raw.contains(&b'\\') => None,
+            Value::String(ref text) => Some(literal(text).into_bytes()),
+            Value::Hex(ref text) if binary.get(position) == Some(&false) => Some(literal(text).into_bytes()),
+            Value::Hex(ref bytes) => Some(blob(bytes).into_bytes()),
+            Value::Bit(bits) => Some(bits.to_string().into_bytes()),
+            _ => None,
+        }
+    })
+}
+
+/// Runs the statements of the `--to=sqlite` output in a database file,
+/// they come in chunks that can end in the middle of an insert.
+#[cfg(feature = "sqlite")]
+pub struct Database {
+    connection: rusqlite::Connection,
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "sqlite")]
+impl Database {
+    pub fn open(path: &str) -> Result<Self, String> {
+        match rusqlite::Connection::open(path) {
+            Ok(connection) => Ok(Database { connection, pending: vec![] }),
+            Err(e) => Err(format!("{}: {}", path, e)),
+        }
+    }
+
+    fn execute(&mut self) -> Result<(), String> {
+        let sql = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        self.connection.execute_batch(&sql).map_err(|e| {
+            let statement: String = sql.trim().chars().take(80).collect();
+            format!("{}: {}", e, statement)
+        })
+    }
+
+    pub fn write(&mut self, tokens: &[u8]) -> Result<(), String> {
+        self.pending.extend(tokens);
+        // chunks hold whole statements or tuples of an insert, so the `;`
+        // a chunk ends with ends a statement, one in a string ends with a quote
+        if tokens.trim_ascii_end().last() == Some(&b';') {
+            self.execute()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        if self.pending.trim_ascii().is_empty() {
+            return Ok(())
+        }
+        self.execute()
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub struct Database;
+
+#[cfg(not(feature = "sqlite"))]
+impl Database {
+    pub fn open(_path: &str) -> Result<Self, String> {
+        Err("sql-split was built without the sqlite feature".to_string())
+    }
+
+    pub fn write(&mut self, _tokens: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod sqlite_test{
+    use parser::Kind;
+    use parser::Object;
+    use super::*;
+
+    #[test]
+    fn create_table(){
+        let sql = b"CREATE TABLE `orders` (\n\
+            `id` int unsigned NOT NULL AUTO_INCREMENT,\n\
+            `user_id` mediumint(8) NOT NULL default '0',\n\
+            `state` enum('new','it''s done') DEFAULT NULL,\n\
+            `note` varchar(20) CHARACTER SET latin1 DEFAULT 'it\\'s',\n\
+            `total` decimal(10,2) DEFAULT NULL,\n\
+            `receipt` blob,\n\
+            `created_at` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,\n\
+            PRIMARY KEY (`id`),\n\
+            KEY `user_id` (`user_id`),\n\
+            CONSTRAINT `orders_user` FOREIGN KEY (`user_id`) REFERENCES `shop`.`users` (`id`) ON DELETE CASCADE\n\
+            ) ENGINE=InnoDB AUTO_INCREMENT=7 DEFAULT CHARSET=utf8mb4;";
+
+        assert_eq!(String::from_utf8(super::create_table(sql).unwrap()).unwrap(), "CREATE TABLE \"orders\" (\n  \
+            \"id\" INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,\n  \
+            \"user_id\" INTEGER DEFAULT '0' NOT NULL,\n  \
+            \"state\" TEXT DEFAULT NULL CHECK (\"state\" IN ('new', 'it''s done')),\n  \
+            \"note\" TEXT DEFAULT 'it''s',\n  \
+            \"total\" NUMERIC DEFAULT NULL,\n  \
+            \"receipt\" BLOB,\n  \
+            \"created_at\" TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,\n  \
+            FOREIGN KEY (\"user_id\") REFERENCES \"users\" (\"id\") ON DELETE CASCADE\n\
+            );\nCREATE INDEX \"orders_user_id\" ON \"orders\" (\"user_id\");");
+
+        let composite = b"CREATE TABLE `t` (`a` int NOT NULL AUTO_INCREMENT, `b` int NOT NULL, PRIMARY KEY (`a`,`b`)) ENGINE=MyISAM;";
+        assert_eq!(String::from_utf8(super::create_table(composite).unwrap()).unwrap(),
+            "CREATE TABLE \"t\" (\n  \"a\" INTEGER NOT NULL,\n  \"b\" INTEGER NOT NULL,\n  PRIMARY KEY (\"a\", \"b\")\n);");
+    }
+
+    #[test]
+    fn statements(){
+        let statement = |kind, sql: &[u8]| statement(kind, sql).unwrap().map(|sql| String::from_utf8(sql).unwrap());
+        assert_eq!(statement(Kind::Drop(Object::Table), b"DROP TABLE IF EXISTS `a`, `shop`.`b`;"),
+            Some("DROP TABLE IF EXISTS \"a\";\nDROP TABLE IF EXISTS \"b\";".to_string()));
+        assert_eq!(statement(Kind::Create(Object::View), b"CREATE ALGORITHM=UNDEFINED DEFINER=`root`@`%` SQL SECURITY DEFINER VIEW `v` AS select `id` from `t` where `name` = 'it\\'s';"),
+            Some("CREATE    VIEW \"v\" AS select \"id\" from \"t\" where \"name\" = 'it''s';".to_string()));
+        assert_eq!(statement(Kind::Lock, b"LOCK TABLES `t` WRITE;"), None);
+        assert_eq!(statement(Kind::Set, b"SET NAMES utf8mb4;"), None);
+        assert_eq!(statement(Kind::Keys, b"/*!40000 ALTER TABLE `t` DISABLE KEYS */;"), None);
+        assert_eq!(statement(Kind::Create(Object::Procedure), b"CREATE PROCEDURE p() BEGIN SELECT 1; END"), None);
+    }
+
+    #[test]
+    fn rows(){
+        assert_eq!(insert_head(b"INSERT IGNORE INTO `shop`.`t` (`a`, `b`) VALUES ").unwrap(), b"INSERT OR IGNORE INTO \"t\" (\"a\", \"b\") VALUES ".to_vec());
+        assert_eq!(insert_head(b"INSERT INTO `t` VALUES ").unwrap(), b"INSERT INTO \"t\" VALUES ".to_vec());
+
+        let row = |tuple: &[u8], binary: &[bool]| String::from_utf8(super::row(tuple, binary)).unwrap();
+        assert_eq!(row(b"\n(1,'it\\'s','a\\nb',NULL,'plain',0x616263,b'101')", &[]),
+            "\n(1,'it''s','a\nb',NULL,'plain',X'616263',5)");
+        assert_eq!(row(b"(0x616263,'\\0a',_binary 'ab')", &[false, true, true]), "('abc',X'0061',X'6162')");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn database(){
+        let mut database = Database::open(":memory:").unwrap();
+        database.write(b"CREATE TABLE \"t\" (\"a\" INTEGER, \"b\" TEXT);\n-- rows;\nBEGIN;\nINSERT INTO \"t\" VALUES ").unwrap();
+        database.write(b"(1,'x;')").unwrap();
+        database.write(b",\n(2,'y');").unwrap();
+        database.write(b"\nCOMMIT;\n").unwrap();
+        database.finish().unwrap();
+
+        let count: i64 = database.connection.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        assert!(database.write(b"INSERT INTO \"missing\" VALUES (1);").is_err());
+    }
+}
@@ -0,0 +1,919 @@
+use tokenizer::Tokenizer;
+use tokenizer::Token;
+use reader::Reader;
+use parser::Table;
+use parser::Object;
+use parser::significant;
+use parser::identifier;
+
+/// Typed form of a statement. The parser only hands out raw bytes,
+/// statements are parsed from them on demand, see `TokenStream::statement`.
+#[derive(Debug,PartialEq,Clone)]
+pub enum Statement {
+    CreateTable {
+        name: Table,
+        if_not_exists: bool,
+        columns: Vec<Column>,
+        keys: Vec<Key>,
+        options: Vec<TableOption>,
+    },
+    Insert {
+        verb: Verb,
+        table: Table,
+        // explicit column list, empty when the insert has none
+        columns: Vec<String>,
+        // raw values tuples, `(1,'a')`
+        rows: Vec<Vec<u8>>,
+    },
+    Set {
+        variables: Vec<Variable>,
+    },
+    Lock {
+        tables: Vec<Table>,
+    },
+    Unlock,
+    Drop {
+        object: Object,
+        names: Vec<Table>,
+        if_exists: bool,
+    },
+    Use {
+        database: String,
+    },
+    CreateView {
+        name: Table,
+        definer: Option<String>,
+    },
+    CreateTrigger {
+        name: Table,
+        table: Table,
+        definer: Option<String>,
+    },
+    CreateProcedure {
+        name: Table,
+        definer: Option<String>,
+    },
+    CreateFunction {
+        name: Table,
+        definer: Option<String>,
+    },
+    CreateEvent {
+        name: Table,
+        definer: Option<String>,
+    },
+    Other,
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Verb {
+    Insert,
+    InsertIgnore,
+    Replace,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Column {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+    // raw default value, `'0'`, `NULL` or `CURRENT_TIMESTAMP`
+    pub default: Option<Vec<u8>>,
+    pub auto_increment: bool,
+    pub charset: Option<String>,
+    pub collation: Option<String>,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct DataType {
+    // lower case, like `int` or `varchar`
+    pub name: String,
+    // length and precision, or the values of enum and set
+    pub args: Vec<String>,
+    pub unsigned: bool,
+}
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum KeyKind {
+    Primary,
+    Unique,
+    Index,
+    Fulltext,
+    Spatial,
+    Foreign,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Key {
+    pub kind: KeyKind,
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    pub references: Option<Reference>,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Reference {
+    pub table: Table,
+    pub columns: Vec<String>,
+    // upper cased, like `CASCADE` or `SET NULL`
+    pub on_delete: Option<String>,
+    pub on_update: Option<String>,
+}
+
+/// Table option like `ENGINE=InnoDB`, the name is upper cased.
+#[derive(Debug,PartialEq,Clone)]
+pub struct TableOption {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Variable {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+/// Walks over the tokens of a statement, skipping white space and comments.
+pub struct Cursor {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Cursor {
+    pub fn new(sql: &[u8]) -> Self {
+        let mut tokenizer = Tokenizer::new(Reader::new(sql));
+        let mut tokens = vec![];
+        // statements come from the parser, so they
+        // tokenize. anything after an error is dropped
+        while let Ok(Some(token)) = tokenizer.token() {
+            tokens.push(token);
+        }
+
+        Self { tokens, index: 0 }
+    }
+
+    fn skip(&mut self) {
+        while self.index < self.tokens.len() && !significant(&self.tokens[self.index]) {
+            self.index += 1;
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&Token> {
+        self.skip();
+        self.tokens.get(self.index)
+    }
+
+    pub fn take(&mut self) -> Option<Token> {
+        self.skip();
+        let token = self.tokens.get(self.index).cloned();
+        if token.is_some() {
+            self.index += 1;
+        }
+        token
+    }
+
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /// Goes back to a position taken before.
+    pub fn rewind(&mut self, position: usize) {
+        self.index = position;
+    }
+
+    /// Consumes the next token if it is the given keyword.
+    pub fn keyword(&mut self, word: &str) -> bool {
+        if self.peek().is_some_and(|token| token.keyword(word)) {
+            self.index += 1;
+            true
+        }else{
+            false
+        }
+    }
+
+    /// Consumes all of the keywords or none of them.
+    pub fn keywords(&mut self, words: &[&str]) -> bool {
+        let start = self.index;
+        for word in words {
+            if !self.keyword(word) {
+                self.index = start;
+                return false
+            }
+        }
+        true
+    }
+
+    /// Consumes the next token if it is the given one.
+    pub fn token(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.index += 1;
+            true
+        }else{
+            false
+        }
+    }
+
+    pub fn identifier(&mut self) -> Option<String> {
+        let name = match self.peek() {
+            Some(token @ Token::Identifier(_)) |
+            Some(token @ Token::Keyword(_)) => identifier(token),
+            _ => None,
+        };
+
+        if name.is_some() {
+            self.index += 1;
+        }
+        name
+    }
+
+    /// Optionally schema qualified name, `db`.`tbl`
+    pub fn name(&mut self) -> Option<Table> {
+        let first = self.identifier()?;
+        if self.token(&Token::Dot) {
+            Some(Table {
+                schema: Some(first),
+                name: self.identifier()?,
+            })
+        }else{
+            Some(Table {
+                schema: None,
+                name: first,
+            })
+        }
+    }
+
+    /// Raw bytes of the next parenthesized group, parentheses included.
+    pub fn group(&mut self) -> Vec<u8> {
+        let mut collection = vec![];
+        if self.peek() != Some(&Token::LP) {
+            return collection
+        }
+
+        let mut depth = 0;
+        while let Some(token) = self.tokens.get(self.index).cloned() {
+            self.index += 1;
+            match token {
+                Token::LP => depth += 1,
+                Token::RP => depth -= 1,
+                _ => {},
+            }
+
+            if raw(&token) {
+                collection.extend(token.value());
+            }
+
+            if depth == 0 {
+                break
+            }
+        }
+        collection
+    }
+
+    /// Raw bytes of the next value, `'0'`, `-1`, `CURRENT_TIMESTAMP(6)`
+    /// or `root`@`localhost`. That is the tokens up to the next white space.
+    pub fn expression(&mut self) -> Vec<u8> {
+        let mut collection = vec![];
+        self.skip();
+        while let Some(token) = self.tokens.get(self.index) {
+            match token {
+                Token::LP => {
+                    collection.extend(self.group());
+                    continue
+                },
+                Token::RP |
+                Token::Comma |
+                Token::SemiColon => break,
+                token if !significant(token) => break,
+                token => collection.extend(token.clone().value()),
+            }
+            self.index += 1;
+        }
+        collection
+    }
+
+    /// Raw bytes up to the next comma or closing parenthesis
+    /// outside of parentheses, trimmed.
+    pub fn until_comma(&mut self) -> Vec<u8> {
+        let mut collection = vec![];
+        self.skip();
+        while let Some(token) = self.tokens.get(self.index) {
+            match token {
+                Token::LP => {
+                    collection.extend(self.group());
+                    continue
+                },
+                Token::RP |
+                Token::Comma |
+                Token::SemiColon => break,
+                token if raw(token) => collection.extend(token.clone().value()),
+                _ => {},
+            }
+            self.index += 1;
+        }
+
+        while collection.last().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            collection.pop();
+        }
+        collection
+    }
+
+    /// `(`a`, `b`(10) DESC)`, the names of an index or insert column list.
+    pub fn names(&mut self) -> Vec<String> {
+        let mut names = vec![];
+        if !self.token(&Token::LP) {
+            return names
+        }
+
+        loop {
+            if let Some(name) = self.identifier() {
+                names.push(name);
+            }
+            self.until_comma();
+            if !self.token(&Token::Comma) {
+                self.token(&Token::RP);
+                break
+            }
+        }
+        names
+    }
+
+    pub fn done(&mut self) -> bool {
+        matches!(self.peek(), None | Some(Token::SemiColon))
+    }
+}
+
+// tokens that are part of the statement text, executable comment
+// markers and comments are not
+fn raw(token: &Token) -> bool {
+    !matches!(token,
+        Token::Comment(_) |
+        Token::InlineComment(_) |
+        Token::ConditionalStart(_) |
+        Token::ConditionalEnd
+    )
+}
+
+fn string(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+impl Statement {
+    pub fn parse(sql: &[u8]) -> Self {
+        let mut cursor = Cursor::new(sql);
+        let statement = if cursor.keyword("create") {
+            create(&mut cursor)
+        }else if cursor.keyword("insert") {
+            insert(&mut cursor, Verb::Insert)
+        }else if cursor.keyword("replace") {
+            insert(&mut cursor, Verb::Replace)
+        }else if cursor.keyword("set") {
+            set(&mut cursor)
+        }else if cursor.keyword("lock") {
+            lock(&mut cursor)
+        }else if cursor.keyword("unlock") {
+            Some(Statement::Unlock)
+        }else if cursor.keyword("drop") {
+            drop(&mut cursor)
+        }else if cursor.keyword("use") {
+            cursor.identifier().map(|database| Statement::Use { database })
+        }else{
+            None
+        };
+
+        statement.unwrap_or(Statement::Other)
+    }
+}
+
+fn create(cursor: &mut Cursor) -> Option<Statement> {
+    let mut definer = None;
+    // CREATE [OR REPLACE] [ALGORITHM=..] [DEFINER=..] [SQL SECURITY ..] VIEW
+    loop {
+        // `SQL SECURITY DEFINER` has no `=`
+        if cursor.keyword("definer") {
+            if cursor.token(&Token::Ignore(b'=')) {
+                definer = Some(string(cursor.expression()));
+            }
+            continue
+        }
+
+        let object = match cursor.peek() {
+            Some(token) if token.keyword("table") => Object::Table,
+            Some(token) if token.keyword("view") => Object::View,
+            Some(token) if token.keyword("trigger") => Object::Trigger,
+            Some(token) if token.keyword("procedure") => Object::Procedure,
+            Some(token) if token.keyword("function") => Object::Function,
+            Some(token) if token.keyword("event") => Object::Event,
+            Some(_) => {
+                cursor.take();
+                continue
+            },
+            None => return None,
+        };
+        cursor.take();
+        let if_not_exists = cursor.keywords(&["if", "not", "exists"]);
+        let name = cursor.name()?;
+
+        return match object {
+            Object::Table => create_table(cursor, name, if_not_exists),
+            Object::View => Some(Statement::CreateView { name, definer }),
+            Object::Trigger => {
+                while !cursor.keyword("on") {
+                    cursor.take()?;
+                }
+                let table = cursor.name()?;
+                Some(Statement::CreateTrigger { name, table, definer })
+            },
+            Object::Procedure => Some(Statement::CreateProcedure { name, definer }),
+            Object::Function => Some(Statement::CreateFunction { name, definer }),
+            _ => Some(Statement::CreateEvent { name, definer }),
+        }
+    }
+}
+
+fn create_table(cursor: &mut Cursor, name: Table, if_not_exists: bool) -> Option<Statement> {
+    let mut columns = vec![];
+    let mut keys = vec![];
+
+    // `CREATE TABLE .. LIKE` and `AS SELECT` have no definitions
+    if cursor.token(&Token::LP) {
+        loop {
+            if !key(cursor, &mut keys) {
+                if let Some(column) = column(cursor, &mut keys) {
+                    columns.push(column);
+                }
+            }
+
+            cursor.until_comma();
+            if !cursor.token(&Token::Comma) {
+                cursor.token(&Token::RP);
+                break
+            }
+        }
+    }
+
+    Some(Statement::CreateTable {
+        name,
+        if_not_exists,
+        columns,
+        keys,
+        options: options(cursor),
+    })
+}
+
+fn key(cursor: &mut Cursor, keys: &mut Vec<Key>) -> bool {
+    let mut name = None;
+    if cursor.keyword("constraint") && !matches!(cursor.peek(), Some(token) if token.keyword("primary") ||
+        token.keyword("unique") || token.keyword("foreign") || token.keyword("check")) {
+        name = cursor.identifier();
+    }
+
+    let kind = if cursor.keywords(&["primary", "key"]) {
+        KeyKind::Primary
+    }else if cursor.keyword("unique") {
+        KeyKind::Unique
+    }else if cursor.keyword("fulltext") {
+        KeyKind::Fulltext
+    }else if cursor.keyword("spatial") {
+        KeyKind::Spatial
+    }else if cursor.keywords(&["foreign", "key"]) {
+        KeyKind::Foreign
+    }else if cursor.keyword("key") || cursor.keyword("index") {
+        KeyKind::Index
+    }else if cursor.keyword("check") {
+        // check constraints are skipped
+        return true
+    }else{
+        return name.is_some()
+    };
+
+    if kind != KeyKind::Primary && kind != KeyKind::Foreign && !cursor.keyword("key") {
+        cursor.keyword("index");
+    }
+
+    if cursor.peek() != Some(&Token::LP) && !matches!(cursor.peek(), Some(token) if token.keyword("using")) {
+        name = cursor.identifier().or(name);
+    }
+
+    if cursor.keyword("using") {
+        cursor.take();
+    }
+
+    let columns = cursor.names();
+    let references = if cursor.keyword("references") {
+        cursor.name().map(|table| {
+            let columns = cursor.names();
+            let (mut on_delete, mut on_update) = (None, None);
+            loop {
+                if cursor.keywords(&["on", "delete"]) {
+                    on_delete = action(cursor);
+                }else if cursor.keywords(&["on", "update"]) {
+                    on_update = action(cursor);
+                }else{
+                    break
+                }
+            }
+            Reference { table, columns, on_delete, on_update }
+        })
+    }else{
+        None
+    };
+
+    keys.push(Key { kind, name, columns, references });
+    true
+}
+
+// `CASCADE`, `SET NULL`, `NO ACTION` of a foreign key
+fn action(cursor: &mut Cursor) -> Option<String> {
+    let action = if cursor.keywords(&["set", "null"]) {
+        "SET NULL"
+    }else if cursor.keywords(&["set", "default"]) {
+        "SET DEFAULT"
+    }else if cursor.keywords(&["no", "action"]) {
+        "NO ACTION"
+    }else{
+        return cursor.identifier().map(|action| action.to_uppercase())
+    };
+    Some(action.to_string())
+}
+
+fn column(cursor: &mut Cursor, keys: &mut Vec<Key>) -> Option<Column> {
+    let name = cursor.identifier()?;
+    let data_type = DataType {
+        name: cursor.identifier()?.to_lowercase(),
+        args: if cursor.peek() == Some(&Token::LP) {
+            cursor.token(&Token::LP);
+            let mut args = vec![];
+            loop {
+                args.push(string(cursor.until_comma()));
+                if !cursor.token(&Token::Comma) {
+                    cursor.token(&Token::RP);
+                    break
+                }
+            }
+            args
+        }else{
+            vec![]
+        },
+        unsigned: {
+            let mut unsigned = false;
+            loop {
+                if cursor.keyword("unsigned") {
+                    unsigned = true;
+                }else if !cursor.keyword("signed") && !cursor.keyword("zerofill") {
+                    break
+                }
+            }
+            unsigned
+        },
+    };
+
+    let mut column = Column {
+        name,
+        data_type,
+        nullable: true,
+        default: None,
+        auto_increment: false,
+        charset: None,
+        collation: None,
+    };
+
+    loop {
+        match cursor.peek() {
+            None | Some(Token::Comma) | Some(Token::RP) | Some(Token::SemiColon) => break,
+            _ => {},
+        }
+
+        if cursor.keywords(&["not", "null"]) {
+            column.nullable = false;
+        }else if cursor.keyword("null") {
+            column.nullable = true;
+        }else if cursor.keyword("default") {
+            column.default = Some(cursor.expression());
+        }else if cursor.keyword("auto_increment") {
+            column.auto_increment = true;
+        }else if cursor.keywords(&["character", "set"]) || cursor.keyword("charset") {
+            column.charset = cursor.identifier();
+        }else if cursor.keyword("collate") {
+            column.collation = cursor.identifier();
+        }else if cursor.keywords(&["primary", "key"]) {
+            keys.push(Key {
+                kind: KeyKind::Primary,
+                name: None,
+                columns: vec![column.name.clone()],
+                references: None,
+            });
+        }else if cursor.keyword("unique") {
+            cursor.keyword("key");
+            keys.push(Key {
+                kind: KeyKind::Unique,
+                name: None,
+                columns: vec![column.name.clone()],
+                references: None,
+            });
+        }else if cursor.keyword("comment") || cursor.keywords(&["on", "update"]) {
+            cursor.expression();
+        }else{
+            cursor.expression();
+            if cursor.peek() == Some(&Token::LP) {
+                cursor.group();
+            }
+        }
+    }
+
+    Some(column)
+}
+
+fn options(cursor: &mut Cursor) -> Vec<TableOption> {
+    let mut options = vec![];
+    loop {
+        cursor.token(&Token::Comma);
+        if cursor.done() || matches!(cursor.peek(), Some(token) if token.keyword("partition") || token.keyword("as")) {
+            break
+        }
+
+        let mut words = vec![];
+        while let Some(Token::Keyword(_)) = cursor.peek() {
+            words.push(cursor.identifier().unwrap_or_default());
+        }
+
+        let value = if cursor.token(&Token::Ignore(b'=')) || !words.is_empty() && !cursor.done() {
+            string(cursor.expression())
+        }else if words.len() > 1 {
+            // `DEFAULT CHARSET latin1` without `=`
+            words.pop().unwrap_or_default()
+        }else{
+            break
+        };
+
+        if words.is_empty() {
+            break
+        }
+
+        options.push(TableOption {
+            name: words.join(" ").to_uppercase(),
+            value,
+        });
+    }
+    options
+}
+
+fn insert(cursor: &mut Cursor, verb: Verb) -> Option<Statement> {
+    let mut verb = verb;
+    while cursor.keyword("low_priority") || cursor.keyword("delayed") ||
+        cursor.keyword("high_priority") {}
+
+    if cursor.keyword("ignore") && verb == Verb::Insert {
+        verb = Verb::InsertIgnore;
+    }
+
+    cursor.keyword("into");
+    let table = cursor.name()?;
+    let columns = cursor.names();
+    let mut rows = vec![];
+
+    if cursor.keyword("values") || cursor.keyword("value") {
+        while cursor.peek() == Some(&Token::LP) {
+            rows.push(cursor.group());
+            if !cursor.token(&Token::Comma) {
+                break
+            }
+        }
+    }
+
+    Some(Statement::Insert { verb, table, columns, rows })
+}
+
+fn set(cursor: &mut Cursor) -> Option<Statement> {
+    let mut variables = vec![];
+    loop {
+        if cursor.keyword("names") {
+            variables.push(Variable {
+                name: "NAMES".to_string(),
+                value: cursor.until_comma(),
+            });
+        }else if cursor.keywords(&["character", "set"]) {
+            variables.push(Variable {
+                name: "CHARACTER SET".to_string(),
+                value: cursor.until_comma(),
+            });
+        }else{
+            let mut name = vec![];
+            let mut word = false;
+            loop {
+                match cursor.take()? {
+                    Token::Ignore(b'=') => break,
+                    Token::Ignore(b':') if cursor.token(&Token::Ignore(b'=')) => break,
+                    token => {
+                        // `SESSION sql_mode`
+                        let keyword = matches!(token, Token::Keyword(_));
+                        if word && keyword {
+                            name.push(b' ');
+                        }
+                        word = keyword;
+                        name.extend(token.value())
+                    },
+                }
+            }
+
+            variables.push(Variable {
+                name: string(name),
+                value: cursor.until_comma(),
+            });
+        }
+
+        if !cursor.token(&Token::Comma) {
+            break
+        }
+    }
+
+    Some(Statement::Set { variables })
+}
+
+fn lock(cursor: &mut Cursor) -> Option<Statement> {
+    if !cursor.keyword("tables") && !cursor.keyword("table") {
+        return None
+    }
+
+    let mut tables = vec![];
+    loop {
+        tables.push(cursor.name()?);
+        cursor.until_comma();
+        if !cursor.token(&Token::Comma) {
+            break
+        }
+    }
+
+    Some(Statement::Lock { tables })
+}
+
+fn drop(cursor: &mut Cursor) -> Option<Statement> {
+    cursor.keyword("temporary");
+    let object = if cursor.keyword("table") || cursor.keyword("tables") {
+        Object::Table
+    }else if cursor.keyword("view") {
+        Object::View
+    }else if cursor.keyword("trigger") {
+        Object::Trigger
+    }else if cursor.keyword("procedure") {
+        Object::Procedure
+    }else if cursor.keyword("function") {
+        Object::Function
+    }else if cursor.keyword("event") {
+        Object::Event
+    }else if cursor.keyword("index") {
+        Object::Index
+    }else if cursor.keyword("database") || cursor.keyword("schema") {
+        Object::Database
+    }else{
+        return None
+    };
+
+    let if_exists = cursor.keywords(&["if", "exists"]);
+    let mut names = vec![];
+    loop {
+        names.push(cursor.name()?);
+        if !cursor.token(&Token::Comma) {
+            break
+        }
+    }
+
+    Some(Statement::Drop { object, names, if_exists })
+}
+
+
+#[cfg(test)]
+mod statement_test{
+    use std::fs::File;
+    use reader::Reader;
+    use tokenizer::Tokenizer;
+    use parser::Parser;
+    use parser::Table;
+    use parser::Object;
+    use super::*;
+
+    fn statements(path: &str) -> Vec<Statement> {
+        let file = File::open(path).unwrap();
+        let mut parser = Parser::new(Tokenizer::new(Reader::new(file)));
+        let mut statements = vec![];
+        loop {
+            match parser.token_stream() {
+                Ok(Some(item)) => statements.extend(item.statement()),
+                Ok(None) => break,
+                Err(e) => panic!("{}", e.text),
+            }
+        }
+        statements
+    }
+
+    fn table(name: &str) -> Table {
+        Table { schema: None, name: name.to_string() }
+    }
+
+    #[test]
+    fn create_table(){
+        let statements = statements("./example-files/mysqldump.txt");
+        let orders = statements.iter().find(|statement| {
+            matches!(statement, Statement::CreateTable { name, .. } if name.name == "orders")
+        });
+
+        let (columns, keys, options) = match orders {
+            Some(Statement::CreateTable { columns, keys, options, .. }) => (columns, keys, options),
+            _ => panic!("expected create table"),
+        };
+
+        let names: Vec<&str> = columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "user_id", "total", "note", "created_at"]);
+        assert!(columns[0].data_type.unsigned);
+        assert!(columns[0].auto_increment);
+        assert!(!columns[0].nullable);
+        assert_eq!(columns[2].data_type, DataType {
+            name: "decimal".to_string(),
+            args: vec!["10".to_string(), "2".to_string()],
+            unsigned: false,
+        });
+        assert_eq!(columns[2].default, Some(b"'0.00'".to_vec()));
+        assert!(columns[3].nullable);
+
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0].kind, KeyKind::Primary);
+        assert_eq!(keys[1].name, Some("orders_user_id_foreign".to_string()));
+        assert_eq!(keys[2], Key {
+            kind: KeyKind::Foreign,
+            name: Some("orders_user_id_foreign".to_string()),
+            columns: vec!["user_id".to_string()],
+            references: Some(Reference {
+                table: table("users"),
+                columns: vec!["id".to_string()],
+                on_delete: Some("CASCADE".to_string()),
+                on_update: None,
+            }),
+        });
+
+        let options: Vec<(&str, &str)> = options.iter()
+            .map(|option| (option.name.as_str(), option.value.as_str()))
+            .collect();
+        assert_eq!(options, vec![
+            ("ENGINE", "InnoDB"),
+            ("AUTO_INCREMENT", "5"),
+            ("DEFAULT CHARSET", "utf8mb4"),
+        ]);
+    }
+
+    #[test]
+    fn insert(){
+        let sql = b"INSERT IGNORE INTO `shop`.`orders` (`id`, `note`) VALUES (1,'a, (b)'),(2,NULL);";
+        assert_eq!(Statement::parse(sql), Statement::Insert {
+            verb: Verb::InsertIgnore,
+            table: Table { schema: Some("shop".to_string()), name: "orders".to_string() },
+            columns: vec!["id".to_string(), "note".to_string()],
+            rows: vec![b"(1,'a, (b)')".to_vec(), b"(2,NULL)".to_vec()],
+        });
+    }
+
+    #[test]
+    fn session_statements(){
+        let sql = b"/*!40014 SET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0 */;";
+        assert_eq!(Statement::parse(sql), Statement::Set {
+            variables: vec![
+                Variable { name: "@OLD_FOREIGN_KEY_CHECKS".to_string(), value: b"@@FOREIGN_KEY_CHECKS".to_vec() },
+                Variable { name: "FOREIGN_KEY_CHECKS".to_string(), value: b"0".to_vec() },
+            ],
+        });
+
+        assert_eq!(Statement::parse(b"LOCK TABLES `users` WRITE, `orders` READ;"), Statement::Lock {
+            tables: vec![table("users"), table("orders")],
+        });
+        assert_eq!(Statement::parse(b"UNLOCK TABLES;"), Statement::Unlock);
+        assert_eq!(Statement::parse(b"DROP TABLE IF EXISTS `a`, `b`;"), Statement::Drop {
+            object: Object::Table,
+            names: vec![table("a"), table("b")],
+            if_exists: true,
+        });
+    }
+
+    #[test]
+    fn routines(){
+        let statements: Vec<Statement> = statements("./example-files/routines.txt").into_iter()
+            .filter(|statement| matches!(statement,
+                Statement::Use { .. } |
+                Statement::CreateView { .. } |
+                Statement::CreateTrigger { .. } |
+                Statement::CreateProcedure { .. } |
+                Statement::CreateFunction { .. }
+            ))
+            .collect();
+
+        let root = Some("`root`@`localhost`".to_string());
+        assert_eq!(statements, vec![
+            Statement::Use { database: "shop".to_string() },
+            Statement::CreateTrigger {
+                name: table("users_before_insert"),
+                table: table("users"),
+                definer: Some("`root`@`%`".to_string()),
+            },
+            Statement::CreateView { name: table("active_users"), definer: None },
+            Statement::CreateProcedure { name: table("reset_logins"), definer: root.clone() },
+            Statement::CreateFunction { name: table("login_count"), definer: root.clone() },
+            Statement::CreateView { name: table("active_users"), definer: root },
+        ]);
+    }
+}
@@ -0,0 +1,467 @@
+use filter::TableFilter;
+use parser::Parser;
+use parser::Table;
+use parser::TokenStream;
+use parser::Kind;
+use parser::Object;
+use reader::Reader;
+use statement::Statement;
+use statement::KeyKind;
+use tokenizer::Tokenizer;
+use tokenizer::SyntaxErr;
+use value::Fields;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Seek, SeekFrom};
+
+// values of some columns of a row, as text
+type KeyValues = Vec<Vec<u8>>;
+// table and the columns of a key
+type KeyColumns = (Table, Vec<String>);
+
+/// Tables to sample for `--subset customers=0.01`. The rows of tables
+/// referencing sampled rows, directly or through other tables, are kept
+/// along with the rows those reference, so the subset loads without
+/// breaking foreign keys. Tables not connected to the sampled ones are
+/// kept whole.
+#[derive(Debug,Default)]
+pub struct Subset {
+    roots: Vec<(TableFilter, f64)>,
+    pub seed: u64,
+}
+
+// foreign key of a table, by column position
+#[derive(Debug)]
+struct ForeignKey {
+    columns: Vec<usize>,
+    parent: Table,
+    parent_columns: Vec<String>,
+}
+
+#[derive(Debug,Default)]
+struct Node {
+    columns: Vec<String>,
+    keys: Vec<ForeignKey>,
+}
+
+/// Rows the subset keeps, by their position among the rows of their table.
+#[derive(Debug,Default)]
+pub struct Selection {
+    kept: HashMap<Table, HashSet<usize>>,
+}
+
+impl Selection {
+    /// Whether the rows of a table are picked one by one.
+    pub fn constrains(&self, table: &Table) -> bool {
+        self.kept.contains_key(table)
+    }
+
+    pub fn keeps(&self, table: &Table, row: usize) -> bool {
+        match self.kept.get(table) {
+            Some(rows) => rows.contains(&row),
+            None => true,
+        }
+    }
+
+    pub fn rows(&self, table: &Table) -> Option<usize> {
+        self.kept.get(table).map(|rows| rows.len())
+    }
+}
+
+/// Position of a row among the rows of a table, the
+/// same in every pass and in the splitter.
+#[derive(Debug,Default)]
+pub struct RowCounter {
+    counts: HashMap<Table, usize>,
+}
+
+impl RowCounter {
+    pub fn next(&mut self, table: &Table) -> usize {
+        let count = self.counts.entry(table.clone()).or_insert(0);
+        *count += 1;
+        *count - 1
+    }
+}
+
+/// Whether a row is in a sample of `fraction` of the rows. The same
+/// row and seed always give the same answer.
+pub fn sampled(seed: u64, table: &Table, row: &[u8], fraction: f64) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_le_bytes());
+    hasher.update(table.name.as_bytes());
+    hasher.update(row);
+    let digest = hasher.finalize();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_le_bytes(bytes) as f64 / u64::MAX as f64) < fraction
+}
+
+// the tuple without white space and the `,` or `;` after it
+pub fn row_bytes(tuple: &[u8]) -> &[u8] {
+    let start = tuple.iter().position(|&byte| byte == b'(').unwrap_or(0);
+    let end = tuple.iter().rposition(|&byte| byte == b')').map_or(tuple.len(), |end| end + 1);
+    &tuple[start..end.max(start)]
+}
+
+fn key_values(values: &[Option<Vec<u8>>], columns: &[usize]) -> Option<KeyValues> {
+    columns.iter()
+        .map(|&index| values.get(index).cloned().unwrap_or(None))
+        .collect()
+}
+
+fn positions(columns: &[String], names: &[String]) -> Option<Vec<usize>> {
+    names.iter()
+        .map(|name| columns.iter().position(|column| column.eq_ignore_ascii_case(name)))
+        .collect()
+}
+
+struct Planner {
+    subset: Subset,
+    nodes: HashMap<Table, Node>,
+    // sampled tables and the ones referencing them
+    constrained: HashSet<Table>,
+    // rows sampled or referencing those, their children are kept too
+    reached: HashMap<Table, HashSet<usize>>,
+    kept: HashMap<Table, HashSet<usize>>,
+    reached_keys: HashMap<KeyColumns, HashSet<KeyValues>>,
+    required_keys: HashMap<KeyColumns, HashSet<KeyValues>>,
+    // columns of each table other tables reference
+    referenced: HashMap<Table, Vec<Vec<String>>>,
+}
+
+// runs `visit` for every row of the dump with its table, the columns of
+// the insert and its position among the rows of the table
+fn rows<R, F>(file: &mut R, mut visit: F) -> Result<(), SyntaxErr>
+    where R: io::Read, F: FnMut(&Table, &[String], usize, &[u8]) {
+    let mut parser = Parser::new(Tokenizer::new(Reader::new(file)));
+    let mut definitions: HashMap<Table, Vec<String>> = HashMap::new();
+    let mut counter = RowCounter::default();
+    let mut current: Option<(Table, Vec<String>)> = None;
+
+    while let Some(item) = parser.token_stream()? {
+        let tuple = match item {
+            TokenStream::Block(Kind::Create(Object::Table), ref tokens, _) => {
+                if let Statement::CreateTable { name, columns, .. } = Statement::parse(tokens) {
+                    definitions.insert(name, columns.into_iter().map(|column| column.name).collect());
+                }
+                continue
+            },
+            TokenStream::Insert(ref tokens, ref insert_stmt, ref table) => {
+                current = table.clone().map(|table| {
+                    let columns = match Statement::parse(insert_stmt) {
+                        Statement::Insert { ref columns, .. } if !columns.is_empty() => columns.clone(),
+                        _ => definitions.get(&table).cloned().unwrap_or_default(),
+                    };
+                    (table, columns)
+                });
+                tokens[insert_stmt.len() - 1..].to_vec()
+            },
+            TokenStream::ValuesTuple(tokens) => tokens,
+            _ => continue,
+        };
+
+        if let Some((ref table, ref columns)) = current {
+            let row = counter.next(table);
+            visit(table, columns, row, &tuple);
+        }
+    }
+    Ok(())
+}
+
+impl Planner {
+    fn schema<R: io::Read>(&mut self, file: &mut R) -> Result<(), SyntaxErr> {
+        let mut parser = Parser::new(Tokenizer::new(Reader::new(file)));
+        while let Some(item) = parser.token_stream()? {
+            if let TokenStream::Block(Kind::Create(Object::Table), ref tokens, _) = item {
+                if let Statement::CreateTable { name, columns, keys, .. } = Statement::parse(tokens) {
+                    let columns: Vec<String> = columns.into_iter().map(|column| column.name).collect();
+                    let keys = keys.into_iter()
+                        .filter(|key| key.kind == KeyKind::Foreign)
+                        .filter_map(|key| {
+                            let reference = key.references?;
+                            // a parent without a schema is in the schema of the table
+                            let parent = Table {
+                                schema: reference.table.schema.or_else(|| name.schema.clone()),
+                                name: reference.table.name,
+                            };
+                            Some(ForeignKey {
+                                columns: positions(&columns, &key.columns)?,
+                                parent,
+                                parent_columns: reference.columns,
+                            })
+                        })
+                        .collect();
+                    self.nodes.insert(name, Node { columns, keys });
+                }
+            }
+        }
+
+        for (table, node) in &self.nodes {
+            for key in &node.keys {
+                let referenced = self.referenced.entry(key.parent.clone()).or_default();
+                if !referenced.contains(&key.parent_columns) {
+                    referenced.push(key.parent_columns.clone());
+                }
+            }
+
+            if self.subset.roots.iter().any(|(filter, _)| filter.allows(table)) {
+                self.constrained.insert(table.clone());
+            }
+        }
+
+        // tables referencing constrained ones are constrained too
+        loop {
+            let children: Vec<Table> = self.nodes.iter()
+                .filter(|(table, node)| {
+                    !self.constrained.contains(*table) &&
+                        node.keys.iter().any(|key| self.constrained.contains(&key.parent))
+                })
+                .map(|(table, _)| table.clone())
+                .collect();
+
+            if children.is_empty() {
+                break
+            }
+            self.constrained.extend(children);
+        }
+        Ok(())
+    }
+
+    fn fraction(&self, table: &Table) -> Option<f64> {
+        self.subset.roots.iter()
+            .find(|(filter, _)| filter.allows(table))
+            .map(|(_, fraction)| *fraction)
+    }
+
+    // records the keys of a kept row, returns whether it is new
+    fn keep(&mut self, table: &Table, row: usize, values: &[Option<Vec<u8>>], reached: bool) -> bool {
+        let node = match self.nodes.get(table) {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let new_reach = reached && self.reached.entry(table.clone()).or_default().insert(row);
+        let new_kept = self.kept.entry(table.clone()).or_default().insert(row);
+        if !new_reach && !new_kept {
+            return false
+        }
+
+        if new_reach {
+            for columns in self.referenced.get(table).cloned().unwrap_or_default() {
+                let values = positions(&node.columns, &columns).and_then(|columns| key_values(values, &columns));
+                if let Some(values) = values {
+                    self.reached_keys.entry((table.clone(), columns)).or_default().insert(values);
+                }
+            }
+        }
+
+        if new_kept {
+            for key in &node.keys {
+                if !self.constrained.contains(&key.parent) {
+                    continue
+                }
+                if let Some(values) = key_values(values, &key.columns) {
+                    self.required_keys.entry((key.parent.clone(), key.parent_columns.clone()))
+                        .or_default()
+                        .insert(values);
+                }
+            }
+        }
+        true
+    }
+
+    fn visit(&mut self, table: &Table, columns: &[String], row: usize, tuple: &[u8]) -> bool {
+        if !self.constrained.contains(table) {
+            return false
+        }
+
+        let node = match self.nodes.get(table) {
+            Some(node) => node,
+            None => return false,
+        };
+
+        // the values of the insert in the order of the create statement
+        let fields: Vec<Option<Vec<u8>>> = Fields::new(tuple)
+            .map(|value| value.text().map(|text| text.into_owned()))
+            .collect();
+        let values: Vec<Option<Vec<u8>>> = node.columns.iter()
+            .map(|column| {
+                columns.iter()
+                    .position(|name| name.eq_ignore_ascii_case(column))
+                    .and_then(|index| fields.get(index).cloned().unwrap_or(None))
+            })
+            .collect();
+
+        let mut reached = match self.fraction(table) {
+            Some(fraction) => sampled(self.subset.seed, table, row_bytes(tuple), fraction),
+            None => false,
+        };
+
+        reached = reached || node.keys.iter().any(|key| {
+            let keys = self.reached_keys.get(&(key.parent.clone(), key.parent_columns.clone()));
+            match (keys, key_values(&values, &key.columns)) {
+                (Some(keys), Some(values)) => keys.contains(&values),
+                _ => false,
+            }
+        });
+
+        let required = !reached && self.referenced.get(table).is_some_and(|referenced| {
+            referenced.iter().any(|columns| {
+                let keys = self.required_keys.get(&(table.clone(), columns.clone()));
+                let values = positions(&node.columns, columns).and_then(|columns| key_values(&values, &columns));
+                match (keys, values) {
+                    (Some(keys), Some(values)) => keys.contains(&values),
+                    _ => false,
+                }
+            })
+        });
+
+        if reached || required {
+            self.keep(table, row, &values, reached)
+        }else{
+            false
+        }
+    }
+}
+
+impl Subset {
+    /// Parses `table=fraction`, like `customers=0.01`.
+    pub fn parse(roots: &[&str]) -> Result<Self, String> {
+        let mut subset = Self::default();
+        for root in roots {
+            let (table, fraction) = match root.find('=') {
+                Some(index) => (root[..index].trim(), root[index + 1..].trim()),
+                None => return Err(format!("{}: expected table=fraction", root)),
+            };
+
+            match fraction.parse::<f64>() {
+                Ok(fraction) if (0.0..=1.0).contains(&fraction) => {
+                    subset.roots.push((TableFilter::new(vec![table.to_string()], vec![]), fraction))
+                },
+                _ => return Err(format!("{}: fraction should be between 0 and 1", root)),
+            }
+        }
+        Ok(subset)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Reads the dump as many times as it takes to find the rows of the
+    /// subset, a row can reference rows further down the dump.
+    pub fn plan<R: io::Read + Seek>(self, file: &mut R) -> Result<Selection, SyntaxErr> {
+        let mut planner = Planner {
+            subset: self,
+            nodes: HashMap::new(),
+            constrained: HashSet::new(),
+            reached: HashMap::new(),
+            kept: HashMap::new(),
+            reached_keys: HashMap::new(),
+            required_keys: HashMap::new(),
+            referenced: HashMap::new(),
+        };
+
+        let rewind = |file: &mut R| {
+            file.seek(SeekFrom::Start(0)).map(|_| ()).map_err(|_| SyntaxErr{
+                text: "Unable to read the dump again."
+            })
+        };
+
+        planner.schema(file)?;
+        loop {
+            rewind(file)?;
+            let mut changed = false;
+            rows(file, |table, columns, row, tuple| {
+                changed = planner.visit(table, columns, row, tuple) || changed;
+            })?;
+
+            if !changed {
+                break
+            }
+        }
+        rewind(file)?;
+
+        let mut selection = Selection::default();
+        for table in planner.constrained {
+            let kept = planner.kept.remove(&table).unwrap_or_default();
+            selection.kept.insert(table, kept);
+        }
+        Ok(selection)
+    }
+}
+
+
+#[cfg(test)]
+mod subset_test{
+    use std::io::Cursor;
+    use parser::Table;
+    use super::*;
+
+    fn table(name: &str) -> Table {
+        Table { schema: None, name: name.to_string() }
+    }
+
+    #[test]
+    fn keeps_referencing_rows(){
+        // order 3 references a customer further down the dump and
+        // order 2 a product that is only kept because of it
+        let sql = b"CREATE TABLE `orders` (`id` int, `customer_id` int, `product_id` int,\n\
+            CONSTRAINT `a` FOREIGN KEY (`customer_id`) REFERENCES `customers` (`id`),\n\
+            CONSTRAINT `b` FOREIGN KEY (`product_id`) REFERENCES `products` (`id`));\n\
+            INSERT INTO `orders` VALUES (1,1,10),(2,2,20),(3,2,NULL);\n\
+            CREATE TABLE `customers` (`id` int, `name` text);\n\
+            INSERT INTO `customers` VALUES (1,'a'),(2,'b');\n\
+            CREATE TABLE `products` (`id` int, `parent_id` int,\n\
+            FOREIGN KEY (`parent_id`) REFERENCES `products` (`id`));\n\
+            INSERT INTO `products` (`id`, `parent_id`) VALUES (10,NULL),(20,30),(30,NULL),(40,NULL);\n\
+            CREATE TABLE `tags` (`id` int);\n\
+            INSERT INTO `tags` VALUES (1);\n";
+
+        let subset = Subset::parse(&["customers=1", "products=0"]).unwrap();
+        let selection = subset.plan(&mut Cursor::new(&sql[..])).unwrap();
+
+        assert!(!selection.constrains(&table("tags")));
+        let kept = |name: &str| {
+            let mut rows: Vec<usize> = (0..10).filter(|&row| selection.keeps(&table(name), row)).collect();
+            rows.sort();
+            rows
+        };
+        assert_eq!(kept("customers"), vec![0, 1]);
+        assert_eq!(kept("orders"), vec![0, 1, 2]);
+        // products referenced by the orders and the parent of one of them
+        assert_eq!(kept("products"), vec![0, 1, 2]);
+
+        let selection = Subset::parse(&["customers=0"]).unwrap().plan(&mut Cursor::new(&sql[..])).unwrap();
+        assert_eq!(selection.rows(&table("orders")), Some(0));
+        assert!(!selection.constrains(&table("products")));
+    }
+
+    #[test]
+    fn qualified_tables(){
+        // the orders of `b` have the name of the ones of `a` and no foreign key
+        let sql = b"CREATE TABLE `a`.`customers` (`id` int);\n\
+            INSERT INTO `a`.`customers` VALUES (1);\n\
+            CREATE TABLE `a`.`orders` (`id` int, `customer_id` int,\n\
+            FOREIGN KEY (`customer_id`) REFERENCES `customers` (`id`));\n\
+            INSERT INTO `a`.`orders` VALUES (1,1);\n\
+            CREATE TABLE `b`.`orders` (`id` int);\n\
+            INSERT INTO `b`.`orders` VALUES (1);\n";
+
+        let selection = Subset::parse(&["a.customers=0"]).unwrap().plan(&mut Cursor::new(&sql[..])).unwrap();
+        let qualified = |schema: &str, name: &str| Table { schema: Some(schema.to_string()), name: name.to_string() };
+        assert_eq!(selection.rows(&qualified("a", "orders")), Some(0));
+        assert!(!selection.constrains(&qualified("b", "orders")));
+        assert!(selection.keeps(&qualified("b", "orders"), 0));
+    }
+
+    #[test]
+    fn deterministic_sample(){
+        let users = table("users");
+        let picked: Vec<bool> = (0..200).map(|id| sampled(7, &users, format!("({})", id).as_bytes(), 0.1)).collect();
+        let again: Vec<bool> = (0..200).map(|id| sampled(7, &users, format!("({})", id).as_bytes(), 0.1)).collect();
+        assert_eq!(picked, again);
+        let count = picked.iter().filter(|&&picked| picked).count();
+        assert!(count > 5 && count < 40, "{}", count);
+    }
+}
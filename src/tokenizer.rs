@@ -1,5 +1,6 @@
 use std::str;
 use reader::Reader;
+use splitter::Dialect;
 use std::io;
 
 #[derive(Debug,PartialEq,Clone)]
@@ -17,13 +18,16 @@ pub enum Token{
     SemiColon,
     Ignore(u8),
     Dot,
+    // opening of an executable comment, `/*!40101` or `/*M!100316`
+    ConditionalStart(Vec<u8>),
+    ConditionalEnd,
 }
 
 impl Token {
     pub fn keyword(&self, string: &str) -> bool {
         match self {
             Token::Keyword(chunk) => {
-                let value = str::from_utf8(&chunk).unwrap();
+                let value = str::from_utf8(chunk).unwrap();
                 value.to_lowercase() == string
             },
             _ => false,
@@ -45,6 +49,8 @@ impl Token {
             Token::Dot => vec![b'.'],
             Token::Space => vec![b' '],
             Token::LineFeed(byte) => vec![byte],
+            Token::ConditionalStart(chunk) => chunk,
+            Token::ConditionalEnd => vec![b'*', b'/'],
         }        
     }
 }
@@ -52,6 +58,10 @@ impl Token {
 
 pub struct Tokenizer<T> {
     reader: Reader<T>,
+    // inside of a `/*! ... */` comment
+    conditional: bool,
+    // SQL dialect of the dump being read
+    dialect: Dialect,
     // line_num: usize,
 }
 
@@ -63,7 +73,42 @@ pub struct SyntaxErr{
 
 impl<T> Tokenizer<T> where T: io::Read {
     pub fn new(reader: Reader<T>) -> Self {
-        Self {reader}
+        Self::for_dialect(reader, Dialect::Mysql)
+    }
+
+    pub fn for_dialect(reader: Reader<T>, dialect: Dialect) -> Self {
+        Self {reader, conditional: false, dialect}
+    }
+
+    pub fn position(&self) -> u64 {
+        self.reader.position()
+    }
+
+    /// Inside a `/*! .. */` comment.
+    pub fn in_conditional(&self) -> bool {
+        self.conditional
+    }
+
+    /// Reads on from `position` of the input, where it was in a conditional comment or not.
+    pub fn resume(&mut self, position: u64, conditional: bool) {
+        self.reader.starts_at(position);
+        self.conditional = conditional;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// The rest of the line as is, with its line feed. Empty at the end of the file.
+    pub fn line(&mut self) -> Vec<u8> {
+        let mut collection = vec![];
+        while let Some(byte) = self.reader.get() {
+            collection.push(byte);
+            if byte == b'\n' {
+                break
+            }
+        }
+        collection
     }
 
     fn read_till(&mut self, item: u8) -> Result<Vec<u8>, SyntaxErr> {
@@ -96,19 +141,17 @@ impl<T> Tokenizer<T> where T: io::Read {
             match byte {
                 Some(item)  => {
                     match item {
-                        b'a'...b'z' |
-                        b'A'...b'Z' => {
+                        b'a'..=b'z' |
+                        b'A'..=b'Z' |
+                        b'0'..=b'9' |
+                        b'_' | b'$' => {
                             self.reader.increment_index();
                             collection.push(item);
                         },
                         _ => break,
                     }
                 },
-                None => {
-                    return Err(SyntaxErr{
-                        text:"While parsing keyword."
-                    })
-                }
+                None => break,
             }
         }
         
@@ -116,28 +159,80 @@ impl<T> Tokenizer<T> where T: io::Read {
     }
 
 
+    fn digits(&mut self, collection: &mut Vec<u8>, hex: bool) {
+        while let Some(byte) = self.reader.peek() {
+            match byte {
+                b'0'..=b'9' => {},
+                b'a'..=b'f' | b'A'..=b'F' if hex => {},
+                _ => break,
+            }
+            self.reader.increment_index();
+            collection.push(byte);
+        }
+    }
+
+    // integers, decimals like `19.99`, exponents like `1.5e-3`
+    // and `0x1F` or `0b101` literals
     fn number(&mut self) -> Token {
         let mut collection = vec![];
-        while let Some(byte @ b'0'...b'9') = self.reader.peek() {
+        self.digits(&mut collection, false);
+
+        let next = self.reader.peek();
+        let after = self.reader.peek_next();
+        if collection == b"0" && (next == Some(b'x') || next == Some(b'b')) &&
+            after.is_some_and(|byte| byte.is_ascii_hexdigit()) {
             self.reader.increment_index();
-            collection.push(byte);
+            collection.push(next.unwrap());
+            self.digits(&mut collection, true);
+            return Token::String(collection)
         }
+
+        if next == Some(b'.') && after.is_some_and(|byte| byte.is_ascii_digit()) {
+            self.reader.increment_index();
+            collection.push(b'.');
+            self.digits(&mut collection, false);
+        }
+
+        if let Some(e @ b'e') | Some(e @ b'E') = self.reader.peek() {
+            let sign = self.reader.peek_next();
+            let signed = sign == Some(b'-') || sign == Some(b'+');
+            let digit = if signed { self.reader.peek_at(2) } else { sign };
+            if digit.is_some_and(|byte| byte.is_ascii_digit()) {
+                self.reader.increment_index();
+                collection.push(e);
+                if signed {
+                    self.reader.increment_index();
+                    collection.push(sign.unwrap());
+                }
+                self.digits(&mut collection, false);
+            }
+        }
+
         Token::String(collection)
     }
 
-    fn read_string(&mut self, closing: u8) -> Result<Token, SyntaxErr> {
+    fn read_string(&mut self, closing: u8, escapes: bool) -> Result<Token, SyntaxErr> {
         let mut collection = vec![];
-        let mut last_byte = self.reader.get().unwrap();
-        collection.push(last_byte);
+        collection.push(self.reader.get().unwrap());
+        // `'\\'` ends with a backslash that is not escaping the quote
+        let mut escaped = false;
 
         loop {
             let byte = self.reader.get();
             if let Some(item) = byte {
                 collection.push(item);
-                if item == closing && last_byte != b'\\' {
+                if escaped {
+                    escaped = false;
+                }else if item == b'\\' && escapes {
+                    escaped = true;
+                }else if item == closing {
+                    // postgres and sqlite double the quote in a string, `'it''s'`
+                    if self.dialect != Dialect::Mysql && self.reader.peek() == Some(closing) {
+                        collection.push(self.reader.get().unwrap());
+                        continue
+                    }
                     break;
                 }
-                last_byte = item;
             }else{
                 return Err(SyntaxErr{
                     text: "Unclosed string."
@@ -147,26 +242,99 @@ impl<T> Tokenizer<T> where T: io::Read {
         Ok(Token::String(collection))
     }
 
+    // `$$` or `$body$` starting a dollar quoted string of postgres
+    fn dollar_tag(&mut self) -> Option<Vec<u8>> {
+        let mut tag = vec![b'$'];
+        let mut ahead = 1;
+        loop {
+            match self.reader.peek_at(ahead)? {
+                b'$' => {
+                    tag.push(b'$');
+                    return Some(tag)
+                },
+                byte @ (b'a'..=b'z' | b'A'..=b'Z' | b'_') => tag.push(byte),
+                byte @ b'0'..=b'9' if ahead > 1 => tag.push(byte),
+                _ => return None,
+            }
+            ahead += 1;
+        }
+    }
+
+    // function bodies like `$$ BEGIN .. END; $$`, the string ends with its opening tag
+    fn dollar_string(&mut self, tag: Vec<u8>) -> Result<Token, SyntaxErr> {
+        let mut collection = vec![];
+        for _ in 0..tag.len() {
+            collection.push(self.reader.get().unwrap());
+        }
+
+        loop {
+            match self.reader.get() {
+                Some(byte) => {
+                    collection.push(byte);
+                    if byte == b'$' && collection.len() >= tag.len() * 2 && collection.ends_with(&tag) {
+                        return Ok(Token::String(collection))
+                    }
+                },
+                None => return Err(SyntaxErr{
+                    text: "Unclosed string."
+                }),
+            }
+        }
+    }
+
+    // `"order"` of postgres and sqlite, a doubled quote is an escaped one
+    fn quoted_identifier(&mut self) -> Result<Token, SyntaxErr> {
+        let mut identifier = vec![self.reader.get().unwrap()];
+        identifier.extend(self.read_till(b'"')?);
+        while self.reader.peek() == Some(b'"') {
+            self.reader.increment_index();
+            identifier.push(b'"');
+            identifier.extend(self.read_till(b'"')?);
+        }
+        Ok(Token::Identifier(identifier))
+    }
+
     fn singular(&mut self, token: Token) -> Result<Option<Token>, SyntaxErr> {
         self.reader.increment_index();
         Ok(Some(token))
     }
     
     pub fn token(&mut self) -> Result<Option<Token>, SyntaxErr> {
+        let mysql = self.dialect == Dialect::Mysql;
+        let postgres = self.dialect == Dialect::Postgres;
         match self.reader.peek() {
+            Some(b'"') if !mysql => Ok(Some(self.quoted_identifier()?)),
+            Some(b'\'') if !mysql => Ok(Some(self.read_string(b'\'', false)?)),
+            // `E'\n'` has the escapes the other strings of postgres don't
+            Some(prefix @ b'E') |
+            Some(prefix @ b'e') if postgres && self.reader.peek_next() == Some(b'\'') => {
+                self.reader.increment_index();
+                let mut string = vec![prefix];
+                string.extend(self.read_string(b'\'', true)?.value());
+                Ok(Some(Token::String(string)))
+            },
+            Some(b'$') if postgres => match self.dollar_tag() {
+                Some(tag) => Ok(Some(self.dollar_string(tag)?)),
+                None => self.singular(Token::Ignore(b'$')),
+            },
             Some(closing @ b'"') |
             Some(closing @ b'\'') => {
-                Ok(Some(self.read_string(closing)?))
+                Ok(Some(self.read_string(closing, true)?))
             },
             Some(byte @ b'/') => {
                 if self.reader.peek_next() == Some(b'*') {
-                    self.comment()
+                    if mysql && (self.reader.peek_at(2) == Some(b'!') ||
+                        (self.reader.peek_at(2) == Some(b'M') && self.reader.peek_at(3) == Some(b'!'))) {
+                        Ok(Some(self.conditional()))
+                    }else{
+                        self.comment()
+                    }
                 }else{
                     self.reader.increment_index();
                     Ok(Some(Token::Ignore(byte)))
                 }
             },
-            Some(b'0'...b'9') => Ok(Some(self.number())),
+            Some(b'0'..=b'9') => Ok(Some(self.number())),
             Some(byte @ b'-') => {
                 if self.reader.peek_next() == Some(b'-') {
                     Ok(Some(Token::InlineComment(self.read_till(b'\n')?)))
@@ -175,8 +343,8 @@ impl<T> Tokenizer<T> where T: io::Read {
                     Ok(Some(Token::Ignore(byte)))
                 }
             },
-            Some(b'a'...b'z') | 
-            Some(b'A'...b'Z') => {
+            Some(b'a'..=b'z') | 
+            Some(b'A'..=b'Z') => {
                 // let keyword = ;
                 Ok(Some(Token::Keyword(self.keyword()?)))
             },
@@ -184,8 +352,19 @@ impl<T> Tokenizer<T> where T: io::Read {
                 self.reader.increment_index(); // skip `
                 let mut identifier = vec![byte];
                 identifier.extend(self.read_till(b'`')?);
+                // doubled backtick is an escaped one
+                while self.reader.peek() == Some(b'`') {
+                    self.reader.increment_index();
+                    identifier.push(b'`');
+                    identifier.extend(self.read_till(b'`')?);
+                }
                 Ok(Some(Token::Identifier(identifier)))
             },
+            Some(b'*') if self.conditional && self.reader.peek_next() == Some(b'/') => {
+                self.reader.increment_index();
+                self.conditional = false;
+                self.singular(Token::ConditionalEnd)
+            },
             Some(b'.') => self.singular(Token::Dot),
             Some(b'(') => self.singular(Token::LP),
             Some(b')') => self.singular(Token::RP),
@@ -200,6 +379,26 @@ impl<T> Tokenizer<T> where T: io::Read {
         }
     }
 
+    // the content of executable comments is tokenized like regular sql,
+    // only the opening and the closing `*/` are marked.
+    fn conditional(&mut self) -> Token {
+        let mut collection = vec![];
+        while let Some(byte) = self.reader.get() {
+            collection.push(byte);
+            if byte == b'!' {
+                break
+            }
+        }
+
+        while let Some(byte @ b'0'..=b'9') = self.reader.peek() {
+            self.reader.increment_index();
+            collection.push(byte);
+        }
+
+        self.conditional = true;
+        Token::ConditionalStart(collection)
+    }
+
     fn comment(&mut self) -> Result<Option<Token>, SyntaxErr> {
         let mut collection = vec![];
         loop {
@@ -0,0 +1,428 @@
+use std::borrow::Cow;
+use std::str;
+
+/// A field of a values tuple. Strings borrow from the tuple unless
+/// they had escapes to undo.
+#[derive(Debug,PartialEq,Clone)]
+pub enum Value<'a> {
+    Null,
+    Int(i64),
+    // decimals and integers that don't fit an i64, kept as written
+    Decimal(&'a str),
+    Float(f64),
+    String(Cow<'a, [u8]>),
+    // `0x1F`, `X'1F'` and `_binary '..'`
+    Hex(Cow<'a, [u8]>),
+    // `0b101` and `b'101'`
+    Bit(u64),
+    // anything else, like `NOW()`, kept as written
+    Expression(&'a [u8]),
+}
+
+impl<'a> Value<'a> {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// Text of the value without quotes and escapes, None for NULL.
+    pub fn text(&self) -> Option<Cow<'_, [u8]>> {
+        match self {
+            Value::Null => None,
+            Value::Int(value) => Some(Cow::Owned(value.to_string().into_bytes())),
+            Value::Decimal(value) => Some(Cow::Borrowed(value.as_bytes())),
+            Value::Float(value) => Some(Cow::Owned(value.to_string().into_bytes())),
+            Value::String(value) |
+            Value::Hex(value) => Some(Cow::Borrowed(value)),
+            Value::Bit(value) => Some(Cow::Owned(value.to_string().into_bytes())),
+            Value::Expression(value) => Some(Cow::Borrowed(value)),
+        }
+    }
+
+    /// Writes the value as a MySQL literal.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Value::Null => out.extend(b"NULL"),
+            Value::Int(value) => out.extend(value.to_string().as_bytes()),
+            Value::Decimal(value) => out.extend(value.as_bytes()),
+            Value::Float(value) => out.extend(format!("{:e}", value).as_bytes()),
+            Value::String(value) => {
+                out.push(b'\'');
+                escape(value, out);
+                out.push(b'\'');
+            },
+            Value::Hex(value) => {
+                if value.is_empty() {
+                    // `0x` alone is not a literal
+                    out.extend(b"''");
+                }else{
+                    out.extend(b"0x");
+                    for byte in value.iter() {
+                        out.extend(format!("{:02X}", byte).as_bytes());
+                    }
+                }
+            },
+            Value::Bit(value) => out.extend(format!("b'{:b}'", value).as_bytes()),
+            Value::Expression(value) => out.extend(*value),
+        }
+    }
+
+    pub fn to_sql(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.write(&mut out);
+        out
+    }
+}
+
+/// Escapes a string the way mysqldump does, quotes not included.
+pub fn escape(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        match byte {
+            0 => out.extend(b"\\0"),
+            b'\'' => out.extend(b"\\'"),
+            b'"' => out.extend(b"\\\""),
+            b'\\' => out.extend(b"\\\\"),
+            b'\n' => out.extend(b"\\n"),
+            b'\r' => out.extend(b"\\r"),
+            0x1a => out.extend(b"\\Z"),
+            byte => out.push(byte),
+        }
+    }
+}
+
+/// Undoes the escapes of a string literal, quotes not included.
+pub fn unescape(bytes: &[u8], quote: u8) -> Cow<'_, [u8]> {
+    if !bytes.iter().any(|&byte| byte == b'\\' || byte == quote) {
+        return Cow::Borrowed(bytes)
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index += 1;
+        if byte == quote {
+            // doubled quote
+            out.push(byte);
+            if bytes.get(index) == Some(&quote) {
+                index += 1;
+            }
+        }else if byte == b'\\' && index < bytes.len() {
+            let next = bytes[index];
+            index += 1;
+            match next {
+                b'0' => out.push(0),
+                b'b' => out.push(8),
+                b'n' => out.push(b'\n'),
+                b'r' => out.push(b'\r'),
+                b't' => out.push(b'\t'),
+                b'Z' => out.push(0x1a),
+                // kept for LIKE patterns
+                b'%' | b'_' => out.extend(&[b'\\', next]),
+                next => out.push(next),
+            }
+        }else{
+            out.push(byte);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Iterates over the fields of a values tuple, like `(1,'a',NOW()),`
+pub struct Fields<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    done: bool,
+}
+
+impl<'a> Fields<'a> {
+    /// Starts at the first `(`, so an insert with its first
+    /// tuple works as well as a lone tuple.
+    pub fn new(tuple: &'a [u8]) -> Self {
+        match tuple.iter().position(|&byte| byte == b'(') {
+            Some(index) => Fields { bytes: tuple, index: index + 1, done: false },
+            None => Fields { bytes: tuple, index: tuple.len(), done: true },
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).cloned()
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.index += 1;
+        }
+    }
+
+    // end of a quoted string starting at `start`, the closing quote included
+    fn string_end(&self, start: usize) -> usize {
+        let quote = self.bytes[start];
+        let mut index = start + 1;
+        while index < self.bytes.len() {
+            match self.bytes[index] {
+                b'\\' => index += 1,
+                byte if byte == quote => {
+                    if self.bytes.get(index + 1) != Some(&quote) {
+                        return index + 1
+                    }
+                    index += 1;
+                },
+                _ => {},
+            }
+            index += 1;
+        }
+        self.bytes.len()
+    }
+
+    // bounds of the field, up to a comma or the closing
+    // parenthesis that are not nested or quoted
+    fn span(&mut self) -> Option<(usize, usize)> {
+        if self.done {
+            return None
+        }
+
+        self.skip_space();
+        if self.peek() == Some(b')') || self.peek().is_none() {
+            self.done = true;
+            return None
+        }
+
+
+        let start = self.index;
+        let mut depth = 0;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\'' | b'"' | b'`' => {
+                    self.index = self.string_end(self.index);
+                    continue
+                },
+                b'(' => depth += 1,
+                b')' if depth == 0 => break,
+                b')' => depth -= 1,
+                b',' if depth == 0 => break,
+                _ => {},
+            }
+            self.index += 1;
+        }
+
+        let mut end = self.index;
+        while end > start && self.bytes[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+
+        match self.peek() {
+            Some(b',') => self.index += 1,
+            _ => self.done = true,
+        }
+        Some((start, end))
+    }
+}
+
+// the part between the quotes, if the whole field is one string
+fn quoted(raw: &[u8]) -> Option<(u8, &[u8])> {
+    let quote = *raw.first()?;
+    if raw.len() > 1 && (quote == b'\'' || quote == b'"') && raw.last() == Some(&quote) {
+        Some((quote, &raw[1..raw.len() - 1]))
+    }else{
+        None
+    }
+}
+
+fn hex(digits: &[u8]) -> Option<Vec<u8>> {
+    let digits = str::from_utf8(digits).ok()?;
+    // odd number of digits has an implied leading zero
+    let padded = if digits.len() % 2 == 1 { format!("0{}", digits) } else { digits.to_string() };
+    (0..padded.len()).step_by(2)
+        .map(|index| u8::from_str_radix(&padded[index..index + 2], 16).ok())
+        .collect()
+}
+
+fn number(raw: &[u8]) -> Option<Value<'_>> {
+    let text = str::from_utf8(raw).ok()?;
+    let digits = text.trim_start_matches(['-', '+']);
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') ||
+        !digits.bytes().any(|byte| byte.is_ascii_digit()) {
+        return None
+    }
+
+    if digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Some(match text.parse::<i64>() {
+            Ok(value) => Value::Int(value),
+            Err(_) => Value::Decimal(text),
+        })
+    }
+
+    if digits.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.') &&
+        digits.bytes().filter(|&byte| byte == b'.').count() == 1 {
+        return Some(Value::Decimal(text))
+    }
+
+    text.parse::<f64>().ok().map(Value::Float)
+}
+
+/// Types a single field as written in a values tuple.
+pub fn parse(raw: &[u8]) -> Value<'_> {
+    if raw.eq_ignore_ascii_case(b"null") {
+        return Value::Null
+    }
+
+    if let Some((quote, inner)) = quoted(raw) {
+        return Value::String(unescape(inner, quote))
+    }
+
+    if let Some(value) = number(raw) {
+        return value
+    }
+
+    let prefixed = |prefix: &[u8]| raw.len() > prefix.len() && raw[..prefix.len()].eq_ignore_ascii_case(prefix);
+    if prefixed(b"0x") {
+        if let Some(bytes) = hex(&raw[2..]) {
+            return Value::Hex(Cow::Owned(bytes))
+        }
+    }
+
+    if prefixed(b"0b") {
+        if let Ok(bits) = u64::from_str_radix(&String::from_utf8_lossy(&raw[2..]), 2) {
+            return Value::Bit(bits)
+        }
+    }
+
+    if prefixed(b"x") || prefixed(b"b") {
+        if let Some((b'\'', inner)) = quoted(&raw[1..]) {
+            let value = if prefixed(b"x") {
+                hex(inner).map(|bytes| Value::Hex(Cow::Owned(bytes)))
+            }else{
+                u64::from_str_radix(&String::from_utf8_lossy(inner), 2).ok().map(Value::Bit)
+            };
+            if let Some(value) = value {
+                return value
+            }
+        }
+    }
+
+    // character set introducer, `_utf8mb4'..'` or `_binary '..'`
+    if raw.first() == Some(&b'_') {
+        if let Some(start) = raw.iter().position(|&byte| byte == b'\'' || byte == b'"') {
+            let introducer = &raw[..start];
+            if let Some((quote, inner)) = quoted(&raw[start..]) {
+                if introducer.trim_ascii_end().eq_ignore_ascii_case(b"_binary") {
+                    return Value::Hex(unescape(inner, quote))
+                }
+                return Value::String(unescape(inner, quote))
+            }
+        }
+    }
+
+    Value::Expression(raw)
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Value<'a>;
+
+    fn next(&mut self) -> Option<Value<'a>> {
+        let bytes = self.bytes;
+        self.span().map(|(start, end)| parse(&bytes[start..end]))
+    }
+}
+
+/// Rebuilds a tuple with the fields `replace` returns new bytes for.
+/// The rest, white space and the trailing `,` or `;` included, is kept as is.
+pub fn rewrite<F>(tuple: &[u8], mut replace: F) -> Vec<u8>
+    where F: FnMut(usize, &[u8]) -> Option<Vec<u8>> {
+    let mut fields = Fields::new(tuple);
+    let mut out = Vec::with_capacity(tuple.len());
+    let mut copied = 0;
+    let mut position = 0;
+    while let Some((start, end)) = fields.span() {
+        if let Some(value) = replace(position, &tuple[start..end]) {
+            out.extend(&tuple[copied..start]);
+            out.extend(value);
+            copied = end;
+        }
+        position += 1;
+    }
+    out.extend(&tuple[copied..]);
+    out
+}
+
+/// Writes values as a tuple, `(1,'a')`
+pub fn tuple(values: &[Value], out: &mut Vec<u8>) {
+    out.push(b'(');
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        value.write(out);
+    }
+    out.push(b')');
+}
+
+
+#[cfg(test)]
+mod value_test{
+    use std::borrow::Cow;
+    use super::*;
+
+    #[test]
+    fn typed_fields(){
+        let row = b"(1, -2, 19.99, 1.5e-3, 'O\\'Neil', \"a\"\"b\", NULL, 0x1F, X'0a0b', 0b101, b'11', \
+            _binary 'x\\0', NOW(), CONCAT('a,)', (1)), 18446744073709551615),";
+        let values: Vec<Value> = Fields::new(row).collect();
+
+        assert_eq!(values, vec![
+            Value::Int(1),
+            Value::Int(-2),
+            Value::Decimal("19.99"),
+            Value::Float(1.5e-3),
+            Value::String(Cow::Owned(b"O'Neil".to_vec())),
+            Value::String(Cow::Owned(b"a\"b".to_vec())),
+            Value::Null,
+            Value::Hex(Cow::Owned(vec![0x1f])),
+            Value::Hex(Cow::Owned(vec![0x0a, 0x0b])),
+            Value::Bit(5),
+            Value::Bit(3),
+            Value::Hex(Cow::Owned(vec![b'x', 0])),
+            Value::Expression(b"NOW()"),
+            Value::Expression(b"CONCAT('a,)', (1))"),
+            Value::Decimal("18446744073709551615"),
+        ]);
+    }
+
+    #[test]
+    fn borrows_plain_strings(){
+        let row = b"('ann@example.com')";
+        match Fields::new(row).next() {
+            Some(Value::String(Cow::Borrowed(value))) => assert_eq!(value, b"ann@example.com"),
+            value => panic!("expected borrowed string, got {:?}", value),
+        }
+        assert_eq!(Fields::new(b"()").count(), 0);
+    }
+
+    #[test]
+    fn round_trip(){
+        let text = b"it's a \"test\"\\\n\r\0\x1a end".to_vec();
+        let value = Value::String(Cow::Borrowed(&text));
+        let sql = value.to_sql();
+        assert_eq!(sql, b"'it\\'s a \\\"test\\\"\\\\\\n\\r\\0\\Z end'".to_vec());
+        assert_eq!(parse(&sql), value);
+
+        let mut out = vec![];
+        let values: Vec<Value> = Fields::new(b"(1,0xFF00,b'101',NULL,NOW())").collect();
+        tuple(&values, &mut out);
+        assert_eq!(out, b"(1,0xFF00,b'101',NULL,NOW())".to_vec());
+    }
+
+    #[test]
+    fn rewrites_fields(){
+        let row = b"\n(1, 'ann', NOW() ),";
+        let out = rewrite(row, |position, raw| {
+            if position == 1 {
+                assert_eq!(raw, b"'ann'");
+                Some(b"'bob'".to_vec())
+            }else{
+                None
+            }
+        });
+        assert_eq!(out, b"\n(1, 'bob', NOW() ),".to_vec());
+    }
+}
@@ -0,0 +1,204 @@
+use parser::Parser;
+use parser::TokenStream;
+use parser::Kind;
+use reader::Reader;
+use splitter::Dialect;
+use subset::row_bytes;
+use tokenizer::Tokenizer;
+use tokenizer::SyntaxErr;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// Statements and rows of a dump, or of the chunks of its split.
+#[derive(Debug,Default)]
+pub struct Summary {
+    // statements other than inserts, in their order
+    statements: Vec<Vec<u8>>,
+    // count and a sum of the hashes of the rows of every table,
+    // the same for the same rows in any order
+    rows: BTreeMap<String, (u64, u128)>,
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|byte| !byte.is_ascii_whitespace()).map_or(start, |end| end + 1);
+    &bytes[start..end]
+}
+
+// `1 row`, `2 rows`
+fn plural(count: u64, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    }else{
+        format!("{} {}s", count, noun)
+    }
+}
+
+// the first bytes of a statement, to point at it
+fn excerpt(statement: &[u8]) -> String {
+    let text = String::from_utf8_lossy(statement);
+    let line = text.lines().next().unwrap_or_default();
+    match line.char_indices().nth(60) {
+        Some((end, _)) => format!("{}..", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+impl Summary {
+    fn row(&mut self, table: &str, row: &[u8]) {
+        let digest = Sha256::digest(row);
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        let entry = self.rows.entry(table.to_string()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 = entry.1.wrapping_add(u128::from_le_bytes(bytes));
+    }
+
+    // `chunk` skips what a split repeats at the start of a file, the
+    // sqlite pragmas before `BEGIN TRANSACTION`
+    fn read<R: Read>(&mut self, file: R, dialect: Dialect, chunk: bool) -> Result<(), SyntaxErr> {
+        let mut parser = Parser::new(Tokenizer::for_dialect(Reader::new(file), dialect));
+        let mut table = String::new();
+        let mut opening = chunk && dialect == Dialect::Sqlite;
+
+        while let Some(item) = parser.token_stream()? {
+            match item {
+                TokenStream::Insert(tokens, insert_stmt, insert_table) => {
+                    table = insert_table.map(|table| table.to_string()).unwrap_or_default();
+                    self.row(&table, row_bytes(&tokens[insert_stmt.len() - 1..]));
+                },
+                TokenStream::ValuesTuple(tokens) => self.row(&table, row_bytes(&tokens)),
+                TokenStream::Copy(_, copy_table) => {
+                    table = copy_table.map(|table| table.to_string()).unwrap_or_default();
+                },
+                TokenStream::CopyRow(tokens) => self.row(&table, trim(&tokens)),
+                // the delimiter and the transaction are repeated in the files of a split
+                TokenStream::Block(Kind::Delimiter, _, _) |
+                TokenStream::Block(Kind::Transaction, _, _) => opening = false,
+                TokenStream::Block(kind, tokens, _) => {
+                    let pragma = kind == Kind::Other && tokens.len() > 6 && tokens[..6].eq_ignore_ascii_case(b"pragma");
+                    if !(opening && pragma) {
+                        opening = false;
+                        self.statements.push(trim(&tokens).to_vec());
+                    }
+                },
+                TokenStream::CopyEnd(_) | TokenStream::Comment(_) | TokenStream::SpaceOrLineFeed(_) => {},
+            }
+        }
+        Ok(())
+    }
+
+    pub fn dump<R: Read>(file: R, dialect: Dialect) -> Result<Self, SyntaxErr> {
+        let mut summary = Summary::default();
+        summary.read(io::BufReader::new(file), dialect, false)?;
+        Ok(summary)
+    }
+
+    /// Reads the chunks one by one, each has to hold up on its own.
+    pub fn chunks(files: &[PathBuf], dialect: Dialect) -> Result<Self, String> {
+        let mut summary = Summary::default();
+        for (index, path) in files.iter().enumerate() {
+            let file = File::open(path).map_err(|_| format!("Unable to read {}", path.display()))?;
+            summary.read(io::BufReader::new(file), dialect, index > 0)
+                .map_err(|e| format!("{}: {}", path.display(), e.text))?;
+        }
+        Ok(summary)
+    }
+
+    /// How the chunks differ from the dump, nothing when they are the same.
+    pub fn differences(&self, chunks: &Summary) -> Vec<String> {
+        let mut differences = vec![];
+        let position = self.statements.iter().zip(&chunks.statements).position(|(dump, chunk)| dump != chunk);
+        if let Some(index) = position {
+            differences.push(format!(
+                "statement {} differs: `{}` in the dump, `{}` in the chunks",
+                index + 1, excerpt(&self.statements[index]), excerpt(&chunks.statements[index]),
+            ));
+        }else if self.statements.len() != chunks.statements.len() {
+            differences.push(format!(
+                "{} in the dump, {} in the chunks",
+                plural(self.statements.len() as u64, "statement"), chunks.statements.len(),
+            ));
+        }
+
+        let tables = self.rows.keys().chain(chunks.rows.keys().filter(|table| !self.rows.contains_key(*table)));
+        for table in tables {
+            let dump = self.rows.get(table).cloned().unwrap_or_default();
+            let chunk = chunks.rows.get(table).cloned().unwrap_or_default();
+            if dump.0 != chunk.0 {
+                differences.push(format!("{}: {} in the dump, {} in the chunks", table, plural(dump.0, "row"), chunk.0));
+            }else if dump.1 != chunk.1 {
+                differences.push(format!("{}: {} in both, but not the same ones", table, plural(dump.0, "row")));
+            }
+        }
+        differences
+    }
+
+    pub fn statements(&self) -> usize {
+        self.statements.len()
+    }
+
+    /// What was checked, the inserts counted by their rows.
+    pub fn checked(&self, files: usize) -> String {
+        format!(
+            "{} other than inserts and {} in {}, same as the dump",
+            plural(self.statements.len() as u64, "statement"), plural(self.rows(), "row"), plural(files as u64, "file"),
+        )
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows.values().map(|(count, _)| count).sum()
+    }
+}
+
+
+#[cfg(test)]
+mod verify_test{
+    use super::*;
+    use std::fs;
+
+    fn chunks(name: &str, files: &[&str]) -> Vec<PathBuf> {
+        let directory = ::std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        files.iter().enumerate().map(|(index, content)| {
+            let path = directory.join(format!("{}.sql", index + 1));
+            fs::write(&path, content).unwrap();
+            path
+        }).collect()
+    }
+
+    #[test]
+    fn same_rows_in_chunks(){
+        let dump = "DELIMITER ;;\nCREATE TABLE t (a int);;\nINSERT INTO t VALUES (1),\n(2),\n(3);;\n";
+        let files = chunks("sql-split-verify-same", &[
+            "DELIMITER ;;\nCREATE TABLE t (a int);;\nINSERT INTO t VALUES (1),\n(2);",
+            "DELIMITER ;;\nINSERT INTO t VALUES \n(3);;\n",
+        ]);
+        let dump = Summary::dump(dump.as_bytes(), Dialect::Mysql).unwrap();
+        let split = Summary::chunks(&files, Dialect::Mysql).unwrap();
+        assert_eq!(dump.differences(&split), Vec::<String>::new());
+        assert_eq!((split.statements(), split.rows()), (1, 3));
+        assert_eq!(split.checked(2), "1 statement other than inserts and 3 rows in 2 files, same as the dump");
+    }
+
+    #[test]
+    fn truncated_and_missing(){
+        let dump = "CREATE TABLE t (a int);\nINSERT INTO t VALUES (1,'a'),(2,'b');\nCREATE TABLE u (a int);\n";
+        let files = chunks("sql-split-verify-differs", &[
+            "CREATE TABLE t (a int);\nINSERT INTO t VALUES (1,'a'),(2,'');\n",
+        ]);
+        let dump = Summary::dump(dump.as_bytes(), Dialect::Mysql).unwrap();
+        let split = Summary::chunks(&files, Dialect::Mysql).unwrap();
+        assert_eq!(dump.differences(&split), vec![
+            "2 statements in the dump, 1 in the chunks".to_string(),
+            "t: 2 rows in both, but not the same ones".to_string(),
+        ]);
+
+        let broken = chunks("sql-split-verify-broken", &["INSERT INTO t VALUES (1,'a'),(2,"]);
+        assert!(Summary::chunks(&broken, Dialect::Mysql).unwrap_err().contains("1.sql"));
+    }
+}